@@ -0,0 +1,258 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Lint configuration files, given with `--lint-config`.
+//!
+//! A lint configuration file is a TOML file with a `[lints]` table, which
+//! sets the levels of lints and lint groups for the whole crate, and
+//! `[lints."path::to::module"]` tables, which set them for a module and the
+//! modules in it:
+//!
+//! ```toml
+//! [lints]
+//! unused_imports = "deny"
+//! missing-docs = "warn"
+//!
+//! [lints."net::http"]
+//! dead_code = "allow"
+//! ```
+//!
+//! Module paths are relative to the crate root. The levels have the same
+//! precedence as levels set on the command line: they are overridden by lint
+//! attributes and by the `-A`/`-W`/`-D`/`-F` flags. A module's levels
+//! override the levels the file sets for the modules around it.
+//!
+//! Only the subset of TOML which such a file needs is understood: tables,
+//! bare and quoted keys, basic and literal strings, and comments.
+
+use lint::Level;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// The levels set by a lint configuration file.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct LintConfig {
+    /// The file the levels were read from.
+    pub path: PathBuf,
+    /// The levels for the whole crate, in the order of the file.
+    pub crate_levels: Vec<(String, Level)>,
+    /// The levels for modules, by module path, in the order of the file.
+    pub module_levels: Vec<(String, Vec<(String, Level)>)>,
+}
+
+impl LintConfig {
+    /// Read the lint configuration file at `path`.
+    pub fn from_file(path: &Path) -> Result<LintConfig, String> {
+        let mut src = String::new();
+        File::open(path).and_then(|mut file| file.read_to_string(&mut src)).map_err(|e| {
+            format!("couldn't read lint config file {}: {}", path.display(), e)
+        })?;
+        LintConfig::parse(path, &src)
+    }
+
+    /// Parse the contents `src` of the lint configuration file at `path`.
+    pub fn parse(path: &Path, src: &str) -> Result<LintConfig, String> {
+        let mut config = LintConfig {
+            path: path.to_path_buf(),
+            crate_levels: Vec::new(),
+            module_levels: Vec::new(),
+        };
+        // The module of the current table, if it is a `[lints]` table.
+        let mut table: Option<Option<String>> = None;
+        // The modules of the tables so far, which may be empty.
+        let mut seen_tables: Vec<Option<String>> = Vec::new();
+
+        for (i, line) in src.lines().enumerate() {
+            let error = |msg: &str| format!("{}:{}: {}", path.display(), i + 1, msg);
+            let mut rest = line.trim();
+            if rest.is_empty() || rest.starts_with('#') {
+                continue;
+            }
+
+            if rest.starts_with('[') {
+                rest = rest[1..].trim_left();
+                let (keys, tail) = parse_keys(rest, ']').map_err(|e| error(&e))?;
+                end_of_line(&tail[1..]).map_err(|e| error(&e))?;
+                let module = match keys.len() {
+                    1 if keys[0] == "lints" => None,
+                    2 if keys[0] == "lints" => Some(keys[1].clone()),
+                    _ => return Err(error(&format!("unknown table `[{}]`", keys.join(".")))),
+                };
+                if seen_tables.contains(&module) {
+                    return Err(error("table defined more than once"));
+                }
+                seen_tables.push(module.clone());
+                if let Some(ref module) = module {
+                    config.module_levels.push((module.clone(), Vec::new()));
+                }
+                table = Some(module);
+                continue;
+            }
+
+            let module = match table {
+                Some(ref module) => module,
+                None => return Err(error("expected a `[lints]` table")),
+            };
+            let (keys, tail) = parse_keys(rest, '=').map_err(|e| error(&e))?;
+            if keys.len() != 1 {
+                return Err(error(&format!("expected the name of a lint, found `{}`",
+                                          keys.join("."))));
+            }
+            let (value, tail) = parse_string(tail[1..].trim_left()).map_err(|e| error(&e))?;
+            end_of_line(tail).map_err(|e| error(&e))?;
+
+            let lint_name = keys[0].replace("-", "_");
+            let level = Level::from_str(&value).ok_or_else(|| {
+                error(&format!("unknown lint level `{}`", value))
+            })?;
+            let levels = config.levels_mut(module);
+            if levels.iter().any(|&(ref name, _)| *name == lint_name) {
+                return Err(error(&format!("level of `{}` set more than once", keys[0])));
+            }
+            levels.push((lint_name, level));
+        }
+
+        Ok(config)
+    }
+
+    /// The levels set for the module at `path`, not counting those set for
+    /// the modules around it.
+    pub fn levels_for_module(&self, path: &str) -> &[(String, Level)] {
+        self.module_levels
+            .iter()
+            .find(|&&(ref module, _)| module == path)
+            .map_or(&[][..], |&(_, ref levels)| &levels[..])
+    }
+
+    fn levels_mut(&mut self, module: &Option<String>) -> &mut Vec<(String, Level)> {
+        match *module {
+            None => &mut self.crate_levels,
+            Some(ref path) => {
+                &mut self.module_levels.iter_mut().find(|&&mut (ref m, _)| m == path).unwrap().1
+            }
+        }
+    }
+}
+
+// Parse dotted keys up to `end`, returning them and the rest of the line,
+// starting with `end`.
+fn parse_keys(mut s: &str, end: char) -> Result<(Vec<String>, &str), String> {
+    let mut keys = Vec::new();
+    loop {
+        let (key, rest) = if s.starts_with('"') || s.starts_with('\'') {
+            parse_string(s)?
+        } else {
+            let len = s.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+                       .unwrap_or(s.len());
+            if len == 0 {
+                return Err(format!("expected a key, found `{}`", s));
+            }
+            (s[..len].to_owned(), &s[len..])
+        };
+        keys.push(key);
+        s = rest.trim_left();
+        if s.starts_with('.') {
+            s = s[1..].trim_left();
+        } else if s.starts_with(end) {
+            return Ok((keys, s));
+        } else {
+            return Err(format!("expected `.` or `{}` after `{}`", end, keys.join(".")));
+        }
+    }
+}
+
+// Parse a basic ("...") or literal ('...') string at the start of `s`,
+// returning it and the rest of `s`.
+fn parse_string(s: &str) -> Result<(String, &str), String> {
+    let quote = match s.chars().next() {
+        Some(c @ '"') | Some(c @ '\'') => c,
+        _ => return Err(format!("expected a string, found `{}`", s)),
+    };
+    let mut value = String::new();
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((value, &s[i + 1..])),
+            '\\' if quote == '"' => {
+                match chars.next().map(|(_, c)| c) {
+                    Some('\\') => value.push('\\'),
+                    Some('"') => value.push('"'),
+                    Some(c) => return Err(format!("unsupported escape `\\{}`", c)),
+                    None => break,
+                }
+            }
+            c => value.push(c),
+        }
+    }
+    Err(format!("unterminated string `{}`", s))
+}
+
+fn end_of_line(s: &str) -> Result<(), String> {
+    let s = s.trim_left();
+    if s.is_empty() || s.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected `{}` at the end of the line", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LintConfig;
+    use lint::{Allow, Warn, Deny, Forbid};
+    use std::path::Path;
+
+    fn parse(src: &str) -> Result<LintConfig, String> {
+        LintConfig::parse(Path::new("lints.toml"), src)
+    }
+
+    #[test]
+    fn crate_and_module_levels() {
+        let config = parse(r#"
+# The policy of the whole organisation.
+[lints]
+unused_imports = "deny"
+missing-docs = 'warn'   # dashes work like on the command line
+
+[ lints . "net::http" ]
+dead_code = "allow"
+"warnings" = "forbid"
+"#).unwrap();
+        assert_eq!(config.crate_levels,
+                   vec![("unused_imports".to_owned(), Deny), ("missing_docs".to_owned(), Warn)]);
+        assert_eq!(config.levels_for_module("net::http"),
+                   &[("dead_code".to_owned(), Allow), ("warnings".to_owned(), Forbid)][..]);
+        assert!(config.levels_for_module("net").is_empty());
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        assert_eq!(parse("dead_code = \"allow\"").unwrap_err(),
+                   "lints.toml:1: expected a `[lints]` table");
+        assert_eq!(parse("[lints]\n\ndead_code = \"never\"").unwrap_err(),
+                   "lints.toml:3: unknown lint level `never`");
+        assert_eq!(parse("[lints]\na = \"warn\"\na = \"deny\"").unwrap_err(),
+                   "lints.toml:3: level of `a` set more than once");
+        assert_eq!(parse("[package]").unwrap_err(),
+                   "lints.toml:1: unknown table `[package]`");
+        assert_eq!(parse("[lints]\na = \"warn").unwrap_err(),
+                   "lints.toml:2: unterminated string `\"warn`");
+    }
+
+    #[test]
+    fn tables_defined_more_than_once() {
+        assert_eq!(parse("[lints]\n[lints.a]\n[lints]").unwrap_err(),
+                   "lints.toml:3: table defined more than once");
+        assert_eq!(parse("[lints.a]\nb = \"deny\"\n[lints.'a']").unwrap_err(),
+                   "lints.toml:3: table defined more than once");
+    }
+}
@@ -30,8 +30,8 @@ use middle::privacy::AccessLevels;
 use ty::{self, TyCtxt};
 use session::{config, early_error, Session};
 use lint::{Level, LevelSource, Lint, LintId, LintPass, LintSource};
-use lint::{EarlyLintPassObject, LateLintPassObject};
-use lint::{Default, CommandLine, Node, Allow, Warn, Deny, Forbid};
+use lint::{EarlyLintPassObject, LateLintPassObject, LintConfig};
+use lint::{Default, CommandLine, Node, Config, Allow, Warn, Deny, Forbid};
 use lint::builtin;
 use util::nodemap::FxHashMap;
 
@@ -40,6 +40,7 @@ use std::default::Default as StdDefault;
 use std::mem;
 use std::fmt;
 use syntax::attr;
+use syntax::symbol::Symbol;
 use syntax::ast;
 use syntax_pos::{MultiSpan, Span};
 use errors::{self, Diagnostic, DiagnosticBuilder};
@@ -120,6 +121,19 @@ impl IntoEarlyLint for Diagnostic {
     }
 }
 
+/// A future incompatibility lint which was triggered, whether or not it was
+/// emitted. These are reported at the end of the compilation, so that the
+/// ones hidden by `--cap-lints` are not lost.
+#[derive(Clone)]
+pub struct FutureIncompatibleHit {
+    pub lint: &'static Lint,
+    pub reference: &'static str,
+    pub message: String,
+    pub span: Option<Span>,
+    /// False if `--cap-lints` kept the lint from being emitted.
+    pub shown: bool,
+}
+
 /// Extra information for a future incompatibility lint. See the call
 /// to `register_future_incompatible` in `librustc_lint/lib.rs` for
 /// guidelines.
@@ -154,15 +168,13 @@ impl LintStore {
         }
     }
 
+    // Lints which are allowed are kept in `levels` too, so that the lint
+    // configuration file knows whether an attribute allowed them.
     fn set_level(&mut self, lint: LintId, mut lvlsrc: LevelSource) {
         if let Some(cap) = self.lint_cap {
             lvlsrc.0 = cmp::min(lvlsrc.0, cap);
         }
-        if lvlsrc.0 == Allow {
-            self.levels.remove(&lint);
-        } else {
-            self.levels.insert(lint, lvlsrc);
-        }
+        self.levels.insert(lint, lvlsrc);
     }
 
     pub fn new() -> LintStore {
@@ -293,32 +305,44 @@ impl LintStore {
         }
     }
 
+    // The lints called `lint_name`: a lint, the lints of a lint group, or
+    // none if there is no such lint or the lint was removed.
+    fn find_lints(&self, lint_name: &str, sess: &Session) -> Vec<LintId> {
+        match self.find_lint(lint_name, sess, None) {
+            Ok(lint_id) => vec![lint_id],
+            Err(FindLintError::Removed) => vec![],
+            Err(FindLintError::NotFound) => {
+                self.lint_groups.get(lint_name).map_or(vec![], |&(ref v, _)| v.clone())
+            }
+        }
+    }
+
     pub fn process_command_line(&mut self, sess: &Session) {
+        // The lint configuration file goes first, so that the flags override it.
+        if let Some(ref config) = sess.opts.lint_config {
+            for &(ref lint_name, level) in &config.crate_levels {
+                check_lint_name_config(sess, self, lint_name, None, config);
+                let source = Config(Symbol::intern(lint_name), level, None);
+                for lint_id in self.find_lints(lint_name, sess) {
+                    self.set_level(lint_id, (level, source));
+                }
+            }
+            for &(ref module, ref levels) in &config.module_levels {
+                for &(ref lint_name, _) in levels {
+                    check_lint_name_config(sess, self, lint_name, Some(module), config);
+                }
+            }
+        }
+
         for &(ref lint_name, level) in &sess.opts.lint_opts {
             check_lint_name_cmdline(sess, self,
                                     &lint_name[..], level);
 
-            match self.find_lint(&lint_name[..], sess, None) {
-                Ok(lint_id) => self.set_level(lint_id, (level, CommandLine)),
-                Err(FindLintError::Removed) => { }
-                Err(_) => {
-                    match self.lint_groups.iter().map(|(&x, pair)| (x, pair.0.clone()))
-                                                 .collect::<FxHashMap<&'static str,
-                                                                      Vec<LintId>>>()
-                                                 .get(&lint_name[..]) {
-                        Some(v) => {
-                            v.iter()
-                             .map(|lint_id: &LintId|
-                                     self.set_level(*lint_id, (level, CommandLine)))
-                             .collect::<Vec<()>>();
-                        }
-                        None => {
-                            // The lint or lint group doesn't exist.
-                            // This is an error, but it was handled
-                            // by check_lint_name_cmdline.
-                        }
-                    }
-                }
+            // If the lint or lint group doesn't exist, this is an error, but
+            // it was handled by check_lint_name_cmdline.
+            let source = CommandLine(Symbol::intern(lint_name), level);
+            for lint_id in self.find_lints(lint_name, sess) {
+                self.set_level(lint_id, (level, source));
             }
         }
 
@@ -352,6 +376,9 @@ pub struct LateContext<'a, 'tcx: 'a> {
     /// levels, this stack keeps track of the previous lint levels of whatever
     /// was modified.
     level_stack: Vec<(LintId, LevelSource)>,
+
+    /// The names of the modules around the current node, from the crate root.
+    module_path: Vec<ast::Name>,
 }
 
 /// Context for lint checking of the AST, after expansion, before lowering to
@@ -370,6 +397,9 @@ pub struct EarlyContext<'a> {
     /// levels, this stack keeps track of the previous lint levels of whatever
     /// was modified.
     level_stack: Vec<(LintId, LevelSource)>,
+
+    /// The names of the modules around the current node, from the crate root.
+    module_path: Vec<ast::Name>,
 }
 
 /// Convenience macro for calling a `LintPass` method on every pass in the context.
@@ -446,28 +476,49 @@ pub fn raw_struct_lint<'a, S>(sess: &'a Session,
     where S: Into<MultiSpan>
 {
     let (mut level, source) = lvlsrc;
+    let span: Option<MultiSpan> = span.map(|span| span.into());
+
+    if let Some(future_incompatible) = lints.future_incompatible(LintId::of(lint)) {
+        // The level before any `--cap-lints`.
+        let uncapped = match source {
+            Default => lint.default_level,
+            Node(_, source_level, _) |
+            CommandLine(_, source_level) |
+            Config(_, source_level, _) => source_level,
+        };
+        if uncapped != Allow {
+            let hit = FutureIncompatibleHit {
+                lint: lint,
+                reference: future_incompatible.reference,
+                message: msg.to_owned(),
+                span: span.as_ref().and_then(|span| span.primary_span()),
+                shown: level != Allow,
+            };
+            let mut hits = sess.future_incompatible_hits.borrow_mut();
+            let seen = hits.iter().any(|seen| {
+                seen.lint.name == lint.name && seen.span == hit.span && seen.message == msg
+            });
+            if !seen {
+                hits.push(hit);
+            }
+        }
+    }
+
     if level == Allow {
         return sess.diagnostic().struct_dummy();
     }
 
     let name = lint.name_lower();
-    let mut def = None;
     let msg = match source {
         Default => {
             format!("{}, #[{}({})] on by default", msg,
                     level.as_str(), name)
         },
-        CommandLine => {
-            format!("{} [-{} {}]", msg,
-                    match level {
-                        Warn => 'W', Deny => 'D', Forbid => 'F',
-                        Allow => bug!()
-                    }, name.replace("_", "-"))
+        CommandLine(flag, source_level) => {
+            format!("{} [-{} {}]", msg, flag_char(source_level),
+                    flag.as_str().replace("_", "-"))
         },
-        Node(src) => {
-            def = Some(src);
-            msg.to_string()
-        }
+        Node(..) | Config(..) => msg.to_string(),
     };
 
     // For purposes of printing, we can treat forbid as deny.
@@ -492,18 +543,63 @@ pub fn raw_struct_lint<'a, S>(sess: &'a Session,
         err.note(&citation);
     }
 
-    if let Some(span) = def {
-        sess.diag_span_note_once(&mut err, lint, span, "lint level defined here");
+    // Say where the level came from, and whether `--cap-lints` lowered it.
+    let source_level = match source {
+        Default => None,
+        Node(name, source_level, span) => {
+            let loc = sess.codemap().lookup_char_pos(span.lo);
+            let message = format!("`#[{}({})]` set at {}:{}",
+                                  source_level.as_str(), name, loc.file.name, loc.line);
+            sess.diag_span_note_once(&mut err, lint, span, &message);
+            Some(source_level)
+        }
+        CommandLine(name, source_level) => {
+            let message = format!("set by command-line flag `-{} {}`",
+                                  flag_char(source_level), name.as_str().replace("_", "-"));
+            sess.diag_note_once(&mut err, lint, &message);
+            Some(source_level)
+        }
+        Config(name, source_level, module) => {
+            let path = sess.opts.lint_config.as_ref().map(|config| config.path.display());
+            let path = path.map_or(String::new(), |path| path.to_string());
+            let message = match module {
+                Some(module) => {
+                    format!("`{} = \"{}\"` set for module `{}` in lint config file {}",
+                            name, source_level.as_str(), module, path)
+                }
+                None => {
+                    format!("`{} = \"{}\"` set in lint config file {}",
+                            name, source_level.as_str(), path)
+                }
+            };
+            sess.diag_note_once(&mut err, lint, &message);
+            Some(source_level)
+        }
+    };
+    if let (Some(source_level), Some(cap)) = (source_level, lints.lint_cap) {
+        if source_level > cap {
+            let message = format!("capped at `{}` by command-line flag `--cap-lints {}`",
+                                  cap.as_str(), cap.as_str());
+            sess.diag_note_once(&mut err, lint, &message);
+        }
     }
 
     err
 }
 
+// The letter of the command-line flag which sets `level`.
+fn flag_char(level: Level) -> char {
+    match level {
+        Allow => 'A', Warn => 'W', Deny => 'D', Forbid => 'F',
+    }
+}
+
 pub trait LintContext<'tcx>: Sized {
     fn sess(&self) -> &Session;
     fn lints(&self) -> &LintStore;
     fn mut_lints(&mut self) -> &mut LintStore;
     fn level_stack(&mut self) -> &mut Vec<(LintId, LevelSource)>;
+    fn module_path(&mut self) -> &mut Vec<ast::Name>;
     fn enter_attrs(&mut self, attrs: &'tcx [ast::Attribute]);
     fn exit_attrs(&mut self, attrs: &'tcx [ast::Attribute]);
 
@@ -628,12 +724,12 @@ pub trait LintContext<'tcx>: Sized {
                 }
                 Ok((lint_name, level, span)) => {
                     match self.lints().find_lint(&lint_name.as_str(), &self.sess(), Some(span)) {
-                        Ok(lint_id) => vec![(lint_id, level, span)],
+                        Ok(lint_id) => vec![(lint_id, lint_name, level, span)],
                         Err(FindLintError::NotFound) => {
                             match self.lints().lint_groups.get(&*lint_name.as_str()) {
                                 Some(&(ref v, _)) => v.iter()
                                                       .map(|lint_id: &LintId|
-                                                           (*lint_id, level, span))
+                                                           (*lint_id, lint_name, level, span))
                                                       .collect(),
                                 None => {
                                     // The lint or lint group doesn't exist.
@@ -648,30 +744,33 @@ pub trait LintContext<'tcx>: Sized {
                 }
             };
 
-            for (lint_id, level, span) in v {
+            for (lint_id, lint_name, level, span) in v {
                 let (now, now_source) = self.lints().get_level_source(lint_id);
                 if now == Forbid && level != Forbid {
-                    let lint_name = lint_id.to_string();
+                    let forbidden_name = lint_id.to_string();
                     let mut diag_builder = struct_span_err!(self.sess(), span, E0453,
                                                             "{}({}) overruled by outer forbid({})",
-                                                            level.as_str(), lint_name,
-                                                            lint_name);
+                                                            level.as_str(), forbidden_name,
+                                                            forbidden_name);
                     diag_builder.span_label(span, &format!("overruled by previous forbid"));
                     match now_source {
                         LintSource::Default => &mut diag_builder,
-                        LintSource::Node(forbid_source_span) => {
+                        LintSource::Node(_, _, forbid_source_span) => {
                             diag_builder.span_label(forbid_source_span,
                                                     &format!("`forbid` level set here"))
                         },
-                        LintSource::CommandLine => {
+                        LintSource::CommandLine(..) => {
                             diag_builder.note("`forbid` lint level was set on command line")
                         }
+                        LintSource::Config(..) => {
+                            diag_builder.note("`forbid` lint level was set in lint config file")
+                        }
                     }.emit()
                 } else if now != level {
                     let src = self.lints().get_level_source(lint_id).1;
                     self.level_stack().push((lint_id, (now, src)));
                     pushed += 1;
-                    self.mut_lints().set_level(lint_id, (level, Node(span)));
+                    self.mut_lints().set_level(lint_id, (level, Node(lint_name, level, span)));
                 }
             }
         }
@@ -686,6 +785,59 @@ pub trait LintContext<'tcx>: Sized {
             self.mut_lints().set_level(lint, lvlsrc);
         }
     }
+
+    /// If `module` is the name of a module inside the current one, merge the
+    /// lint levels which the lint configuration file sets for it into the
+    /// current lint context, call the provided function, then reset the
+    /// lints in effect to their previous state.
+    ///
+    /// Like the flags on the command line, the file does not override the
+    /// levels set by attributes or flags.
+    fn with_module_lint_config<F>(&mut self,
+                                  module: Option<ast::Name>,
+                                  f: F)
+        where F: FnOnce(&mut Self),
+    {
+        let module = match module {
+            Some(module) => module,
+            None => return f(self),
+        };
+        self.module_path().push(module);
+        let path = self.module_path()
+                       .iter()
+                       .map(|name| name.as_str().to_string())
+                       .collect::<Vec<_>>()
+                       .join("::");
+        let levels = self.sess().opts.lint_config.as_ref().map_or(vec![], |config| {
+            config.levels_for_module(&path).to_vec()
+        });
+
+        let mut pushed = 0;
+        for (lint_name, level) in levels {
+            let source = Config(Symbol::intern(&lint_name), level, Some(Symbol::intern(&path)));
+            for lint_id in self.lints().find_lints(&lint_name, self.sess()) {
+                let (now, now_source) = self.lints().get_level_source(lint_id);
+                match now_source {
+                    Default | Config(..) => {}
+                    Node(..) | CommandLine(..) => continue,
+                }
+                if now != level {
+                    self.level_stack().push((lint_id, (now, now_source)));
+                    pushed += 1;
+                    self.mut_lints().set_level(lint_id, (level, source));
+                }
+            }
+        }
+
+        f(self);
+
+        // rollback
+        for _ in 0..pushed {
+            let (lint, lvlsrc) = self.level_stack().pop().unwrap();
+            self.mut_lints().set_level(lint, lvlsrc);
+        }
+        self.module_path().pop();
+    }
 }
 
 
@@ -701,6 +853,7 @@ impl<'a> EarlyContext<'a> {
             krate: krate,
             lints: lint_store,
             level_stack: vec![],
+            module_path: vec![],
         }
     }
 }
@@ -734,6 +887,10 @@ impl<'a, 'tcx> LintContext<'tcx> for LateContext<'a, 'tcx> {
         &mut self.level_stack
     }
 
+    fn module_path(&mut self) -> &mut Vec<ast::Name> {
+        &mut self.module_path
+    }
+
     fn enter_attrs(&mut self, attrs: &'tcx [ast::Attribute]) {
         debug!("late context: enter_attrs({:?})", attrs);
         run_lints!(self, enter_lint_attrs, late_passes, attrs);
@@ -763,6 +920,10 @@ impl<'a> LintContext<'a> for EarlyContext<'a> {
         &mut self.level_stack
     }
 
+    fn module_path(&mut self) -> &mut Vec<ast::Name> {
+        &mut self.module_path
+    }
+
     fn enter_attrs(&mut self, attrs: &'a [ast::Attribute]) {
         debug!("early context: enter_attrs({:?})", attrs);
         run_lints!(self, enter_lint_attrs, early_passes, attrs);
@@ -791,11 +952,17 @@ impl<'a, 'tcx> hir_visit::Visitor<'tcx> for LateContext<'a, 'tcx> {
     }
 
     fn visit_item(&mut self, it: &'tcx hir::Item) {
-        self.with_lint_attrs(&it.attrs, |cx| {
-            run_lints!(cx, check_item, late_passes, it);
-            cx.visit_ids(|v| v.visit_item(it));
-            hir_visit::walk_item(cx, it);
-            run_lints!(cx, check_item_post, late_passes, it);
+        let module = match it.node {
+            hir::ItemMod(..) => Some(it.name),
+            _ => None,
+        };
+        self.with_module_lint_config(module, |cx| {
+            cx.with_lint_attrs(&it.attrs, |cx| {
+                run_lints!(cx, check_item, late_passes, it);
+                cx.visit_ids(|v| v.visit_item(it));
+                hir_visit::walk_item(cx, it);
+                run_lints!(cx, check_item_post, late_passes, it);
+            })
         })
     }
 
@@ -954,10 +1121,16 @@ impl<'a, 'tcx> hir_visit::Visitor<'tcx> for LateContext<'a, 'tcx> {
 
 impl<'a> ast_visit::Visitor<'a> for EarlyContext<'a> {
     fn visit_item(&mut self, it: &'a ast::Item) {
-        self.with_lint_attrs(&it.attrs, |cx| {
-            run_lints!(cx, check_item, early_passes, it);
-            ast_visit::walk_item(cx, it);
-            run_lints!(cx, check_item_post, early_passes, it);
+        let module = match it.node {
+            ast::ItemKind::Mod(..) => Some(it.ident.name),
+            _ => None,
+        };
+        self.with_module_lint_config(module, |cx| {
+            cx.with_lint_attrs(&it.attrs, |cx| {
+                run_lints!(cx, check_item, early_passes, it);
+                ast_visit::walk_item(cx, it);
+                run_lints!(cx, check_item_post, early_passes, it);
+            })
         })
     }
 
@@ -1205,6 +1378,31 @@ fn check_lint_name_attribute(cx: &LateContext, attr: &ast::Attribute) {
 // Checks the validity of lint names derived from the command line
 fn check_lint_name_cmdline(sess: &Session, lint_cx: &LintStore,
                            lint_name: &str, level: Level) {
+    let msg = format!("requested on the command line with `{} {}`",
+                      match level {
+                          Level::Allow => "-A",
+                          Level::Warn => "-W",
+                          Level::Deny => "-D",
+                          Level::Forbid => "-F",
+                      },
+                      lint_name);
+    check_lint_name_requested(sess, lint_cx, lint_name, &msg);
+}
+
+fn check_lint_name_config(sess: &Session, lint_cx: &LintStore,
+                          lint_name: &str, module: Option<&String>, config: &LintConfig) {
+    let msg = match module {
+        Some(module) => format!("requested for module `{}` in lint config file {}",
+                                module, config.path.display()),
+        None => format!("requested in lint config file {}", config.path.display()),
+    };
+    check_lint_name_requested(sess, lint_cx, lint_name, &msg);
+}
+
+// Report an unknown or renamed lint, with a note `requested` saying where
+// its name came from.
+fn check_lint_name_requested(sess: &Session, lint_cx: &LintStore,
+                             lint_name: &str, requested: &str) {
     let db = match check_lint_name(lint_cx, lint_name) {
         CheckLintNameResult::Ok => None,
         CheckLintNameResult::Warning(ref msg) => {
@@ -1216,15 +1414,7 @@ fn check_lint_name_cmdline(sess: &Session, lint_cx: &LintStore,
     };
 
     if let Some(mut db) = db {
-        let msg = format!("requested on the command line with `{} {}`",
-                          match level {
-                              Level::Allow => "-A",
-                              Level::Warn => "-W",
-                              Level::Deny => "-D",
-                              Level::Forbid => "-F",
-                          },
-                          lint_name);
-        db.note(&msg);
+        db.note(requested);
         db.emit();
     }
 }
@@ -1248,6 +1438,7 @@ pub fn check_crate<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
         access_levels: access_levels,
         lints: lint_store,
         level_stack: vec![],
+        module_path: vec![],
     };
 
     // Visit the whole crate.
@@ -41,7 +41,9 @@ use hir;
 
 pub use lint::context::{LateContext, EarlyContext, LintContext, LintStore,
                         raw_emit_lint, check_crate, check_ast_crate, gather_attrs,
-                        raw_struct_lint, FutureIncompatibleInfo, EarlyLint, IntoEarlyLint};
+                        raw_struct_lint, FutureIncompatibleInfo, FutureIncompatibleHit,
+                        EarlyLint, IntoEarlyLint};
+pub use lint::config_file::LintConfig;
 
 /// Specification of a single lint.
 #[derive(Copy, Clone, Debug)]
@@ -335,14 +337,22 @@ pub enum LintSource {
     /// in rustc or a plugin.
     Default,
 
-    /// Lint level was set by an attribute.
-    Node(Span),
+    /// Lint level was set by an attribute for the named lint or lint group.
+    /// The level is the one in the attribute, before any `--cap-lints`.
+    Node(ast::Name, Level, Span),
 
-    /// Lint level was set by a command-line flag.
-    CommandLine,
+    /// Lint level was set by a command-line flag for the named lint or lint
+    /// group. The level is the one of the flag, before any `--cap-lints`.
+    CommandLine(ast::Name, Level),
+
+    /// Lint level was set by the lint configuration file for the named lint
+    /// or lint group, for the whole crate or for the module with the given
+    /// path. The level is the one in the file, before any `--cap-lints`.
+    Config(ast::Name, Level, Option<ast::Name>),
 }
 
 pub type LevelSource = (Level, LintSource);
 
 pub mod builtin;
+mod config_file;
 mod context;
@@ -167,11 +167,31 @@ impl<'a, 'tcx> Passes {
     }
 
     pub fn run_passes(&mut self, tcx: TyCtxt<'a, 'tcx, 'tcx>) {
+        self.run_passes_and_then(tcx, |_, _| true);
+    }
+
+    /// Runs the passes like `run_passes`, calling `after_pass` with the name of
+    /// each pass (and its disambiguator, if any) once it has run over the whole
+    /// MIR map. If `after_pass` returns false, the remaining passes are skipped
+    /// and false is returned.
+    pub fn run_passes_and_then<F>(&mut self, tcx: TyCtxt<'a, 'tcx, 'tcx>, mut after_pass: F)
+                                  -> bool
+        where F: FnMut(TyCtxt<'a, 'tcx, 'tcx>, &str) -> bool
+    {
         let Passes { ref mut passes, ref mut plugin_passes, ref mut pass_hooks } = *self;
         for pass in plugin_passes.iter_mut().chain(passes.iter_mut()) {
             time(tcx.sess.time_passes(), &*pass.name(),
                  || pass.run_pass(tcx, pass_hooks));
+
+            let name = match pass.disambiguator() {
+                Some(disambiguator) => format!("{}-{}", pass.name(), disambiguator),
+                None => pass.name().into_owned(),
+            };
+            if !after_pass(tcx, &name) {
+                return false;
+            }
         }
+        true
     }
 
     /// Pushes a built-in pass.
@@ -42,7 +42,7 @@ use std::hash::Hasher;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
 use std::iter::FromIterator;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub struct Config {
     pub target: Target,
@@ -120,7 +120,9 @@ impl OutputType {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ErrorOutputType {
     HumanReadable(ColorConfig),
+    Short(ColorConfig),
     Json,
+    Sarif,
 }
 
 impl Default for ErrorOutputType {
@@ -273,6 +275,8 @@ top_level_options!(
         debuginfo: DebugInfoLevel [TRACKED],
         lint_opts: Vec<(String, lint::Level)> [TRACKED],
         lint_cap: Option<lint::Level> [TRACKED],
+        // the lint levels read from the file given with `--lint-config`
+        lint_config: Option<lint::LintConfig> [TRACKED],
         describe_lints: bool [UNTRACKED],
         output_types: OutputTypes [TRACKED],
         // FIXME(mw): We track this for now but it actually doesn't make too
@@ -286,6 +290,11 @@ top_level_options!(
 
         test: bool [TRACKED],
         error_format: ErrorOutputType [UNTRACKED],
+        // if Some, the suggestions made by the diagnostics of the compilation
+        // are applied to its source files, or printed as a diff
+        apply_suggestions: Option<ApplySuggestions> [UNTRACKED],
+        // if Some, compilation is aborted once this many errors were reported
+        error_limit: Option<usize> [UNTRACKED],
 
         // if Some, enable incremental compilation, using the given
         // directory to store intermediate results
@@ -313,6 +322,15 @@ top_level_options!(
     }
 );
 
+/// What `--apply-suggestions` does with the suggested edits.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ApplySuggestions {
+    /// Rewrite the source files in place.
+    Write,
+    /// Print the edits as a unified diff.
+    Diff,
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub enum PrintRequest {
     FileNames,
@@ -445,6 +463,7 @@ pub fn basic_options() -> Options {
         debuginfo: NoDebugInfo,
         lint_opts: Vec::new(),
         lint_cap: None,
+        lint_config: None,
         describe_lints: false,
         output_types: OutputTypes(BTreeMap::new()),
         search_paths: SearchPaths::new(),
@@ -456,6 +475,8 @@ pub fn basic_options() -> Options {
         prints: Vec::new(),
         cg: basic_codegen_options(),
         error_format: ErrorOutputType::default(),
+        apply_suggestions: None,
+        error_limit: None,
         externs: Externs(BTreeMap::new()),
         crate_name: None,
         alt_std_name: None,
@@ -851,6 +872,8 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
         "skip LLVM verification"),
     borrowck_stats: bool = (false, parse_bool, [UNTRACKED],
         "gather borrowck statistics"),
+    borrowck_mir: bool = (false, parse_bool, [UNTRACKED],
+        "check borrows for conflicts on MIR instead of the AST"),
     no_landing_pads: bool = (false, parse_bool, [TRACKED],
         "omit landing pads for unwinding"),
     debug_llvm: bool = (false, parse_bool, [UNTRACKED],
@@ -896,6 +919,10 @@ options! {DebuggingOptions, DebuggingSetter, basic_debugging_options,
           "treat all errors that occur as bugs"),
     continue_parse_after_error: bool = (false, parse_bool, [TRACKED],
           "attempt to recover from parse errors (experimental)"),
+    diagnostics_per_code: Option<usize> = (None, parse_opt_uint, [UNTRACKED],
+          "show at most this many errors and warnings with the same code"),
+    diagnostics_per_span: Option<usize> = (None, parse_opt_uint, [UNTRACKED],
+          "show at most this many errors and warnings at the same location"),
     incremental: Option<String> = (None, parse_opt_string, [UNTRACKED],
           "enable incremental compilation (experimental)"),
     incremental_info: bool = (false, parse_bool, [UNTRACKED],
@@ -1191,6 +1218,8 @@ pub fn rustc_short_optgroups() -> Vec<RustcOptGroup> {
                                 in <dir>", "DIR"),
         opt::opt_s("", "explain", "Provide a detailed explanation of an error \
                                message", "OPT"),
+        opt::opt("", "explain-search", "Search the extended descriptions of error codes \
+                                       for a keyword", "KEYWORD"),
         opt::flag_s("", "test", "Build a test harness"),
         opt::opt_s("", "target", "Target triple for which the code is compiled", "TARGET"),
         opt::multi_s("W", "warn", "Set lint warnings", "OPT"),
@@ -1218,7 +1247,14 @@ pub fn rustc_optgroups() -> Vec<RustcOptGroup> {
         opt::multi_ubnr("Z", "", "Set internal debugging options", "FLAG"),
         opt::opt_s("", "error-format",
                       "How errors and other messages are produced",
-                      "human|json"),
+                      "human|short|json|sarif"),
+        opt::opt("", "apply-suggestions",
+                 "Apply the machine-applicable suggestions of the compiler's diagnostics;
+                  `write` rewrites the source files in place, `diff` prints a unified diff",
+                 "write|diff"),
+        opt::opt("", "error-limit", "Abort compilation after this many errors", "N"),
+        opt::opt("", "lint-config", "Read the levels of lints and lint groups, for the \
+                                     crate or for modules, from a TOML file", "FILE"),
         opt::opt_s("", "color", "Configure coloring of output:
                                  auto   = colorize, if output goes to a tty (default);
                                  always = always colorize output;
@@ -1294,14 +1330,30 @@ pub fn build_session_options_and_crate_config(matches: &getopts::Matches)
     let error_format = if matches.opts_present(&["error-format".to_owned()]) {
         match matches.opt_str("error-format").as_ref().map(|s| &s[..]) {
             Some("human")   => ErrorOutputType::HumanReadable(color),
+            Some("short") => {
+                if !nightly_options::is_unstable_enabled(matches) {
+                    early_error(ErrorOutputType::HumanReadable(color),
+                                "the `-Z unstable-options` flag must also be passed to enable \
+                                 `--error-format short`");
+                }
+                ErrorOutputType::Short(color)
+            }
             Some("json") => ErrorOutputType::Json,
+            Some("sarif") => {
+                if !nightly_options::is_unstable_enabled(matches) {
+                    early_error(ErrorOutputType::HumanReadable(color),
+                                "the `-Z unstable-options` flag must also be passed to enable \
+                                 `--error-format sarif`");
+                }
+                ErrorOutputType::Sarif
+            }
 
             None => ErrorOutputType::HumanReadable(color),
 
             Some(arg) => {
                 early_error(ErrorOutputType::HumanReadable(color),
-                            &format!("argument for --error-format must be human or json (instead \
-                                      was `{}`)",
+                            &format!("argument for --error-format must be human, short, json or \
+                                      sarif (instead was `{}`)",
                                      arg))
             }
         }
@@ -1309,6 +1361,38 @@ pub fn build_session_options_and_crate_config(matches: &getopts::Matches)
         ErrorOutputType::HumanReadable(color)
     };
 
+    let apply_suggestions = if matches.opts_present(&["apply-suggestions".to_owned()]) {
+        match matches.opt_str("apply-suggestions").as_ref().map(|s| &s[..]) {
+            Some("write") => Some(ApplySuggestions::Write),
+            Some("diff") => Some(ApplySuggestions::Diff),
+            None => None,
+            Some(arg) => {
+                early_error(error_format,
+                            &format!("argument for --apply-suggestions must be write or diff \
+                                      (instead was `{}`)",
+                                     arg))
+            }
+        }
+    } else {
+        None
+    };
+
+    let error_limit = if matches.opts_present(&["error-limit".to_owned()]) {
+        matches.opt_str("error-limit").map(|limit| {
+            match limit.parse::<usize>() {
+                Ok(limit) if limit > 0 => limit,
+                _ => {
+                    early_error(error_format,
+                                &format!("argument for --error-limit must be a positive \
+                                          integer (instead was `{}`)",
+                                         limit))
+                }
+            }
+        })
+    } else {
+        None
+    };
+
     let unparsed_crate_types = matches.opt_strs("crate-type");
     let (crate_types, emit_metadata) = parse_crate_types_from_list(unparsed_crate_types)
         .unwrap_or_else(|e| early_error(error_format, &e[..]));
@@ -1332,6 +1416,16 @@ pub fn build_session_options_and_crate_config(matches: &getopts::Matches)
         })
     });
 
+    let lint_config = if matches.opts_present(&["lint-config".to_owned()]) {
+        matches.opt_str("lint-config").map(|path| {
+            lint::LintConfig::from_file(Path::new(&path)).unwrap_or_else(|e| {
+                early_error(error_format, &e)
+            })
+        })
+    } else {
+        None
+    };
+
     let debugging_opts = build_debugging_options(matches, error_format);
 
     let mut output_types = BTreeMap::new();
@@ -1539,6 +1633,7 @@ pub fn build_session_options_and_crate_config(matches: &getopts::Matches)
         debuginfo: debuginfo,
         lint_opts: lint_opts,
         lint_cap: lint_cap,
+        lint_config: lint_config,
         describe_lints: describe_lints,
         output_types: OutputTypes(output_types),
         search_paths: search_paths,
@@ -1550,6 +1645,8 @@ pub fn build_session_options_and_crate_config(matches: &getopts::Matches)
         prints: prints,
         cg: cg,
         error_format: error_format,
+        apply_suggestions: apply_suggestions,
+        error_limit: error_limit,
         externs: Externs(externs),
         crate_name: crate_name,
         alt_std_name: None,
@@ -1740,6 +1837,7 @@ mod dep_tracking {
     impl_dep_tracking_hash_via_hash!(Option<String>);
     impl_dep_tracking_hash_via_hash!(Option<PanicStrategy>);
     impl_dep_tracking_hash_via_hash!(Option<lint::Level>);
+    impl_dep_tracking_hash_via_hash!(Option<lint::LintConfig>);
     impl_dep_tracking_hash_via_hash!(Option<PathBuf>);
     impl_dep_tracking_hash_via_hash!(CrateType);
     impl_dep_tracking_hash_via_hash!(PanicStrategy);
@@ -1818,7 +1916,7 @@ mod tests {
     use session::build_session;
     use std::collections::{BTreeMap, BTreeSet};
     use std::iter::FromIterator;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use std::rc::Rc;
     use super::{OutputType, OutputTypes, Externs};
     use rustc_back::PanicStrategy;
@@ -2085,6 +2183,24 @@ mod tests {
         assert_eq!(v2.dep_tracking_hash(), v2.clone().dep_tracking_hash());
     }
 
+    #[test]
+    fn test_lint_config_tracking_hash_different_values() {
+        let v1 = super::basic_options();
+        let mut v2 = super::basic_options();
+        let mut v3 = super::basic_options();
+
+        let path = Path::new("lints.toml");
+        v2.lint_config = Some(lint::LintConfig::parse(path, "[lints]\na = \"deny\"").unwrap());
+        v3.lint_config = Some(lint::LintConfig::parse(path, "[lints.m]\na = \"deny\"").unwrap());
+
+        assert!(v1.dep_tracking_hash() != v2.dep_tracking_hash());
+        assert!(v1.dep_tracking_hash() != v3.dep_tracking_hash());
+        assert!(v2.dep_tracking_hash() != v3.dep_tracking_hash());
+
+        // Check clone
+        assert_eq!(v2.dep_tracking_hash(), v2.clone().dep_tracking_hash());
+    }
+
     #[test]
     fn test_search_paths_tracking_hash_different_values() {
         let mut v1 = super::basic_options();
@@ -2395,6 +2511,8 @@ mod tests {
         assert_eq!(reference.dep_tracking_hash(), opts.dep_tracking_hash());
         opts.debugging_opts.borrowck_stats = true;
         assert_eq!(reference.dep_tracking_hash(), opts.dep_tracking_hash());
+        opts.debugging_opts.borrowck_mir = true;
+        assert_eq!(reference.dep_tracking_hash(), opts.dep_tracking_hash());
         opts.debugging_opts.debug_llvm = true;
         assert_eq!(reference.dep_tracking_hash(), opts.dep_tracking_hash());
         opts.debugging_opts.meta_stats = true;
@@ -91,6 +91,8 @@ struct CheckLoanCtxt<'a, 'tcx: 'a> {
     move_data: &'a move_data::FlowedMoveData<'a, 'tcx>,
     all_loans: &'a [Loan<'tcx>],
     param_env: &'a ty::ParameterEnvironment<'tcx>,
+    /// False if conflicts between loans are left to the MIR borrow checker.
+    check_conflicts: bool,
 }
 
 impl<'a, 'tcx> euv::Delegate<'tcx> for CheckLoanCtxt<'a, 'tcx> {
@@ -143,7 +145,9 @@ impl<'a, 'tcx> euv::Delegate<'tcx> for CheckLoanCtxt<'a, 'tcx> {
             self.check_if_path_is_moved(borrow_id, borrow_span, moved_value_use_kind, &lp);
         }
 
-        self.check_for_conflicting_loans(borrow_id);
+        if self.check_conflicts {
+            self.check_for_conflicting_loans(borrow_id);
+        }
     }
 
     fn mutate(&mut self,
@@ -188,7 +192,8 @@ pub fn check_loans<'a, 'b, 'c, 'tcx>(bccx: &BorrowckCtxt<'a, 'tcx>,
                                      dfcx_loans: &LoanDataFlow<'b, 'tcx>,
                                      move_data: &move_data::FlowedMoveData<'c, 'tcx>,
                                      all_loans: &[Loan<'tcx>],
-                                     body: &hir::Body) {
+                                     body: &hir::Body,
+                                     check_conflicts: bool) {
     debug!("check_loans(body id={})", body.value.id);
 
     let infcx = bccx.tcx.borrowck_fake_infer_ctxt(body.id());
@@ -197,7 +202,8 @@ pub fn check_loans<'a, 'b, 'c, 'tcx>(bccx: &BorrowckCtxt<'a, 'tcx>,
         dfcx_loans: dfcx_loans,
         move_data: move_data,
         all_loans: all_loans,
-        param_env: &infcx.parameter_environment
+        param_env: &infcx.parameter_environment,
+        check_conflicts: check_conflicts,
     };
     euv::ExprUseVisitor::new(&mut clcx, &infcx).consume_body(body);
 }
@@ -594,7 +600,9 @@ impl<'a, 'tcx> CheckLoanCtxt<'a, 'tcx> {
         if let Some(lp) = opt_loan_path(&cmt) {
             let moved_value_use_kind = match mode {
                 euv::Copy => {
-                    self.check_for_copy_of_frozen_path(id, span, &lp);
+                    if self.check_conflicts {
+                        self.check_for_copy_of_frozen_path(id, span, &lp);
+                    }
                     MovedInUse
                 }
                 euv::Move(_) => {
@@ -608,8 +616,10 @@ impl<'a, 'tcx> CheckLoanCtxt<'a, 'tcx> {
                             MovedInUse
                         }
                         Some(move_kind) => {
-                            self.check_for_move_of_borrowed_path(id, span,
-                                                                 &lp, move_kind);
+                            if self.check_conflicts {
+                                self.check_for_move_of_borrowed_path(id, span,
+                                                                     &lp, move_kind);
+                            }
                             if move_kind == move_data::Captured {
                                 MovedInCapture
                             } else {
@@ -824,11 +834,13 @@ impl<'a, 'tcx> CheckLoanCtxt<'a, 'tcx> {
 
         // Check that we don't invalidate any outstanding loans
         if let Some(loan_path) = opt_loan_path(&assignee_cmt) {
-            let scope = self.tcx().region_maps.node_extent(assignment_id);
-            self.each_in_scope_loan_affecting_path(scope, &loan_path, |loan| {
-                self.report_illegal_mutation(assignment_span, &loan_path, loan);
-                false
-            });
+            if self.check_conflicts {
+                let scope = self.tcx().region_maps.node_extent(assignment_id);
+                self.each_in_scope_loan_affecting_path(scope, &loan_path, |loan| {
+                    self.report_illegal_mutation(assignment_span, &loan_path, loan);
+                    false
+                });
+            }
         }
 
         // Check for reassignments to (immutable) local variables. This
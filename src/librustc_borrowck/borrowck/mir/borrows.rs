@@ -0,0 +1,366 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The borrows made by the MIR of a function, and the `Borrows` analysis
+//! of the borrows which reach each point of it.
+//!
+//! Rather than for the lexical scope of its region, a borrow is taken to
+//! be in effect for as long as the reference it makes may still be used:
+//! wherever one of its *carriers*, the locals which may hold the reference
+//! or a reference derived from it, is live. A reference which may have
+//! been stored anywhere but in a local *escapes*, and its borrow is in
+//! effect wherever it reaches.
+
+use rustc::mir::{BasicBlock, BorrowKind, Local, Location, Lvalue, Mir};
+use rustc::mir::{ProjectionElem, Rvalue, StatementKind, TerminatorKind};
+use rustc::mir::visit::{LvalueContext, Visitor};
+use rustc::ty::TyCtxt;
+use rustc::ty::fold::TypeFoldable;
+use rustc::util::nodemap::FxHashMap;
+use rustc_data_structures::bitslice::BitwiseOperator;
+use rustc_data_structures::bitvec::BitVector;
+use rustc_data_structures::indexed_set::IdxSet;
+use rustc_data_structures::indexed_vec::{Idx, IndexVec};
+use rustc_mir::dataflow::{BitDenotation, BlockSets, DataflowOperator};
+use syntax_pos::Span;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct BorrowIndex(usize);
+
+impl Idx for BorrowIndex {
+    fn new(idx: usize) -> Self { BorrowIndex(idx) }
+    fn index(self) -> usize { self.0 }
+}
+
+pub struct BorrowData<'tcx> {
+    /// The statement which makes the borrow.
+    pub location: Location,
+    pub kind: BorrowKind,
+    pub lvalue: Lvalue<'tcx>,
+    pub span: Span,
+    /// The locals which may hold the reference, by index.
+    pub carriers: BitVector,
+    pub escapes: bool,
+}
+
+impl<'tcx> BorrowData<'tcx> {
+    /// Whether the borrow is in effect at a point after which the locals
+    /// in `live` are live, if it reaches that point.
+    pub fn is_in_effect(&self, live: &IdxSet<Local>) -> bool {
+        self.escapes || self.carriers.iter().any(|i| live.contains(&Local::new(i)))
+    }
+
+    fn sole_carrier(&self) -> Option<Local> {
+        let mut carriers = self.carriers.iter();
+        match (self.escapes, carriers.next(), carriers.next()) {
+            (false, Some(i), None) => Some(Local::new(i)),
+            _ => None,
+        }
+    }
+}
+
+/// `Borrows` tracks the borrows which may have been made on some path to
+/// a point, and whose reference may not have been overwritten since.
+///
+/// A borrow ends when its only carrier is overwritten with a value that
+/// is not derived from it. Whether a borrow which reaches a point is in
+/// effect there also depends on the liveness of its carriers.
+pub struct Borrows<'a, 'tcx: 'a> {
+    mir: &'a Mir<'tcx>,
+    borrows: IndexVec<BorrowIndex, BorrowData<'tcx>>,
+    location_map: FxHashMap<Location, BorrowIndex>,
+}
+
+impl<'a, 'tcx> Borrows<'a, 'tcx> {
+    pub fn new(tcx: TyCtxt<'a, 'tcx, 'tcx>, mir: &'a Mir<'tcx>) -> Self {
+        let mut borrows = IndexVec::new();
+        let mut location_map = FxHashMap();
+        for (bb, data) in mir.basic_blocks().iter_enumerated() {
+            for (i, stmt) in data.statements.iter().enumerate() {
+                if let StatementKind::Assign(_, Rvalue::Ref(_, kind, ref lvalue)) = stmt.kind {
+                    let location = Location { block: bb, statement_index: i };
+                    let index = borrows.push(BorrowData {
+                        location: location,
+                        kind: kind,
+                        lvalue: lvalue.clone(),
+                        span: stmt.source_info.span,
+                        carriers: BitVector::new(mir.local_decls.len()),
+                        escapes: false,
+                    });
+                    location_map.insert(location, index);
+                }
+            }
+        }
+
+        Carriers { tcx: tcx, mir: mir, borrows: &mut borrows }.find();
+
+        Borrows { mir: mir, borrows: borrows, location_map: location_map }
+    }
+
+    pub fn borrows(&self) -> &IndexVec<BorrowIndex, BorrowData<'tcx>> { &self.borrows }
+}
+
+impl<'a, 'tcx> BitDenotation for Borrows<'a, 'tcx> {
+    type Idx = BorrowIndex;
+    fn name() -> &'static str { "borrows" }
+    fn bits_per_block(&self) -> usize {
+        self.borrows.len()
+    }
+
+    fn start_block_effect(&self, _sets: &mut BlockSets<BorrowIndex>) {
+        // no borrows have been made on entry to the function
+    }
+
+    fn statement_effect(&self,
+                        sets: &mut BlockSets<BorrowIndex>,
+                        bb: BasicBlock,
+                        idx: usize)
+    {
+        let location = Location { block: bb, statement_index: idx };
+        if let StatementKind::Assign(Lvalue::Local(local), ref rvalue) =
+            self.mir[bb].statements[idx].kind
+        {
+            let mut read = LocalsRead(vec![]);
+            read.visit_rvalue(rvalue, location);
+            if !read.0.contains(&local) {
+                for (index, borrow) in self.borrows.iter_enumerated() {
+                    if borrow.sole_carrier() == Some(local) {
+                        sets.kill(&index);
+                    }
+                }
+            }
+        }
+        if let Some(index) = self.location_map.get(&location) {
+            sets.gen(index);
+        }
+    }
+
+    fn terminator_effect(&self,
+                         _sets: &mut BlockSets<BorrowIndex>,
+                         _bb: BasicBlock,
+                         _idx: usize)
+    {
+        // terminators make no borrows, and the destination of a call
+        // is only assigned when the call returns
+    }
+
+    fn propagate_call_return(&self,
+                             _in_out: &mut IdxSet<BorrowIndex>,
+                             _call_bb: BasicBlock,
+                             _dest_bb: BasicBlock,
+                             _dest_lval: &Lvalue) {
+        // the destination may hold any borrow passed to the call, so
+        // no borrow ends here
+    }
+}
+
+impl<'a, 'tcx> BitwiseOperator for Borrows<'a, 'tcx> {
+    #[inline]
+    fn join(&self, pred1: usize, pred2: usize) -> usize {
+        pred1 | pred2 // a borrow reaches a point if it does along any path
+    }
+}
+
+impl<'a, 'tcx> DataflowOperator for Borrows<'a, 'tcx> {
+    #[inline]
+    fn bottom_value() -> bool {
+        false // no borrows reach a point unless they are made before it
+    }
+}
+
+// Finds the carriers of the borrows by following their references from
+// local to local until nothing changes.
+struct Carriers<'b, 'a: 'b, 'tcx: 'a> {
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    mir: &'a Mir<'tcx>,
+    borrows: &'b mut IndexVec<BorrowIndex, BorrowData<'tcx>>,
+}
+
+impl<'b, 'a, 'tcx> Carriers<'b, 'a, 'tcx> {
+    fn find(&mut self) {
+        let mir = self.mir;
+        for index in self.borrows.indices() {
+            let location = self.borrows[index].location;
+            let stmt = &mir[location.block].statements[location.statement_index];
+            if let StatementKind::Assign(ref dest, _) = stmt.kind {
+                self.flow_into(index, dest);
+            }
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (bb, data) in mir.basic_blocks().iter_enumerated() {
+                for (i, stmt) in data.statements.iter().enumerate() {
+                    if let StatementKind::Assign(ref lvalue, ref rvalue) = stmt.kind {
+                        let mut read = LocalsRead(vec![]);
+                        read.visit_rvalue(rvalue, Location { block: bb, statement_index: i });
+                        changed |= match *rvalue {
+                            // assembly can store what it is given anywhere
+                            Rvalue::InlineAsm { .. } => self.escape_from(&read.0),
+                            _ => self.flow_from(&read.0, lvalue, None),
+                        };
+                    }
+                }
+                changed |= self.flow_through_terminator(bb);
+            }
+        }
+    }
+
+    fn flow_through_terminator(&mut self, bb: BasicBlock) -> bool {
+        let mir = self.mir;
+        let location = Location { block: bb, statement_index: mir[bb].statements.len() };
+        let mut read = LocalsRead(vec![]);
+        match mir[bb].terminator().kind {
+            TerminatorKind::Call { ref func, ref args, ref destination, .. } => {
+                read.visit_operand(func, location);
+                for arg in args {
+                    read.visit_operand(arg, location);
+                }
+                let mut changed = false;
+                if let Some((ref dest, _)) = *destination {
+                    changed |= self.flow_from(&read.0, dest, None);
+                }
+                // The callee can also store the references it is passed
+                // in whatever the other borrows it is passed are of.
+                for index in self.borrows.indices() {
+                    if self.is_carried_by(index, &read.0) {
+                        let lvalue = self.borrows[index].lvalue.clone();
+                        changed |= self.flow_from(&read.0, &lvalue, Some(index));
+                    }
+                }
+                changed
+            }
+            TerminatorKind::DropAndReplace { location: ref dest, ref value, .. } => {
+                read.visit_operand(value, location);
+                self.flow_from(&read.0, dest, None)
+            }
+            _ => false,
+        }
+    }
+
+    fn is_carried_by(&self, index: BorrowIndex, locals: &[Local]) -> bool {
+        let borrow = &self.borrows[index];
+        !borrow.escapes && locals.iter().any(|local| borrow.carriers.contains(local.index()))
+    }
+
+    // Records that `lvalue` may be assigned a value derived from the
+    // locals `read`, and so hold the references they carry.
+    fn flow_from(&mut self,
+                 read: &[Local],
+                 lvalue: &Lvalue<'tcx>,
+                 except: Option<BorrowIndex>)
+                 -> bool
+    {
+        let mut changed = false;
+        for index in self.borrows.indices() {
+            if Some(index) != except && self.is_carried_by(index, read) {
+                changed |= self.flow_into(index, lvalue);
+            }
+        }
+        changed
+    }
+
+    fn escape_from(&mut self, read: &[Local]) -> bool {
+        let mut changed = false;
+        for index in self.borrows.indices() {
+            if self.is_carried_by(index, read) {
+                self.borrows[index].escapes = true;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    // Records that `lvalue` may hold the reference of the borrow `index`,
+    // returning whether that is new.
+    fn flow_into(&mut self, index: BorrowIndex, lvalue: &Lvalue<'tcx>) -> bool {
+        let ty = lvalue.ty(self.mir, self.tcx).to_ty(self.tcx);
+        if self.borrows[index].escapes || !ty.has_erasable_regions() {
+            // a value whose type has no regions holds no references
+            return false;
+        }
+
+        let escape = match self.store_target(lvalue) {
+            StoreTarget::Local(local) => {
+                return self.borrows[index].carriers.insert(local.index());
+            }
+            StoreTarget::Through(pointer) => {
+                // Storing through a pointer stores in what it borrows, if
+                // that is known and not itself behind a pointer.
+                let targets: Vec<_> = self.borrows.iter_enumerated().filter(|&(other, data)| {
+                    other != index && !data.escapes && data.carriers.contains(pointer.index())
+                }).map(|(_, data)| data.lvalue.clone()).collect();
+                if targets.is_empty() || targets.iter().any(|target| {
+                    match self.store_target(target) {
+                        StoreTarget::Local(_) => false,
+                        _ => true,
+                    }
+                }) {
+                    true
+                } else {
+                    return targets.iter().fold(false, |changed, target| {
+                        self.flow_into(index, target) | changed
+                    });
+                }
+            }
+            StoreTarget::Unknown => true,
+        };
+        if escape {
+            self.borrows[index].escapes = true;
+        }
+        escape
+    }
+
+    fn store_target(&self, lvalue: &Lvalue<'tcx>) -> StoreTarget {
+        match *lvalue {
+            Lvalue::Local(local) => StoreTarget::Local(local),
+            Lvalue::Static(_) => StoreTarget::Unknown,
+            Lvalue::Projection(ref proj) => {
+                let base = self.store_target(&proj.base);
+                match proj.elem {
+                    ProjectionElem::Deref => {
+                        let base_ty = proj.base.ty(self.mir, self.tcx).to_ty(self.tcx);
+                        match base {
+                            // a box owns what it points to
+                            _ if base_ty.is_unique() => base,
+                            StoreTarget::Local(local) => StoreTarget::Through(local),
+                            _ => StoreTarget::Unknown,
+                        }
+                    }
+                    _ => base,
+                }
+            }
+        }
+    }
+}
+
+// Where a value stored in an lvalue ends up.
+enum StoreTarget {
+    Local(Local),
+    // in what the pointer held by the local points to
+    Through(Local),
+    Unknown,
+}
+
+// The locals read by an rvalue or an operand.
+struct LocalsRead(Vec<Local>);
+
+impl<'tcx> Visitor<'tcx> for LocalsRead {
+    fn visit_lvalue(&mut self,
+                    lvalue: &Lvalue<'tcx>,
+                    context: LvalueContext<'tcx>,
+                    location: Location) {
+        if let Lvalue::Local(local) = *lvalue {
+            self.0.push(local);
+        }
+        self.super_lvalue(lvalue, context, location);
+    }
+}
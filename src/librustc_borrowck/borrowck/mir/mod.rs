@@ -10,55 +10,45 @@
 
 use borrowck::BorrowckCtxt;
 
-use syntax::ast::{self, MetaItem};
-use syntax_pos::DUMMY_SP;
-
-use rustc::mir::{self, BasicBlock, BasicBlockData, Mir, Statement, Terminator, Location};
-use rustc::session::Session;
-use rustc::ty::{self, TyCtxt};
-
-mod abs_domain;
+use syntax::ast;
+use syntax_pos::{Span, DUMMY_SP};
+
+use rustc::mir::{BasicBlock, BorrowKind, Field, Local, Location, Lvalue, LvalueProjection};
+use rustc::mir::{Mir, ProjectionElem, Statement, Terminator, TerminatorKind, UpvarDecl};
+use rustc::mir::tcx::LvalueTy;
+use rustc::mir::visit::{LvalueContext, Visitor};
+use rustc::ty;
+use rustc::util::nodemap::FxHashSet;
+use rustc_data_structures::indexed_set::{IdxSet, IdxSetBuf};
+use rustc_data_structures::indexed_vec::Idx;
+
+use rustc_mir::dataflow::{block_liveness, do_dataflow, has_rustc_mir_with};
+use rustc_mir::dataflow::{BitDenotation, BlockSets, DataflowResults, MoveDataParamEnv};
+use rustc_mir::dataflow::{MaybeInitializedLvals, MaybeUninitializedLvals};
+use rustc_mir::dataflow::{DefinitelyInitializedLvals, LiveLocals};
+use rustc_mir::dataflow::move_paths::{HasMoveData, MoveData};
+
+use self::borrows::{BorrowIndex, Borrows};
+
+mod borrows;
 pub mod elaborate_drops;
-mod dataflow;
-mod gather_moves;
 mod patch;
-// mod graphviz;
-
-use self::dataflow::{BitDenotation};
-use self::dataflow::{DataflowOperator};
-use self::dataflow::{Dataflow, DataflowAnalysis, DataflowResults};
-use self::dataflow::{MaybeInitializedLvals, MaybeUninitializedLvals};
-use self::dataflow::{DefinitelyInitializedLvals};
-use self::gather_moves::{HasMoveData, MoveData, MovePathIndex, LookupResult};
-
-use std::fmt;
-
-fn has_rustc_mir_with(attrs: &[ast::Attribute], name: &str) -> Option<MetaItem> {
-    for attr in attrs {
-        if attr.check_name("rustc_mir") {
-            let items = attr.meta_item_list();
-            for item in items.iter().flat_map(|l| l.iter()) {
-                match item.meta_item() {
-                    Some(mi) if mi.check_name(name) => return Some(mi.clone()),
-                    _ => continue
-                }
-            }
-        }
-    }
-    return None;
-}
-
-pub struct MoveDataParamEnv<'tcx> {
-    move_data: MoveData<'tcx>,
-    param_env: ty::ParameterEnvironment<'tcx>,
-}
+mod sanity_check;
 
+/// Checks the borrows made by the MIR of the fn `id` for conflicts with
+/// each other and with the other accesses to what they borrow, with
+/// `-Z borrowck-mir`. Otherwise, as for `#[rustc_mir_borrowck]`, only the
+/// dataflow over the MIR is computed, for the `rustc_mir` test attributes.
+///
+/// A borrow is in effect for as long as the reference it makes may be
+/// used later, which is usually shorter than the lexical scope which the
+/// AST borrow checker gives it; see the `borrows` module.
 pub fn borrowck_mir(bcx: &mut BorrowckCtxt,
                     id: ast::NodeId,
                     attributes: &[ast::Attribute]) {
     let tcx = bcx.tcx;
     let def_id = tcx.map.local_def_id(id);
-    debug!("borrowck_mir({}) UNIMPLEMENTED", tcx.item_path_str(def_id));
+    debug!("borrowck_mir({})", tcx.item_path_str(def_id));
 
     let mir = &tcx.item_mir(def_id);
     let param_env = ty::ParameterEnvironment::for_item(tcx, id);
@@ -75,26 +65,41 @@ pub fn borrowck_mir(bcx: &mut BorrowckCtxt,
                     |bd, i| &bd.move_data().move_paths[i]);
 
     if has_rustc_mir_with(attributes, "rustc_peek_maybe_init").is_some() {
-        dataflow::sanity_check_via_rustc_peek(bcx.tcx, mir, id, attributes, &flow_inits);
+        sanity_check::sanity_check_via_rustc_peek(bcx.tcx, mir, id, attributes, &flow_inits);
     }
     if has_rustc_mir_with(attributes, "rustc_peek_maybe_uninit").is_some() {
-        dataflow::sanity_check_via_rustc_peek(bcx.tcx, mir, id, attributes, &flow_uninits);
+        sanity_check::sanity_check_via_rustc_peek(bcx.tcx, mir, id, attributes, &flow_uninits);
     }
     if has_rustc_mir_with(attributes, "rustc_peek_definite_init").is_some() {
-        dataflow::sanity_check_via_rustc_peek(bcx.tcx, mir, id, attributes, &flow_def_inits);
+        sanity_check::sanity_check_via_rustc_peek(bcx.tcx, mir, id, attributes, &flow_def_inits);
     }
 
     if has_rustc_mir_with(attributes, "stop_after_dataflow").is_some() {
         bcx.tcx.sess.fatal("stop_after_dataflow ended compilation");
     }
 
+    if !tcx.sess.opts.debugging_opts.borrowck_mir {
+        return;
+    }
+
+    let flow_borrows =
+        do_dataflow(tcx, mir, id, attributes, Borrows::new(tcx, mir),
+                    |bd, i| &bd.borrows()[i].lvalue);
+    let flow_live =
+        do_dataflow(tcx, mir, id, attributes, LiveLocals::new(mir),
+                    |bd, i| &bd.mir().local_decls[i]);
+
     let mut mbcx = MirBorrowckCtxt {
         bcx: bcx,
         mir: mir,
         node_id: id,
         move_data: &mdpe.move_data,
+        param_env: &mdpe.param_env,
         flow_inits: flow_inits,
         flow_uninits: flow_uninits,
+        flow_borrows: flow_borrows,
+        flow_live: flow_live,
+        reported: FxHashSet(),
     };
 
     for bb in mir.basic_blocks().indices() {
@@ -104,294 +109,380 @@ pub fn borrowck_mir(bcx: &mut BorrowckCtxt,
     debug!("borrowck_mir done");
 }
 
-fn do_dataflow<'a, 'tcx, BD, P>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
-                                mir: &Mir<'tcx>,
-                                node_id: ast::NodeId,
-                                attributes: &[ast::Attribute],
-                                bd: BD,
-                                p: P)
-                                -> DataflowResults<BD>
-    where BD: BitDenotation<Idx=MovePathIndex> + DataflowOperator,
-          P: Fn(&BD, BD::Idx) -> &fmt::Debug
-{
-    let name_found = |sess: &Session, attrs: &[ast::Attribute], name| -> Option<String> {
-        if let Some(item) = has_rustc_mir_with(attrs, name) {
-            if let Some(s) = item.value_str() {
-                return Some(s.to_string())
-            } else {
-                sess.span_err(
-                    item.span,
-                    &format!("{} attribute requires a path", item.name()));
-                return None;
-            }
-        }
-        return None;
-    };
-
-    let print_preflow_to =
-        name_found(tcx.sess, attributes, "borrowck_graphviz_preflow");
-    let print_postflow_to =
-        name_found(tcx.sess, attributes, "borrowck_graphviz_postflow");
-
-    let mut mbcx = MirBorrowckCtxtPreDataflow {
-        node_id: node_id,
-        print_preflow_to: print_preflow_to,
-        print_postflow_to: print_postflow_to,
-        flow_state: DataflowAnalysis::new(tcx, mir, bd),
-    };
-
-    mbcx.dataflow(p);
-    mbcx.flow_state.results()
-}
-
-
-pub struct MirBorrowckCtxtPreDataflow<'a, 'tcx: 'a, BD> where BD: BitDenotation
-{
-    node_id: ast::NodeId,
-    flow_state: DataflowAnalysis<'a, 'tcx, BD>,
-    print_preflow_to: Option<String>,
-    print_postflow_to: Option<String>,
-}
-
 #[allow(dead_code)]
 pub struct MirBorrowckCtxt<'b, 'a: 'b, 'tcx: 'a> {
     bcx: &'b mut BorrowckCtxt<'a, 'tcx>,
     mir: &'b Mir<'tcx>,
     node_id: ast::NodeId,
     move_data: &'b MoveData<'tcx>,
+    param_env: &'b ty::ParameterEnvironment<'tcx>,
     flow_inits: DataflowResults<MaybeInitializedLvals<'b, 'tcx>>,
-    flow_uninits: DataflowResults<MaybeUninitializedLvals<'b, 'tcx>>
+    flow_uninits: DataflowResults<MaybeUninitializedLvals<'b, 'tcx>>,
+    flow_borrows: DataflowResults<Borrows<'b, 'tcx>>,
+    flow_live: DataflowResults<LiveLocals<'b, 'tcx>>,
+    /// The spans which errors have been reported at.
+    reported: FxHashSet<Span>,
+}
+
+/// How an lvalue is accessed.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Access {
+    /// Copying or inspecting it.
+    Read,
+    Move,
+    Write,
+    Borrow(BorrowKind),
+}
+
+impl Access {
+    fn conflicts_with(self, kind: BorrowKind) -> bool {
+        match self {
+            Access::Read | Access::Borrow(BorrowKind::Shared) => kind != BorrowKind::Shared,
+            _ => true,
+        }
+    }
 }
 
 impl<'b, 'a: 'b, 'tcx: 'a> MirBorrowckCtxt<'b, 'a, 'tcx> {
     fn process_basic_block(&mut self, bb: BasicBlock) {
-        let BasicBlockData { ref statements, ref terminator, is_cleanup: _ } =
-            self.mir[bb];
-        for stmt in statements {
-            self.process_statement(bb, stmt);
+        let mir = self.mir;
+        let data = &mir[bb];
+        // the locals live after each statement are live before the next
+        let live = block_liveness(&self.flow_live, mir, bb);
+        let mut borrows = self.flow_borrows.sets().on_entry_set_for(bb.index()).to_owned();
+        for (i, stmt) in data.statements.iter().enumerate() {
+            let location = Location { block: bb, statement_index: i };
+            self.process_statement(location, stmt, &borrows, &live[i + 1]);
+            self.apply_statement_effect(&mut borrows, location);
         }
 
-        self.process_terminator(bb, terminator);
+        let location = Location { block: bb, statement_index: data.statements.len() };
+        self.process_terminator(location, data.terminator(), &borrows,
+                                &live[data.statements.len() + 1]);
     }
 
-    fn process_statement(&mut self, bb: BasicBlock, stmt: &Statement<'tcx>) {
-        debug!("MirBorrowckCtxt::process_statement({:?}, {:?}", bb, stmt);
+    fn process_statement(&mut self,
+                         location: Location,
+                         stmt: &Statement<'tcx>,
+                         borrows: &IdxSet<BorrowIndex>,
+                         live: &IdxSet<Local>) {
+        debug!("MirBorrowckCtxt::process_statement({:?}, {:?}", location, stmt);
+        let mut accesses = Accesses(vec![]);
+        accesses.visit_statement(location.block, stmt, location);
+        self.check_accesses(location, stmt.source_info.span, &accesses.0, borrows, live);
     }
 
-    fn process_terminator(&mut self, bb: BasicBlock, term: &Option<Terminator<'tcx>>) {
-        debug!("MirBorrowckCtxt::process_terminator({:?}, {:?})", bb, term);
+    fn process_terminator(&mut self,
+                          location: Location,
+                          term: &Terminator<'tcx>,
+                          borrows: &IdxSet<BorrowIndex>,
+                          live: &IdxSet<Local>) {
+        debug!("MirBorrowckCtxt::process_terminator({:?}, {:?})", location, term);
+        let mut accesses = Accesses(vec![]);
+        accesses.visit_terminator(location.block, term, location);
+        self.check_accesses(location, term.source_info.span, &accesses.0, borrows, live);
     }
-}
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-enum DropFlagState {
-    Present, // i.e. initialized
-    Absent, // i.e. deinitialized or "moved"
-}
-
-impl DropFlagState {
-    fn value(self) -> bool {
-        match self {
-            DropFlagState::Present => true,
-            DropFlagState::Absent => false
+    // Turns the borrows which reach a statement into those which reach the
+    // statement after it.
+    fn apply_statement_effect(&self, borrows: &mut IdxSetBuf<BorrowIndex>, location: Location) {
+        let bits_per_block = self.flow_borrows.sets().bits_per_block();
+        let mut gen = IdxSetBuf::new_empty(bits_per_block);
+        let mut kill = IdxSetBuf::new_empty(bits_per_block);
+        {
+            let mut sets = BlockSets { on_entry: borrows,
+                                       gen_set: &mut gen,
+                                       kill_set: &mut kill };
+            self.flow_borrows.operator().statement_effect(&mut sets,
+                                                          location.block,
+                                                          location.statement_index);
         }
+        borrows.union(&gen);
+        borrows.subtract(&kill);
     }
-}
 
-fn move_path_children_matching<'tcx, F>(move_data: &MoveData<'tcx>,
-                                        path: MovePathIndex,
-                                        mut cond: F)
-                                        -> Option<MovePathIndex>
-    where F: FnMut(&mir::LvalueProjection<'tcx>) -> bool
-{
-    let mut next_child = move_data.move_paths[path].first_child;
-    while let Some(child_index) = next_child {
-        match move_data.move_paths[child_index].lvalue {
-            mir::Lvalue::Projection(ref proj) => {
-                if cond(proj) {
-                    return Some(child_index)
-                }
+    // Reports the first borrow in effect at `location` that each of the
+    // `accesses` made there conflicts with. `live` are the locals live
+    // after `location`.
+    fn check_accesses(&mut self,
+                      location: Location,
+                      span: Span,
+                      accesses: &[(Lvalue<'tcx>, LvalueContext<'tcx>)],
+                      borrows: &IdxSet<BorrowIndex>,
+                      live: &IdxSet<Local>) {
+        for &(ref lvalue, context) in accesses {
+            let access = match self.classify(lvalue, context) {
+                Some(access) => access,
+                None => continue,
+            };
+            let conflict = self.flow_borrows.operator().borrows().iter_enumerated()
+                .find(|&(index, borrow)| {
+                    borrows.contains(&index) &&
+                        borrow.location != location &&
+                        access.conflicts_with(borrow.kind) &&
+                        borrow.is_in_effect(live) &&
+                        self.lvalues_conflict(&borrow.lvalue, lvalue, access)
+                })
+                .map(|(index, _)| index);
+            if let Some(index) = conflict {
+                self.report_conflict(span, lvalue, access, index);
             }
-            _ => {}
         }
-        next_child = move_data.move_paths[child_index].next_sibling;
     }
 
-    None
-}
+    fn classify(&self, lvalue: &Lvalue<'tcx>, context: LvalueContext<'tcx>) -> Option<Access> {
+        let tcx = self.bcx.tcx;
+        Some(match context {
+            LvalueContext::Store | LvalueContext::Call => Access::Write,
+            LvalueContext::Inspect => Access::Read,
+            LvalueContext::Borrow { kind, .. } => Access::Borrow(kind),
+            LvalueContext::Consume => {
+                let ty = lvalue.ty(self.mir, tcx).to_ty(tcx);
+                if ty.moves_by_default(tcx, self.param_env, DUMMY_SP) {
+                    Access::Move
+                } else {
+                    Access::Read
+                }
+            }
+            // whether borrowed values live long enough is still checked
+            // on the AST
+            LvalueContext::Drop |
+            LvalueContext::Projection(_) |
+            LvalueContext::StorageLive |
+            LvalueContext::StorageDead => return None,
+        })
+    }
 
-/// When enumerating the child fragments of a path, don't recurse into
-/// paths (1.) past arrays, slices, and pointers, nor (2.) into a type
-/// that implements `Drop`.
-///
-/// Lvalues behind references or arrays are not tracked by elaboration
-/// and are always assumed to be initialized when accessible. As
-/// references and indexes can be reseated, trying to track them can
-/// only lead to trouble.
-///
-/// Lvalues behind ADT's with a Drop impl are not tracked by
-/// elaboration since they can never have a drop-flag state that
-/// differs from that of the parent with the Drop impl.
-///
-/// In both cases, the contents can only be accessed if and only if
-/// their parents are initialized. This implies for example that there
-/// is no need to maintain separate drop flags to track such state.
-///
-/// FIXME: we have to do something for moving slice patterns.
-fn lvalue_contents_drop_state_cannot_differ<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
-                                                      mir: &Mir<'tcx>,
-                                                      lv: &mir::Lvalue<'tcx>) -> bool {
-    let ty = lv.ty(mir, tcx).to_ty(tcx);
-    match ty.sty {
-        ty::TyArray(..) | ty::TySlice(..) | ty::TyRef(..) | ty::TyRawPtr(..) => {
-            debug!("lvalue_contents_drop_state_cannot_differ lv: {:?} ty: {:?} refd => true",
-                   lv, ty);
-            true
+    // Whether the `access` to `accessed` touches what a borrow of
+    // `borrowed` borrows.
+    fn lvalues_conflict(&self,
+                        borrowed: &Lvalue<'tcx>,
+                        accessed: &Lvalue<'tcx>,
+                        access: Access)
+                        -> bool {
+        let (borrowed_base, borrowed_projs) = split_projections(borrowed);
+        let (accessed_base, accessed_projs) = split_projections(accessed);
+        if borrowed_base != accessed_base {
+            return false;
         }
-        ty::TyAdt(def, _) if def.has_dtor() || def.is_union() => {
-            debug!("lvalue_contents_drop_state_cannot_differ lv: {:?} ty: {:?} Drop => true",
-                   lv, ty);
-            true
+        for (b, a) in borrowed_projs.iter().zip(&accessed_projs) {
+            match (&b.elem, &a.elem) {
+                (&ProjectionElem::Field(f1, _), &ProjectionElem::Field(f2, _)) if f1 != f2 => {
+                    return false;
+                }
+                _ => {}
+            }
         }
-        _ => {
-            false
+
+        if access == Access::Write && accessed_projs.len() < borrowed_projs.len() {
+            // Overwriting a reference leaves what it refers to alone.
+            let tcx = self.bcx.tcx;
+            return !borrowed_projs[accessed_projs.len()..].iter().any(|proj| {
+                match proj.elem {
+                    ProjectionElem::Deref => !proj.base.ty(self.mir, tcx).to_ty(tcx).is_unique(),
+                    _ => false,
+                }
+            });
         }
+        true
     }
-}
 
-fn on_lookup_result_bits<'a, 'tcx, F>(
-    tcx: TyCtxt<'a, 'tcx, 'tcx>,
-    mir: &Mir<'tcx>,
-    move_data: &MoveData<'tcx>,
-    lookup_result: LookupResult,
-    each_child: F)
-    where F: FnMut(MovePathIndex)
-{
-    match lookup_result {
-        LookupResult::Parent(..) => {
-            // access to untracked value - do not touch children
-        }
-        LookupResult::Exact(e) => {
-            on_all_children_bits(tcx, mir, move_data, e, each_child)
+    fn report_conflict(&mut self,
+                       span: Span,
+                       lvalue: &Lvalue<'tcx>,
+                       access: Access,
+                       index: BorrowIndex) {
+        // An expression often accesses an lvalue more than once in MIR,
+        // so only the first conflict at a span is reported.
+        if !self.reported.insert(span) {
+            return;
         }
+
+        let borrow = &self.flow_borrows.operator().borrows()[index];
+        let accessed = self.describe_lvalue(lvalue);
+        let borrowed = self.describe_lvalue(&borrow.lvalue);
+        let sess = self.bcx.tcx.sess;
+        let mut err = match access {
+            Access::Borrow(BorrowKind::Mut) if borrow.kind == BorrowKind::Mut => {
+                let mut err = struct_span_err!(sess, span, E0499,
+                                               "cannot borrow `{}` as mutable \
+                                                more than once at a time",
+                                               accessed);
+                err.span_label(borrow.span, &format!("first mutable borrow occurs here"));
+                err.span_label(span, &format!("second mutable borrow occurs here"));
+                err
+            }
+            Access::Borrow(kind) => {
+                let pronoun = if borrowed == accessed {
+                    "it".to_string()
+                } else {
+                    format!("`{}`", borrowed)
+                };
+                let mut err = struct_span_err!(sess, span, E0502,
+                                               "cannot borrow `{}` as {} because \
+                                                {} is also borrowed as {}",
+                                               accessed,
+                                               borrow_kind_str(kind),
+                                               pronoun,
+                                               borrow_kind_str(borrow.kind));
+                err.span_label(borrow.span,
+                               &format!("{} borrow occurs here", borrow_kind_str(borrow.kind)));
+                err.span_label(span, &format!("{} borrow occurs here", borrow_kind_str(kind)));
+                err
+            }
+            Access::Read => {
+                let mut err = struct_span_err!(sess, span, E0503,
+                                               "cannot use `{}` because it was mutably borrowed",
+                                               accessed);
+                err.span_label(borrow.span, &format!("borrow of `{}` occurs here", borrowed));
+                err.span_label(span, &format!("use of borrowed `{}`", borrowed));
+                err
+            }
+            Access::Move => {
+                let mut err = struct_span_err!(sess, span, E0505,
+                                               "cannot move out of `{}` because it is borrowed",
+                                               accessed);
+                err.span_label(borrow.span, &format!("borrow of `{}` occurs here", borrowed));
+                err.span_label(span, &format!("move out of `{}` occurs here", accessed));
+                err
+            }
+            Access::Write => {
+                let mut err = struct_span_err!(sess, span, E0506,
+                                               "cannot assign to `{}` because it is borrowed",
+                                               accessed);
+                err.span_label(borrow.span, &format!("borrow of `{}` occurs here", borrowed));
+                err.span_label(span, &format!("assignment to borrowed `{}` occurs here", accessed));
+                err
+            }
+        };
+        err.emit();
     }
-}
 
-fn on_all_children_bits<'a, 'tcx, F>(
-    tcx: TyCtxt<'a, 'tcx, 'tcx>,
-    mir: &Mir<'tcx>,
-    move_data: &MoveData<'tcx>,
-    move_path_index: MovePathIndex,
-    mut each_child: F)
-    where F: FnMut(MovePathIndex)
-{
-    fn is_terminal_path<'a, 'tcx>(
-        tcx: TyCtxt<'a, 'tcx, 'tcx>,
-        mir: &Mir<'tcx>,
-        move_data: &MoveData<'tcx>,
-        path: MovePathIndex) -> bool
-    {
-        lvalue_contents_drop_state_cannot_differ(
-            tcx, mir, &move_data.move_paths[path].lvalue)
+    // The lvalue as it would be written in the source, as far as that can
+    // be told.
+    fn describe_lvalue(&self, lvalue: &Lvalue<'tcx>) -> String {
+        if let Some(upvar) = self.upvar(lvalue) {
+            return upvar.debug_name.to_string();
+        }
+        match *lvalue {
+            Lvalue::Local(local) => match self.mir.local_decls[local].name {
+                Some(name) => name.to_string(),
+                None => format!("{:?}", local),
+            },
+            Lvalue::Static(def_id) => self.bcx.tcx.item_path_str(def_id),
+            Lvalue::Projection(ref proj) => match proj.elem {
+                ProjectionElem::Deref => {
+                    match self.upvar(&proj.base) {
+                        Some(upvar) if upvar.by_ref => upvar.debug_name.to_string(),
+                        _ => format!("*{}", self.describe_lvalue(&proj.base)),
+                    }
+                }
+                ProjectionElem::Field(field, _) => {
+                    // fields are accessed through references implicitly
+                    let base = match proj.base {
+                        Lvalue::Projection(ref base) => match base.elem {
+                            ProjectionElem::Deref => &base.base,
+                            _ => &proj.base,
+                        },
+                        _ => &proj.base,
+                    };
+                    format!("{}.{}", self.describe_lvalue(base), self.field_name(&proj.base, field))
+                }
+                ProjectionElem::Downcast(..) => self.describe_lvalue(&proj.base),
+                ProjectionElem::Index(_) |
+                ProjectionElem::ConstantIndex { .. } |
+                ProjectionElem::Subslice { .. } => {
+                    format!("{}[..]", self.describe_lvalue(&proj.base))
+                }
+            },
+        }
     }
 
-    fn on_all_children_bits<'a, 'tcx, F>(
-        tcx: TyCtxt<'a, 'tcx, 'tcx>,
-        mir: &Mir<'tcx>,
-        move_data: &MoveData<'tcx>,
-        move_path_index: MovePathIndex,
-        each_child: &mut F)
-        where F: FnMut(MovePathIndex)
-    {
-        each_child(move_path_index);
-
-        if is_terminal_path(tcx, mir, move_data, move_path_index) {
-            return
+    fn field_name(&self, base: &Lvalue<'tcx>, field: Field) -> String {
+        let tcx = self.bcx.tcx;
+        match base.ty(self.mir, tcx) {
+            LvalueTy::Downcast { adt_def, variant_index, .. } => {
+                adt_def.variants[variant_index].fields[field.index()].name.to_string()
+            }
+            LvalueTy::Ty { ty } => match ty.sty {
+                ty::TyAdt(def, _) if !def.is_enum() => {
+                    def.struct_variant().fields[field.index()].name.to_string()
+                }
+                _ => field.index().to_string(),
+            },
         }
+    }
 
-        let mut next_child_index = move_data.move_paths[move_path_index].first_child;
-        while let Some(child_index) = next_child_index {
-            on_all_children_bits(tcx, mir, move_data, child_index, each_child);
-            next_child_index = move_data.move_paths[child_index].next_sibling;
+    // The upvar which `lvalue` is the field of the environment of a closure
+    // for, if it is one.
+    fn upvar(&self, lvalue: &Lvalue<'tcx>) -> Option<&'b UpvarDecl> {
+        let mir = self.mir;
+        let tcx = self.bcx.tcx;
+        match *lvalue {
+            Lvalue::Projection(ref proj) => match proj.elem {
+                ProjectionElem::Field(field, _) => {
+                    match proj.base.ty(mir, tcx).to_ty(tcx).sty {
+                        ty::TyClosure(..) => Some(&mir.upvar_decls[field.index()]),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            _ => None,
         }
     }
-    on_all_children_bits(tcx, mir, move_data, move_path_index, &mut each_child);
 }
 
-fn drop_flag_effects_for_function_entry<'a, 'tcx, F>(
-    tcx: TyCtxt<'a, 'tcx, 'tcx>,
-    mir: &Mir<'tcx>,
-    ctxt: &MoveDataParamEnv<'tcx>,
-    mut callback: F)
-    where F: FnMut(MovePathIndex, DropFlagState)
-{
-    let move_data = &ctxt.move_data;
-    for arg in mir.args_iter() {
-        let lvalue = mir::Lvalue::Local(arg);
-        let lookup_result = move_data.rev_lookup.find(&lvalue);
-        on_lookup_result_bits(tcx, mir, move_data,
-                              lookup_result,
-                              |moi| callback(moi, DropFlagState::Present));
+fn borrow_kind_str(kind: BorrowKind) -> &'static str {
+    match kind {
+        BorrowKind::Shared => "immutable",
+        BorrowKind::Unique => "uniquely immutable",
+        BorrowKind::Mut => "mutable",
     }
 }
 
-fn drop_flag_effects_for_location<'a, 'tcx, F>(
-    tcx: TyCtxt<'a, 'tcx, 'tcx>,
-    mir: &Mir<'tcx>,
-    ctxt: &MoveDataParamEnv<'tcx>,
-    loc: Location,
-    mut callback: F)
-    where F: FnMut(MovePathIndex, DropFlagState)
-{
-    let move_data = &ctxt.move_data;
-    let param_env = &ctxt.param_env;
-    debug!("drop_flag_effects_for_location({:?})", loc);
-
-    // first, move out of the RHS
-    for mi in &move_data.loc_map[loc] {
-        let path = mi.move_path_index(move_data);
-        debug!("moving out of path {:?}", move_data.move_paths[path]);
-
-        // don't move out of non-Copy things
-        let lvalue = &move_data.move_paths[path].lvalue;
-        let ty = lvalue.ty(mir, tcx).to_ty(tcx);
-        if !ty.moves_by_default(tcx, param_env, DUMMY_SP) {
-            continue;
+// The lvalue which `lvalue` is a projection of, if any, and the projections
+// from it, innermost first.
+fn split_projections<'c, 'tcx>(lvalue: &'c Lvalue<'tcx>)
+                               -> (&'c Lvalue<'tcx>, Vec<&'c LvalueProjection<'tcx>>) {
+    match *lvalue {
+        Lvalue::Projection(ref proj) => {
+            let (base, mut projs) = split_projections(&proj.base);
+            projs.push(&**proj);
+            (base, projs)
         }
+        _ => (lvalue, vec![]),
+    }
+}
 
-        on_all_children_bits(tcx, mir, move_data,
-                             path,
-                             |moi| callback(moi, DropFlagState::Absent))
+// The lvalues which a statement or a terminator accesses, and how.
+struct Accesses<'tcx>(Vec<(Lvalue<'tcx>, LvalueContext<'tcx>)>);
+
+impl<'tcx> Visitor<'tcx> for Accesses<'tcx> {
+    fn visit_lvalue(&mut self,
+                    lvalue: &Lvalue<'tcx>,
+                    context: LvalueContext<'tcx>,
+                    location: Location) {
+        match context {
+            // it is the projection of the lvalue which is accessed
+            LvalueContext::Projection(_) => {}
+            _ => self.0.push((lvalue.clone(), context)),
+        }
+        self.super_lvalue(lvalue, context, location);
     }
 
-    let block = &mir[loc.block];
-    match block.statements.get(loc.statement_index) {
-        Some(stmt) => match stmt.kind {
-            mir::StatementKind::SetDiscriminant{ .. } => {
-                span_bug!(stmt.source_info.span, "SetDiscrimant should not exist during borrowck");
-            }
-            mir::StatementKind::Assign(ref lvalue, _) => {
-                debug!("drop_flag_effects: assignment {:?}", stmt);
-                 on_lookup_result_bits(tcx, mir, move_data,
-                                       move_data.rev_lookup.find(lvalue),
-                                       |moi| callback(moi, DropFlagState::Present))
-            }
-            mir::StatementKind::StorageLive(_) |
-            mir::StatementKind::StorageDead(_) |
-            mir::StatementKind::Nop => {}
-        },
-        None => {
-            debug!("drop_flag_effects: replace {:?}", block.terminator());
-            match block.terminator().kind {
-                mir::TerminatorKind::DropAndReplace { ref location, .. } => {
-                    on_lookup_result_bits(tcx, mir, move_data,
-                                          move_data.rev_lookup.find(location),
-                                          |moi| callback(moi, DropFlagState::Present))
-                }
-                _ => {
-                    // other terminators do not contain move-ins
-                }
+    fn visit_terminator_kind(&mut self,
+                             block: BasicBlock,
+                             kind: &TerminatorKind<'tcx>,
+                             location: Location) {
+        match *kind {
+            // assigning to an lvalue which needs dropping drops its old value
+            // and writes the new one in its place
+            TerminatorKind::DropAndReplace { location: ref lvalue, ref value, .. } => {
+                self.visit_lvalue(lvalue, LvalueContext::Store, location);
+                self.visit_operand(value, location);
             }
+            _ => self.super_terminator_kind(block, kind, location),
         }
     }
 }
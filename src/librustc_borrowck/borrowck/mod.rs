@@ -44,7 +44,7 @@ use std::rc::Rc;
 use std::hash::{Hash, Hasher};
 use syntax::ast;
 use syntax_pos::{MultiSpan, Span};
-use errors::DiagnosticBuilder;
+use errors::{Applicability, DiagnosticBuilder};
 
 use rustc::hir;
 use rustc::hir::intravisit::{self, Visitor, FnKind, NestedVisitorMap};
@@ -169,7 +169,12 @@ fn borrowck_fn<'a, 'tcx>(this: &mut BorrowckCtxt<'a, 'tcx>,
 
     let body = this.tcx.map.body(body_id);
 
-    if attributes.iter().any(|item| item.check_name("rustc_mir_borrowck")) {
+    // With `-Z borrowck-mir`, conflicts between loans are checked on the MIR
+    // and only the rest is left to the checks below. `#[rustc_mir_borrowck]`
+    // just runs the dataflow over the MIR, alongside all of those checks.
+    let check_conflicts_on_mir = this.tcx.sess.opts.debugging_opts.borrowck_mir;
+    if check_conflicts_on_mir ||
+        attributes.iter().any(|item| item.check_name("rustc_mir_borrowck")) {
         this.with_temp_region_map(id, |this| {
             mir::borrowck_mir(this, id, attributes)
         });
@@ -189,7 +194,8 @@ fn borrowck_fn<'a, 'tcx>(this: &mut BorrowckCtxt<'a, 'tcx>,
                                                  &flowed_moves.move_data,
                                                  id);
 
-    check_loans::check_loans(this, &loan_dfcx, &flowed_moves, &all_loans[..], body);
+    check_loans::check_loans(this, &loan_dfcx, &flowed_moves, &all_loans[..], body,
+                             !check_conflicts_on_mir);
 
     intravisit::walk_fn(this, fk, decl, body_id, sp, id);
 }
@@ -936,12 +942,14 @@ impl<'a, 'tcx> BorrowckCtxt<'a, 'tcx> {
             .span_label(err.span,
                        &format!("may outlive borrowed value {}",
                                 cmt_path_or_string))
-            .span_suggestion(err.span,
-                             &format!("to force the closure to take ownership of {} \
-                                       (and any other referenced variables), \
-                                       use the `move` keyword, as shown:",
-                                       cmt_path_or_string),
-                             suggestion)
+            .span_suggestion_with_applicability(
+                err.span,
+                &format!("to force the closure to take ownership of {} \
+                          (and any other referenced variables), \
+                          use the `move` keyword, as shown:",
+                         cmt_path_or_string),
+                suggestion,
+                Applicability::MachineApplicable)
             .emit();
     }
 
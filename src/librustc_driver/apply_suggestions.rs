@@ -0,0 +1,377 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Applying the suggestions of the diagnostics of a compilation to its
+//! source files (`--apply-suggestions`).
+//!
+//! Only suggestions which are marked as machine-applicable are applied, and
+//! only those which lie entirely in a local source file outside of any macro
+//! expansion. Where the edits of two suggestions overlap, the one which
+//! starts first in the file is applied and the other is dropped, so that the
+//! result does not depend on the order in which the diagnostics were emitted.
+
+use rustc::session::Session;
+use rustc::session::config::ApplySuggestions;
+use rustc_errors::{Applicability, CodeSuggestion};
+use syntax::codemap::CodeMap;
+use syntax_pos::NO_EXPANSION;
+
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+/// The number of unchanged lines shown around each change of a diff.
+const CONTEXT: usize = 3;
+
+/// A replacement of the bytes `lo..hi` of a source file with `text`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct Replacement {
+    lo: usize,
+    hi: usize,
+    text: String,
+}
+
+impl Replacement {
+    // Whether applying both `self` and `other` would be ambiguous. Two
+    // insertions at the same point conflict, since their order is unknown.
+    fn conflicts_with(&self, other: &Replacement) -> bool {
+        self != other && (self.lo == other.lo || (self.lo < other.hi && other.lo < self.hi))
+    }
+}
+
+/// Applies the suggestions collected by the handler of `sess`, or prints
+/// them as a diff, depending on `mode`.
+pub fn apply_suggestions(sess: &Session, mode: ApplySuggestions) {
+    let suggestions = sess.diagnostic().take_suggestions();
+
+    let mut by_file = BTreeMap::new();
+    for suggestion in &suggestions {
+        if let Some((file, replacements)) = to_replacements(sess.codemap(), suggestion) {
+            by_file.entry(file).or_insert_with(Vec::new).push(replacements);
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut skipped = 0;
+    for (file, suggestions) in by_file {
+        let (replacements, file_skipped) = resolve_overlaps(suggestions);
+        skipped += file_skipped;
+
+        let old = match read_unchanged(sess, &file) {
+            Some(old) => old,
+            None => continue,
+        };
+        let replacements = replacements.into_iter()
+                                       .filter(|r| old[r.lo..r.hi] != r.text[..])
+                                       .collect::<Vec<_>>();
+        if replacements.is_empty() {
+            continue;
+        }
+
+        let result = match mode {
+            ApplySuggestions::Write => {
+                let new = apply_replacements(&old, 0, &replacements);
+                File::create(&file).and_then(|mut f| f.write_all(new.as_bytes()))
+            }
+            ApplySuggestions::Diff => {
+                stdout.write_all(unified_diff(&file, &old, &replacements).as_bytes())
+            }
+        };
+        if let Err(e) = result {
+            sess.err(&format!("failed to apply suggestions to `{}`: {}", file, e));
+        }
+    }
+
+    if skipped > 0 {
+        let msg = if skipped == 1 {
+            "1 suggestion was not applied because it overlaps with another one".to_owned()
+        } else {
+            format!("{} suggestions were not applied because they overlap with other ones",
+                    skipped)
+        };
+        sess.note_without_error(&msg);
+    }
+}
+
+// The file which `suggestion` edits and its edits, sorted by position, or
+// `None` if it should not be applied automatically.
+fn to_replacements(codemap: &CodeMap,
+                   suggestion: &CodeSuggestion)
+                   -> Option<(String, Vec<Replacement>)> {
+    if suggestion.applicability != Applicability::MachineApplicable {
+        return None;
+    }
+    let spans = suggestion.msp.primary_spans();
+    if spans.is_empty() || spans.len() != suggestion.substitutes.len() {
+        return None;
+    }
+
+    let mut file = None;
+    let mut replacements = Vec::new();
+    for (span, text) in spans.iter().zip(&suggestion.substitutes) {
+        if span.expn_id != NO_EXPANSION || span.hi < span.lo {
+            return None;
+        }
+        let lo = codemap.lookup_byte_offset(span.lo);
+        let hi = codemap.lookup_byte_offset(span.hi);
+        if !lo.fm.is_real_file() || lo.fm.is_imported() || lo.fm.name != hi.fm.name {
+            return None;
+        }
+        match file {
+            Some(ref name) if *name != lo.fm.name => return None,
+            _ => file = Some(lo.fm.name.clone()),
+        }
+        replacements.push(Replacement {
+            lo: lo.pos.0 as usize,
+            hi: hi.pos.0 as usize,
+            text: text.clone(),
+        });
+    }
+    replacements.sort();
+    file.map(|file| (file, replacements))
+}
+
+// Picks the suggestions of a file which are applied: in order of position,
+// each one whose edits do not conflict with those of the suggestions picked
+// before it. Returns their edits, sorted by position, and the number of
+// suggestions which were dropped. Duplicate suggestions count only once.
+fn resolve_overlaps(mut suggestions: Vec<Vec<Replacement>>) -> (Vec<Replacement>, usize) {
+    suggestions.sort();
+    suggestions.dedup();
+
+    let mut accepted: Vec<Replacement> = Vec::new();
+    let mut skipped = 0;
+    for replacements in suggestions {
+        if replacements.iter().any(|r| accepted.iter().any(|a| r.conflicts_with(a))) {
+            skipped += 1;
+            continue;
+        }
+        accepted.extend(replacements);
+    }
+    accepted.sort();
+    accepted.dedup();
+    (accepted, skipped)
+}
+
+// Reads `file`, unless it has changed since it was compiled, in which case
+// the positions of the suggestions may no longer be right.
+fn read_unchanged(sess: &Session, file: &str) -> Option<String> {
+    let filemap = match sess.codemap().get_filemap(file) {
+        Some(filemap) => filemap,
+        None => return None,
+    };
+    let mut contents = String::new();
+    if let Err(e) = File::open(file).and_then(|mut f| f.read_to_string(&mut contents)) {
+        sess.err(&format!("failed to read `{}`: {}", file, e));
+        return None;
+    }
+    match filemap.src {
+        Some(ref src) if **src == contents => Some(contents),
+        _ => {
+            sess.warn(&format!("not applying suggestions to `{}`, which has changed \
+                                since it was read",
+                               file));
+            None
+        }
+    }
+}
+
+// Applies the sorted, non-overlapping `replacements` to `text`, which
+// starts at byte `offset` of the file they refer to.
+fn apply_replacements(text: &str, offset: usize, replacements: &[Replacement]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+    for r in replacements {
+        result.push_str(&text[pos..r.lo - offset]);
+        result.push_str(&r.text);
+        pos = r.hi - offset;
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+// The byte offsets at which the lines of `text` start.
+fn line_starts(text: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(text.match_indices('\n').map(|(i, _)| i + 1).filter(|&i| i < text.len()));
+    starts
+}
+
+fn line_of(starts: &[usize], pos: usize) -> usize {
+    match starts.binary_search(&pos) {
+        Ok(line) => line,
+        Err(line) => line - 1,
+    }
+}
+
+// The lines of `text`, each with its newline.
+fn lines(text: &str) -> Vec<&str> {
+    let starts = line_starts(text);
+    (0..starts.len()).map(|i| {
+        &text[starts[i]..starts.get(i + 1).cloned().unwrap_or(text.len())]
+    }).filter(|line| !line.is_empty()).collect()
+}
+
+// A run of lines of the old file, `first..last + 1`, and the lines which the
+// replacements within it turn it into.
+struct Change {
+    first: usize,
+    last: usize,
+    new_lines: Vec<String>,
+    replacements: Vec<Replacement>,
+}
+
+// Renders the sorted, non-overlapping `replacements` of the contents `old`
+// of `file` as a unified diff.
+fn unified_diff(file: &str, old: &str, replacements: &[Replacement]) -> String {
+    let starts = line_starts(old);
+    let old_lines = lines(old);
+
+    let mut changes: Vec<Change> = Vec::new();
+    for r in replacements {
+        let first = line_of(&starts, r.lo);
+        let last = line_of(&starts, if r.hi > r.lo { r.hi - 1 } else { r.lo });
+        if let Some(change) = changes.last_mut() {
+            if first <= change.last {
+                change.last = max(change.last, last);
+                change.replacements.push(r.clone());
+                continue;
+            }
+        }
+        changes.push(Change {
+            first: first,
+            last: last,
+            new_lines: Vec::new(),
+            replacements: vec![r.clone()],
+        });
+    }
+    for change in &mut changes {
+        let lo = starts[change.first];
+        let hi = starts.get(change.last + 1).cloned().unwrap_or(old.len());
+        let new = apply_replacements(&old[lo..hi], lo, &change.replacements);
+        change.new_lines = lines(&new).into_iter().map(|l| l.to_owned()).collect();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", file, file);
+    let mut added_lines = 0isize;
+    let mut i = 0;
+    while i < changes.len() {
+        // Changes whose context would touch go into the same hunk.
+        let mut j = i + 1;
+        while j < changes.len() && changes[j].first - changes[j - 1].last <= 2 * CONTEXT + 1 {
+            j += 1;
+        }
+        let start = changes[i].first.saturating_sub(CONTEXT);
+        let end = min(changes[j - 1].last + 1 + CONTEXT, old_lines.len());
+
+        let mut body = String::new();
+        let mut new_count = 0;
+        let mut line = start;
+        for change in &changes[i..j] {
+            for l in &old_lines[line..change.first] {
+                push_line(&mut body, ' ', l);
+            }
+            for l in &old_lines[change.first..change.last + 1] {
+                push_line(&mut body, '-', l);
+            }
+            for l in &change.new_lines {
+                push_line(&mut body, '+', l);
+            }
+            new_count += change.first - line + change.new_lines.len();
+            line = change.last + 1;
+        }
+        for l in &old_lines[line..end] {
+            push_line(&mut body, ' ', l);
+        }
+        new_count += end - line;
+
+        let old_count = end - start;
+        let new_start = (start as isize + added_lines) as usize;
+        out.push_str(&format!("@@ -{} +{} @@\n",
+                              hunk_range(start, old_count),
+                              hunk_range(new_start, new_count)));
+        out.push_str(&body);
+        added_lines += new_count as isize - old_count as isize;
+        i = j;
+    }
+    out
+}
+
+// A hunk's range of lines, starting at the zero-based line `start`. An empty
+// range refers to the line before it.
+fn hunk_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+fn push_line(out: &mut String, marker: char, line: &str) {
+    out.push(marker);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Replacement, apply_replacements, resolve_overlaps, unified_diff};
+
+    fn replace(lo: usize, hi: usize, text: &str) -> Replacement {
+        Replacement {
+            lo: lo,
+            hi: hi,
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn overlapping_suggestions_are_resolved_by_position() {
+        let suggestions = vec![vec![replace(6, 10, "b")],
+                               vec![replace(0, 4, "a"), replace(8, 12, "c")],
+                               vec![replace(14, 16, "d")],
+                               vec![replace(14, 16, "d")]];
+        let (replacements, skipped) = resolve_overlaps(suggestions.clone());
+        assert_eq!(replacements, vec![replace(0, 4, "a"), replace(8, 12, "c"),
+                                      replace(14, 16, "d")]);
+        assert_eq!(skipped, 1);
+
+        // The order in which the diagnostics were emitted does not matter.
+        let mut reversed = suggestions;
+        reversed.reverse();
+        assert_eq!(resolve_overlaps(reversed), (replacements, skipped));
+    }
+
+    #[test]
+    fn insertions_at_the_same_point_conflict() {
+        let (replacements, skipped) = resolve_overlaps(vec![vec![replace(3, 3, "x")],
+                                                            vec![replace(3, 3, "y")],
+                                                            vec![replace(1, 3, "z")]]);
+        assert_eq!(replacements, vec![replace(1, 3, "z"), replace(3, 3, "x")]);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn diff_of_replacements() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn";
+        let replacements = vec![replace(2, 3, "B"), replace(8, 9, "E\nE2"), replace(26, 27, "N")];
+        assert_eq!(apply_replacements(old, 0, &replacements),
+                   "a\nB\nc\nd\nE\nE2\nf\ng\nh\ni\nj\nk\nl\nm\nN");
+        assert_eq!(unified_diff("lib.rs", old, &replacements),
+                   "--- lib.rs\n+++ lib.rs\n\
+                    @@ -1,8 +1,9 @@\n a\n-b\n+B\n c\n d\n-e\n+E\n+E2\n f\n g\n h\n\
+                    @@ -11,4 +12,4 @@\n k\n l\n m\n-n\n\\ No newline at end of file\n\
+                    +N\n\\ No newline at end of file\n");
+    }
+}
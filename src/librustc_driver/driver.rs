@@ -19,6 +19,8 @@ use rustc::session::search_paths::PathKind;
 use rustc::lint;
 use rustc::middle::{self, dependency_format, stability, reachable};
 use rustc::middle::privacy::AccessLevels;
+use rustc::mir::Mir;
+use rustc::mir::transform::MirSource;
 use rustc::ty::{self, TyCtxt, Resolutions, GlobalArenas};
 use rustc::util::common::time;
 use rustc::util::nodemap::{NodeSet, NodeMap};
@@ -170,6 +172,7 @@ pub fn compile_input(sess: &Session,
                                     &arena,
                                     &arenas,
                                     &crate_name,
+                                    control,
                                     |tcx, analysis, incremental_hashes_map, result| {
             {
                 // Eventually, we will want to track plugins.
@@ -197,7 +200,7 @@ pub fn compile_input(sess: &Session,
                 println!("Pre-trans");
                 tcx.print_debug_stats();
             }
-            let trans = phase_4_translate_to_llvm(tcx, analysis, &incremental_hashes_map);
+            let trans = phase_4_translate_to_llvm(tcx, analysis, &incremental_hashes_map, control)?;
 
             if log_enabled!(::log::INFO) {
                 println!("Post-trans");
@@ -278,11 +281,22 @@ pub fn source_name(input: &Input) -> String {
 /// Note that if compilation is set to stop and a callback is provided for a
 /// given entry point, the callback is called before compilation is stopped.
 ///
+/// The entry points within the analysis passes (`after_typeck`,
+/// `after_mir_construction` and `after_mir_pass`) are given the type context
+/// instead of a `CompileState`, and decide whether compilation continues by
+/// their return value.
+///
 /// Expect more entry points to be added in the future.
 pub struct CompileController<'a> {
     pub after_parse: PhaseController<'a>,
     pub after_expand: PhaseController<'a>,
     pub after_hir_lowering: PhaseController<'a>,
+    pub after_typeck: AnalysisController<'a>,
+    pub after_mir_construction: AnalysisController<'a>,
+    // Called for the MIR of each item after every MIR pass, including the
+    // optimisations run before translation. This is `None` by default, so
+    // that the MIR is not walked after each pass for nothing.
+    pub after_mir_pass: Option<Box<Fn(&MirPassState) -> Compilation + 'a>>,
     pub after_analysis: PhaseController<'a>,
     pub after_llvm: PhaseController<'a>,
     pub compilation_done: PhaseController<'a>,
@@ -296,6 +310,9 @@ impl<'a> CompileController<'a> {
             after_parse: PhaseController::basic(),
             after_expand: PhaseController::basic(),
             after_hir_lowering: PhaseController::basic(),
+            after_typeck: AnalysisController::basic(),
+            after_mir_construction: AnalysisController::basic(),
+            after_mir_pass: None,
             after_analysis: PhaseController::basic(),
             after_llvm: PhaseController::basic(),
             compilation_done: PhaseController::basic(),
@@ -322,6 +339,38 @@ impl<'a> PhaseController<'a> {
     }
 }
 
+/// Controls an entry point within the analysis passes. The callback may report
+/// errors through `state.tcx.sess`; if it returns `Compilation::Stop`,
+/// compilation stops with those errors.
+pub struct AnalysisController<'a> {
+    pub callback: Box<Fn(&AnalysisState) -> Compilation + 'a>,
+}
+
+impl<'a> AnalysisController<'a> {
+    pub fn basic() -> AnalysisController<'a> {
+        AnalysisController {
+            callback: box |_| Compilation::Continue,
+        }
+    }
+}
+
+/// State that is passed to an `AnalysisController` callback.
+pub struct AnalysisState<'a, 'tcx: 'a> {
+    pub tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    pub crate_name: &'a str,
+}
+
+/// State that is passed to the `after_mir_pass` callback, once for the MIR of
+/// each local item and each of its promoted constants.
+pub struct MirPassState<'a, 'tcx: 'a> {
+    pub tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    /// The name of the pass which has just run, followed by its disambiguator
+    /// if it has one, e.g. `SimplifyCfg-initial`.
+    pub pass_name: &'a str,
+    pub source: MirSource,
+    pub mir: &'a Mir<'tcx>,
+}
+
 /// State that is passed to a callback. What state is available depends on when
 /// during compilation the callback is made. See the various constructor methods
 /// (`state_*`) in the impl to see which data is provided for any given entry point.
@@ -624,7 +673,7 @@ pub fn phase_2_configure_and_expand<F>(sess: &Session,
 
     // Lint plugins are registered; now we can process command line flags.
     if sess.opts.describe_lints {
-        super::describe_lints(&sess.lint_store.borrow(), true);
+        super::describe_lints(&sess.lint_store.borrow(), true, sess.opts.error_format);
         return Err(0);
     }
     sess.track_errors(|| sess.lint_store.borrow_mut().process_command_line(sess))?;
@@ -698,7 +747,8 @@ pub fn phase_2_configure_and_expand<F>(sess: &Session,
                                          &mut resolver,
                                          sess.opts.test,
                                          krate,
-                                         sess.diagnostic())
+                                         sess.diagnostic(),
+                                         &sess.features.borrow())
     });
 
     // If we're in rustdoc we're always compiling as an rlib, but that'll trip a
@@ -810,6 +860,7 @@ pub fn phase_3_run_analysis_passes<'tcx, F, R>(sess: &'tcx Session,
                                                arena: &'tcx DroplessArena,
                                                arenas: &'tcx GlobalArenas<'tcx>,
                                                name: &str,
+                                               control: &CompileController,
                                                f: F)
                                                -> Result<R, usize>
     where F: for<'a> FnOnce(TyCtxt<'a, 'tcx, 'tcx>,
@@ -895,6 +946,10 @@ pub fn phase_3_run_analysis_passes<'tcx, F, R>(sess: &'tcx Session,
         analysis.hir_ty_to_ty =
             try_with_f!(typeck::check_crate(tcx), (tcx, analysis, incremental_hashes_map));
 
+        if run_analysis_callback(tcx, name, &control.after_typeck) == Compilation::Stop {
+            return Ok(f(tcx, analysis, incremental_hashes_map, Err(sess.err_count())));
+        }
+
         time(time_passes,
              "const checking",
              || consts::check_crate(tcx));
@@ -936,7 +991,12 @@ pub fn phase_3_run_analysis_passes<'tcx, F, R>(sess: &'tcx Session,
             mir_stats::print_mir_stats(tcx, "PRE CLEANUP MIR STATS");
         }
 
-        time(time_passes, "MIR cleanup and validation", || {
+        if run_analysis_callback(tcx, name, &control.after_mir_construction) ==
+           Compilation::Stop {
+            return Ok(f(tcx, analysis, incremental_hashes_map, Err(sess.err_count())));
+        }
+
+        let completed = time(time_passes, "MIR cleanup and validation", || {
             let mut passes = sess.mir_passes.borrow_mut();
             // Push all the built-in validation passes.
             // NB: if you’re adding an *optimisation* it ought to go to another set of passes
@@ -949,9 +1009,16 @@ pub fn phase_3_run_analysis_passes<'tcx, F, R>(sess: &'tcx Session,
             passes.push_pass(
                 box mir::transform::simplify_branches::SimplifyBranches::new("initial"));
             passes.push_pass(box mir::transform::simplify::SimplifyCfg::new("qualify-consts"));
+            // Lints are emitted by the lint checking below, so this can't wait for stage 4.
+            passes.push_pass(box mir::transform::const_prop::ConstPropLint);
             // And run everything.
-            passes.run_passes(tcx);
+            passes.run_passes_and_then(tcx, |tcx, pass_name| {
+                run_mir_pass_callback(tcx, pass_name, control) == Compilation::Continue
+            })
         });
+        if !completed {
+            return Ok(f(tcx, analysis, incremental_hashes_map, Err(sess.err_count())));
+        }
 
         time(time_passes,
              "borrow checking",
@@ -996,8 +1063,9 @@ pub fn phase_3_run_analysis_passes<'tcx, F, R>(sess: &'tcx Session,
 /// Run the translation phase to LLVM, after which the AST and analysis can
 pub fn phase_4_translate_to_llvm<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
                                            analysis: ty::CrateAnalysis,
-                                           incremental_hashes_map: &IncrementalHashesMap)
-                                           -> trans::CrateTranslation {
+                                           incremental_hashes_map: &IncrementalHashesMap,
+                                           control: &CompileController)
+                                           -> Result<trans::CrateTranslation, usize> {
     let time_passes = tcx.sess.time_passes();
 
     time(time_passes,
@@ -1010,7 +1078,7 @@ pub fn phase_4_translate_to_llvm<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
 
     // Run the passes that transform the MIR into a more suitable form for translation to LLVM
     // code.
-    time(time_passes, "MIR optimisations", || {
+    let completed = time(time_passes, "MIR optimisations", || {
         let mut passes = ::rustc::mir::transform::Passes::new();
         passes.push_hook(box mir::transform::dump_mir::DumpMir);
         passes.push_pass(box mir::transform::no_landing_pads::NoLandingPads);
@@ -1021,6 +1089,9 @@ pub fn phase_4_translate_to_llvm<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
 
         passes.push_pass(box mir::transform::add_call_guards::AddCallGuards);
         passes.push_pass(box borrowck::ElaborateDrops);
+        // Inlined MIR from other crates can have landing pads even if this
+        // crate does not unwind, so inline before removing them.
+        passes.push_pass(box mir::transform::inline::Inline);
         passes.push_pass(box mir::transform::no_landing_pads::NoLandingPads);
         passes.push_pass(box mir::transform::simplify::SimplifyCfg::new("elaborate-drops"));
 
@@ -1028,13 +1099,23 @@ pub fn phase_4_translate_to_llvm<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
         passes.push_pass(box mir::transform::instcombine::InstCombine::new());
         passes.push_pass(box mir::transform::deaggregator::Deaggregator);
         passes.push_pass(box mir::transform::copy_prop::CopyPropagation);
+        passes.push_pass(box mir::transform::const_prop::ConstProp);
+        passes.push_pass(
+            box mir::transform::simplify_branches::SimplifyBranches::new("after-const-prop"));
+        passes.push_pass(box mir::transform::simplify::SimplifyCfg::new("after-const-prop"));
+        passes.push_pass(box mir::transform::dead_stores::DeadStoreElimination);
 
         passes.push_pass(box mir::transform::simplify::SimplifyLocals);
         passes.push_pass(box mir::transform::add_call_guards::AddCallGuards);
         passes.push_pass(box mir::transform::dump_mir::Marker("PreTrans"));
 
-        passes.run_passes(tcx);
+        passes.run_passes_and_then(tcx, |tcx, pass_name| {
+            run_mir_pass_callback(tcx, pass_name, control) == Compilation::Continue
+        })
     });
+    if !completed {
+        return Err(tcx.sess.err_count());
+    }
 
     if tcx.sess.opts.debugging_opts.mir_stats {
         mir_stats::print_mir_stats(tcx, "POST OPTIMISATION MIR STATS");
@@ -1054,7 +1135,60 @@ pub fn phase_4_translate_to_llvm<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
          || rustc_incremental::save_dep_graph(tcx,
                                               &incremental_hashes_map,
                                               translation.link.crate_hash));
-    translation
+    Ok(translation)
+}
+
+fn run_analysis_callback<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                                   crate_name: &str,
+                                   controller: &AnalysisController)
+                                   -> Compilation {
+    // Eventually, we will want to track plugins.
+    let _ignore = tcx.dep_graph.in_ignore();
+
+    let state = AnalysisState {
+        tcx: tcx,
+        crate_name: crate_name,
+    };
+    (controller.callback)(&state)
+}
+
+// Calls the `after_mir_pass` callback, if any, for the MIR of each local item
+// once `pass_name` has run.
+fn run_mir_pass_callback<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                                   pass_name: &str,
+                                   control: &CompileController)
+                                   -> Compilation {
+    let callback = match control.after_mir_pass {
+        Some(ref callback) => callback,
+        None => return Compilation::Continue,
+    };
+    let _ignore = tcx.dep_graph.in_ignore();
+
+    let def_ids = tcx.mir_map.borrow().keys();
+    for def_id in def_ids {
+        let id = match tcx.map.as_local_node_id(def_id) {
+            Some(id) => id,
+            None => continue,
+        };
+        let mir = tcx.item_mir(def_id);
+        let mut state = MirPassState {
+            tcx: tcx,
+            pass_name: pass_name,
+            source: MirSource::from_node(tcx, id),
+            mir: &mir,
+        };
+        if callback(&state) == Compilation::Stop {
+            return Compilation::Stop;
+        }
+        for (i, promoted) in mir.promoted.iter_enumerated() {
+            state.source = MirSource::Promoted(id, i);
+            state.mir = promoted;
+            if callback(&state) == Compilation::Stop {
+                return Compilation::Stop;
+            }
+        }
+    }
+    Compilation::Continue
 }
 
 /// Run LLVM itself, producing a bitcode file, assembly file or object file
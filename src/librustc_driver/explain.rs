@@ -0,0 +1,173 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `--explain` and `--explain-search`, which print the extended descriptions
+//! of error codes.
+//!
+//! The prose of a description is printed first, with a marker where each of
+//! its code examples was. The erroneous examples and the corrected examples
+//! follow in sections of their own, syntax highlighted if the output is a
+//! terminal.
+
+use errors::ColorConfig;
+use errors::registry::{ExampleKind, ExplanationPart, Registry};
+use rustc::session::config::ErrorOutputType;
+use rustc::session::early_error;
+use syntax::parse::lexer::TokenAndSpan;
+use syntax::print::highlight::{self, Class, Writer};
+
+use std::fmt::Display;
+use std::io;
+
+const RESET: &'static str = "\x1b[0m";
+
+pub fn explain(code: &str, registry: &Registry, output: ErrorOutputType) {
+    let normalised = if code.starts_with("E") {
+        code.to_string()
+    } else {
+        format!("E{0:0>4}", code)
+    };
+    let explanation = match registry.find_explanation(&normalised) {
+        Some(explanation) => explanation,
+        None => early_error(output, &format!("no extended information for {}", code)),
+    };
+
+    let mut text = String::new();
+    let mut erroneous = 0;
+    let mut corrected = 0;
+    for part in &explanation.parts {
+        match *part {
+            ExplanationPart::Text(ref t) => text.push_str(&normalise_fences(t)),
+            ExplanationPart::Example(ExampleKind::Erroneous, _) => {
+                erroneous += 1;
+                text.push_str(&format!("[erroneous example {}]\n", erroneous));
+            }
+            ExplanationPart::Example(ExampleKind::Corrected, _) => {
+                corrected += 1;
+                text.push_str(&format!("[corrected example {}]\n", corrected));
+            }
+        }
+    }
+    print!("{}", text.trim_left_matches('\n'));
+
+    let color = use_color(output);
+    for &(name, kind) in &[("Erroneous", ExampleKind::Erroneous),
+                           ("Corrected", ExampleKind::Corrected)] {
+        for (i, code) in explanation.examples(kind).iter().enumerate() {
+            println!("\n{} code example {}:\n", name, i + 1);
+            for line in render(code, color).lines() {
+                println!("    {}", line);
+            }
+        }
+    }
+}
+
+pub fn search(keyword: &str, registry: &Registry, output: ErrorOutputType) {
+    let codes = registry.search(keyword);
+    if codes.is_empty() {
+        early_error(output, &format!("no error code descriptions mention `{}`", keyword));
+    }
+    for code in codes {
+        let description = registry.find_description(code).unwrap_or("");
+        // The first line of prose, which is usually a summary of the error.
+        let summary = description.lines()
+                                 .map(|line| line.trim_left_matches('#').trim())
+                                 .find(|line| !line.is_empty())
+                                 .unwrap_or("");
+        println!("{}: {}", code, summary);
+    }
+}
+
+// The tags of code blocks are meant for rustdoc, not for the reader.
+fn normalise_fences(text: &str) -> String {
+    text.lines().map(|line| {
+        format!("{}\n", if line.starts_with("```") { "```" } else { line })
+    }).collect()
+}
+
+fn render(code: &str, color: bool) -> String {
+    // Examples which are not valid Rust are printed as they are, without the
+    // lexer's complaints about them.
+    if color {
+        let mut writer = AnsiWriter { out: String::new() };
+        if highlight::highlight_silently(code, &mut writer).is_ok() {
+            return writer.out;
+        }
+    }
+    code.to_string()
+}
+
+fn use_color(output: ErrorOutputType) -> bool {
+    match output {
+        ErrorOutputType::HumanReadable(color) | ErrorOutputType::Short(color) => {
+            match color {
+                ColorConfig::Always => true,
+                ColorConfig::Never => false,
+                ColorConfig::Auto => stdout_isatty(),
+            }
+        }
+        ErrorOutputType::Json | ErrorOutputType::Sarif => false,
+    }
+}
+
+#[cfg(unix)]
+fn stdout_isatty() -> bool {
+    use libc;
+    unsafe { libc::isatty(libc::STDOUT_FILENO) != 0 }
+}
+
+// Older Windows consoles do not understand the escape codes which are used
+// for the colors.
+#[cfg(windows)]
+fn stdout_isatty() -> bool {
+    false
+}
+
+// Writes highlighted source with ANSI terminal colors.
+struct AnsiWriter {
+    out: String,
+}
+
+fn ansi_color(klass: Class) -> Option<&'static str> {
+    match klass {
+        Class::Comment | Class::DocComment => Some("\x1b[90m"),
+        Class::KeyWord | Class::RefKeyWord | Class::Self_ => Some("\x1b[1;35m"),
+        Class::String => Some("\x1b[32m"),
+        Class::Number | Class::Bool | Class::PreludeVal => Some("\x1b[36m"),
+        Class::Macro | Class::MacroNonTerminal => Some("\x1b[34m"),
+        Class::Attribute | Class::Lifetime | Class::PreludeTy => Some("\x1b[33m"),
+        Class::QuestionMark => Some("\x1b[1m"),
+        Class::None | Class::Op | Class::Ident => None,
+    }
+}
+
+impl Writer for AnsiWriter {
+    fn enter_span(&mut self, klass: Class) -> io::Result<()> {
+        self.out.push_str(ansi_color(klass).unwrap_or(""));
+        Ok(())
+    }
+
+    fn exit_span(&mut self) -> io::Result<()> {
+        self.out.push_str(RESET);
+        Ok(())
+    }
+
+    fn string<T: Display>(&mut self,
+                          text: T,
+                          klass: Class,
+                          _tas: Option<&TokenAndSpan>)
+                          -> io::Result<()> {
+        match ansi_color(klass) {
+            Some(color) => self.out.push_str(&format!("{}{}{}", color, text, RESET)),
+            None => self.out.push_str(&text.to_string()),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The report of the future incompatibility lints which `--cap-lints` kept
+//! from being emitted.
+//!
+//! Dependencies are built with their lints capped, so without this report
+//! nobody learns that they will stop compiling with a future release. The
+//! report lists every future incompatibility lint triggered by the crate,
+//! including the ones which were emitted, and is only made if some of them
+//! were not. It is a warning, or a line of JSON with `--error-format json`.
+
+use rustc::lint::FutureIncompatibleHit;
+use rustc::session::Session;
+use rustc::session::config::ErrorOutputType;
+use serialize::json::{Json, ToJson};
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+pub fn report(sess: &Session) {
+    let hits = sess.future_incompatible_hits.borrow();
+    let hidden = hits.iter().filter(|hit| !hit.shown).count();
+    if hidden == 0 {
+        return;
+    }
+
+    match sess.opts.error_format {
+        ErrorOutputType::Json => json_report(sess, &hits),
+        _ => text_report(sess, &hits, hidden),
+    }
+}
+
+fn text_report(sess: &Session, hits: &[FutureIncompatibleHit], hidden: usize) {
+    let cap = sess.opts.lint_cap.map_or("", |cap| cap.as_str());
+    let mut diag = sess.struct_warn("this crate contains code which will be rejected by a \
+                                     future release of the compiler");
+    diag.note(&format!("{} of the warnings about it were hidden by `--cap-lints {}`",
+                       hidden, cap));
+    let mut references: Vec<(String, &str)> = Vec::new();
+    for hit in hits {
+        let name = hit.lint.name_lower();
+        diag.note(&format!("{}{} [{}]", location(sess, hit), hit.message, name));
+        if !references.iter().any(|&(ref seen, _)| *seen == name) {
+            references.push((name, hit.reference));
+        }
+    }
+    for (name, reference) in references {
+        diag.note(&format!("for more information about `{}`, see {}", name, reference));
+    }
+    diag.emit();
+}
+
+fn json_report(sess: &Session, hits: &[FutureIncompatibleHit]) {
+    let hits = hits.iter().map(|hit| {
+        let mut fields = vec![("lint", hit.lint.name_lower().to_json()),
+                              ("message", hit.message.to_json()),
+                              ("reference", hit.reference.to_json()),
+                              ("shown", hit.shown.to_json())];
+        if let Some(span) = hit.span {
+            let loc = sess.codemap().lookup_char_pos(span.lo);
+            fields.push(("file_name", loc.file.name.to_json()));
+            fields.push(("line", loc.line.to_json()));
+            fields.push(("column", (loc.col.0 + 1).to_json()));
+        }
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+    }).collect();
+
+    let mut report = BTreeMap::new();
+    report.insert("future_incompat_report".to_owned(), Json::Array(hits));
+    // There is nowhere left to report a failure to write the report to.
+    let _ = writeln!(io::stderr(), "{}", Json::Object(report));
+}
+
+// The `file:line:col: ` of a hit, if it has a span.
+fn location(sess: &Session, hit: &FutureIncompatibleHit) -> String {
+    hit.span.map_or(String::new(), |span| {
+        let loc = sess.codemap().lookup_char_pos(span.lo);
+        format!("{}:{}:{}: ", loc.file.name, loc.line, loc.col.0 + 1)
+    })
+}
@@ -80,7 +80,7 @@ use rustc_metadata::locator;
 use rustc_metadata::cstore::CStore;
 use rustc::util::common::time;
 
-use serialize::json::ToJson;
+use serialize::json::{Json, ToJson};
 
 use std::cmp::max;
 use std::cmp::Ordering::Equal;
@@ -88,6 +88,7 @@ use std::default::Default;
 use std::env;
 use std::io::{self, Read, Write};
 use std::iter::repeat;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::PathBuf;
 use std::process;
 use std::rc::Rc;
@@ -107,7 +108,10 @@ pub mod test;
 pub mod driver;
 pub mod pretty;
 pub mod target_features;
+mod apply_suggestions;
 mod derive_registrar;
+mod explain;
+mod future_incompat;
 
 const BUG_REPORT_URL: &'static str = "https://github.com/rust-lang/rust/blob/master/CONTRIBUTING.\
                                       md#bug-reports";
@@ -135,19 +139,34 @@ pub fn run<F>(run_compiler: F) -> isize
 {
     monitor(move || {
         let (result, session) = run_compiler();
-        if let Err(err_count) = result {
+        let err_count = match result {
+            Ok(()) => 0,
+            Err(err_count) => err_count,
+        };
+        if let Some(ref sess) = session {
             if err_count > 0 {
-                match session {
-                    Some(sess) => sess.fatal(&abort_msg(err_count)),
-                    None => {
-                        let emitter =
-                            errors::emitter::EmitterWriter::stderr(errors::ColorConfig::Auto, None);
-                        let handler = errors::Handler::with_emitter(true, false, Box::new(emitter));
-                        handler.emit(&MultiSpan::new(),
-                                     &abort_msg(err_count),
-                                     errors::Level::Fatal);
-                        exit_on_err();
-                    }
+                sess.struct_fatal(&abort_msg(err_count)).emit();
+            }
+            // Some emitters, like the SARIF one, only write out their output
+            // now, and a failure to do so must not go unnoticed.
+            if let Err(e) = sess.diagnostic().flush_emitter() {
+                early_error(ErrorOutputType::default(),
+                            &format!("failed to write the diagnostics: {}", e));
+            }
+        }
+        if err_count > 0 {
+            match session {
+                Some(_) => panic!(errors::FatalError),
+                None => {
+                    let emitter =
+                        errors::emitter::EmitterWriter::stderr(errors::ColorConfig::Auto,
+                                                               None,
+                                                               false);
+                    let handler = errors::Handler::with_emitter(true, false, Box::new(emitter));
+                    handler.emit(&MultiSpan::new(),
+                                 &abort_msg(err_count),
+                                 errors::Level::Fatal);
+                    exit_on_err();
                 }
             }
         }
@@ -209,6 +228,9 @@ pub fn run_compiler<'a>(args: &[String],
         sopts, &dep_graph, input_file_path, descriptions, cstore.clone(), codemap, emitter_dest,
     );
     rustc_lint::register_builtins(&mut sess.lint_store.borrow_mut(), Some(&sess));
+    if sess.opts.apply_suggestions.is_some() {
+        sess.diagnostic().collect_suggestions();
+    }
 
     let mut cfg = config::build_configuration(&sess, cfg);
     target_features::add_configuration(&mut cfg, &sess);
@@ -218,8 +240,36 @@ pub fn run_compiler<'a>(args: &[String],
 
     let plugins = sess.opts.debugging_opts.extra_plugins.clone();
     let control = callbacks.build_controller(&sess, &matches);
-    (driver::compile_input(&sess, &cstore, &input, &odir, &ofile, Some(plugins), &control),
-     Some(sess))
+    let compiled = panic::catch_unwind(AssertUnwindSafe(|| {
+        driver::compile_input(&sess, &cstore, &input, &odir, &ofile, Some(plugins), &control)
+    }));
+    let mut result = match compiled {
+        Ok(result) => result,
+        Err(value) => {
+            // Fatal errors, like reaching `--error-limit`, unwind out of the
+            // compilation, but what it found until then is still reported.
+            if value.is::<errors::FatalError>() {
+                finish_compilation(&sess);
+            }
+            panic::resume_unwind(value);
+        }
+    };
+    finish_compilation(&sess);
+    if sess.opts.apply_suggestions.is_some() && result.is_ok() {
+        result = session::compile_result_from_err_count(sess.err_count());
+    }
+    (result, Some(sess))
+}
+
+// Reports what was collected during the compilation and applies the
+// suggestions. The suggestions are applied even if the compilation failed,
+// since many of them are fixes for its errors.
+fn finish_compilation(sess: &Session) {
+    sess.diagnostic().report_hidden_diagnostics();
+    future_incompat::report(sess);
+    if let Some(mode) = sess.opts.apply_suggestions {
+        apply_suggestions::apply_suggestions(sess, mode);
+    }
 }
 
 // Extract output directory and file from matches.
@@ -348,31 +398,6 @@ pub trait CompilerCalls<'a> {
 #[derive(Copy, Clone)]
 pub struct RustcDefaultCalls;
 
-fn handle_explain(code: &str,
-                  descriptions: &errors::registry::Registry,
-                  output: ErrorOutputType) {
-    let normalised = if code.starts_with("E") {
-        code.to_string()
-    } else {
-        format!("E{0:0>4}", code)
-    };
-    match descriptions.find_description(&normalised) {
-        Some(ref description) => {
-            // Slice off the leading newline and print.
-            print!("{}", &(&description[1..]).split("\n").map(|x| {
-                format!("{}\n", if x.starts_with("```") {
-                    "```"
-                } else {
-                    x
-                })
-            }).collect::<String>());
-        }
-        None => {
-            early_error(output, &format!("no extended information for {}", code));
-        }
-    }
-}
-
 impl<'a> CompilerCalls<'a> for RustcDefaultCalls {
     fn early_callback(&mut self,
                       matches: &getopts::Matches,
@@ -382,9 +407,15 @@ impl<'a> CompilerCalls<'a> for RustcDefaultCalls {
                       output: ErrorOutputType)
                       -> Compilation {
         if let Some(ref code) = matches.opt_str("explain") {
-            handle_explain(code, descriptions, output);
+            explain::explain(code, descriptions, output);
             return Compilation::Stop;
         }
+        if matches.opts_present(&["explain-search".to_owned()]) {
+            if let Some(ref keyword) = matches.opt_str("explain-search") {
+                explain::search(keyword, descriptions, output);
+                return Compilation::Stop;
+            }
+        }
 
         Compilation::Continue
     }
@@ -402,7 +433,7 @@ impl<'a> CompilerCalls<'a> for RustcDefaultCalls {
                 if sopts.describe_lints {
                     let mut ls = lint::LintStore::new();
                     rustc_lint::register_builtins(&mut ls, None);
-                    describe_lints(&ls, false);
+                    describe_lints(&ls, false, sopts.error_format);
                     return None;
                 }
                 let dep_graph = DepGraph::new(sopts.build_dep_graph());
@@ -730,7 +761,11 @@ fn usage(verbose: bool, include_unstable_options: bool) {
              extra_help);
 }
 
-fn describe_lints(lint_store: &lint::LintStore, loaded_plugins: bool) {
+fn describe_lints(lint_store: &lint::LintStore, loaded_plugins: bool, format: ErrorOutputType) {
+    if format == ErrorOutputType::Json {
+        return describe_lints_json(lint_store, loaded_plugins);
+    }
+
     println!("
 Available lint options:
     -W <foo>           Warn about <foo>
@@ -864,6 +899,49 @@ Available lint options:
     }
 }
 
+// Print the lints and lint groups as JSON, for tools which check lint names
+// or levels without running the compiler on a crate.
+fn describe_lints_json(lint_store: &lint::LintStore, loaded_plugins: bool) {
+    fn object(fields: Vec<(&str, Json)>) -> Json {
+        Json::Object(fields.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+    }
+
+    let mut groups = lint_store.get_lint_groups();
+    groups.sort_by(|&(x, ..), &(y, ..)| x.cmp(y));
+
+    let mut lints = lint_store.get_lints().to_vec();
+    lints.sort_by(|&(x, _), &(y, _)| x.name.cmp(y.name));
+    let lints = lints.into_iter().map(|(lint, plugin)| {
+        let id = lint::LintId::of(lint);
+        let in_groups = groups.iter()
+                              .filter(|&&(_, ref ids, _)| ids.contains(&id))
+                              .map(|&(name, ..)| name.to_json())
+                              .collect();
+        let future_incompatible = lint_store.future_incompatible(id).map(|info| {
+            object(vec![("reference", info.reference.to_json())])
+        });
+        object(vec![("name", lint.name_lower().to_json()),
+                    ("default_level", lint.default_level.as_str().to_json()),
+                    ("description", lint.desc.to_json()),
+                    ("plugin", plugin.to_json()),
+                    ("groups", Json::Array(in_groups)),
+                    ("future_incompatible", future_incompatible.unwrap_or(Json::Null))])
+    }).collect();
+
+    let groups = groups.into_iter().map(|(name, ids, plugin)| {
+        let lints = ids.iter().map(|id| id.to_string().to_json()).collect();
+        object(vec![("name", name.to_json()),
+                    ("lints", Json::Array(lints)),
+                    ("plugin", plugin.to_json())])
+    }).collect();
+
+    // Whether the plugins of a crate were loaded, so that the lints they
+    // register are included.
+    println!("{}", object(vec![("lints", Json::Array(lints)),
+                               ("groups", Json::Array(groups)),
+                               ("plugins_loaded", loaded_plugins.to_json())]));
+}
+
 fn describe_debug_flags() {
     println!("\nAvailable debug options:\n");
     print_flag_list("-Z", config::DB_OPTIONS);
@@ -1057,7 +1135,9 @@ pub fn monitor<F: FnOnce() + Send + 'static>(f: F) {
         // Thread panicked without emitting a fatal diagnostic
         if !value.is::<errors::FatalError>() {
             let emitter =
-                Box::new(errors::emitter::EmitterWriter::stderr(errors::ColorConfig::Auto, None));
+                Box::new(errors::emitter::EmitterWriter::stderr(errors::ColorConfig::Auto,
+                                                                None,
+                                                                false));
             let handler = errors::Handler::with_emitter(true, false, emitter);
 
             // a .span_bug or .bug call has already printed what
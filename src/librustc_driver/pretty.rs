@@ -229,6 +229,7 @@ impl PpSourceMode {
                 f(&annotation, payload, ast_map.forest.krate())
             }
             PpmTyped => {
+                let control = driver::CompileController::basic();
                 abort_on_err(driver::phase_3_run_analysis_passes(sess,
                                                                  ast_map.clone(),
                                                                  analysis.clone(),
@@ -236,6 +237,7 @@ impl PpSourceMode {
                                                                  arena,
                                                                  arenas,
                                                                  id,
+                                                                 &control,
                                                                  |tcx, _, _, _| {
                     let empty_tables = ty::Tables::empty();
                     let annotation = TypedAnnotation {
@@ -1001,6 +1003,7 @@ fn print_with_analysis<'tcx, 'a: 'tcx>(sess: &'a Session,
                                                      arena,
                                                      arenas,
                                                      crate_name,
+                                                     &driver::CompileController::basic(),
                                                      |tcx, _, _, _| {
         match ppm {
             PpmMir | PpmMirCFG => {
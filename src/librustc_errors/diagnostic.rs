@@ -8,6 +8,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use Applicability;
 use CodeSuggestion;
 use Level;
 use RenderSpan;
@@ -148,12 +149,25 @@ impl Diagnostic {
                                                msg: &str,
                                                suggestion: String)
                                                -> &mut Self {
+        self.span_suggestion_with_applicability(sp, msg, suggestion, Applicability::Unspecified)
+    }
+
+    /// Like `span_suggestion`, but also says whether tools may apply the
+    /// suggestion automatically.
+    pub fn span_suggestion_with_applicability<S: Into<MultiSpan>>(
+        &mut self,
+        sp: S,
+        msg: &str,
+        suggestion: String,
+        applicability: Applicability)
+        -> &mut Self {
         self.sub(Level::Help,
                  msg,
                  MultiSpan::new(),
                  Some(Suggestion(CodeSuggestion {
                      msp: sp.into(),
                      substitutes: vec![suggestion],
+                     applicability: applicability,
                  })));
         self
     }
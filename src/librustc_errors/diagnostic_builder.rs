@@ -8,6 +8,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+use Applicability;
 use Diagnostic;
 use Level;
 use Handler;
@@ -80,6 +81,7 @@ impl<'a> DiagnosticBuilder<'a> {
             return;
         }
 
+        let is_error = self.level == Level::Error;
         match self.level {
             Level::Bug |
             Level::Fatal |
@@ -95,9 +97,15 @@ impl<'a> DiagnosticBuilder<'a> {
             }
         }
 
-        self.handler.emitter.borrow_mut().emit(&self);
+        if self.handler.should_show(&self) {
+            self.handler.emitter.borrow_mut().emit(&self);
+        }
+        self.handler.record_suggestions(&self);
         self.cancel();
         self.handler.panic_if_treat_err_as_bug();
+        if is_error {
+            self.handler.abort_at_error_limit();
+        }
 
         // if self.is_fatal() {
         //     panic!(FatalError);
@@ -144,6 +152,13 @@ impl<'a> DiagnosticBuilder<'a> {
                                                         msg: &str,
                                                         suggestion: String)
                                                         -> &mut Self);
+    forward!(pub fn span_suggestion_with_applicability<S: Into<MultiSpan>>(
+        &mut self,
+        sp: S,
+        msg: &str,
+        suggestion: String,
+        applicability: Applicability)
+        -> &mut Self);
     forward!(pub fn set_span<S: Into<MultiSpan>>(&mut self, sp: S) -> &mut Self);
     forward!(pub fn code(&mut self, s: String) -> &mut Self);
 
@@ -26,6 +26,12 @@ use term;
 pub trait Emitter {
     /// Emit a structured diagnostic.
     fn emit(&mut self, db: &DiagnosticBuilder);
+
+    /// Write out whatever has been held back until the end of the
+    /// compilation. Nothing should be emitted after this.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 impl Emitter for EmitterWriter {
@@ -33,7 +39,15 @@ impl Emitter for EmitterWriter {
         let mut primary_span = db.span.clone();
         let mut children = db.children.clone();
         self.fix_multispans_in_std_macros(&mut primary_span, &mut children);
-        self.emit_messages_default(&db.level, &db.message, &db.code, &primary_span, &children);
+        if self.short_message {
+            self.emit_message_short(&db.level, &db.message, &db.code, &primary_span, &children);
+        } else {
+            self.emit_messages_default(&db.level,
+                                       &db.message,
+                                       &db.code,
+                                       &primary_span,
+                                       &children);
+        }
     }
 }
 
@@ -60,6 +74,7 @@ impl ColorConfig {
 pub struct EmitterWriter {
     dst: Destination,
     cm: Option<Rc<CodeMapper>>,
+    short_message: bool,
 }
 
 struct FileWithAnnotatedLines {
@@ -84,25 +99,36 @@ macro_rules! println_maybe_styled {
 }
 
 impl EmitterWriter {
-    pub fn stderr(color_config: ColorConfig, code_map: Option<Rc<CodeMapper>>) -> EmitterWriter {
+    /// With `short_message`, each diagnostic is printed on a single line,
+    /// without any source snippets.
+    pub fn stderr(color_config: ColorConfig,
+                  code_map: Option<Rc<CodeMapper>>,
+                  short_message: bool)
+                  -> EmitterWriter {
         if color_config.use_color() {
             let dst = Destination::from_stderr();
             EmitterWriter {
                 dst: dst,
                 cm: code_map,
+                short_message: short_message,
             }
         } else {
             EmitterWriter {
                 dst: Raw(Box::new(io::stderr())),
                 cm: code_map,
+                short_message: short_message,
             }
         }
     }
 
-    pub fn new(dst: Box<Write + Send>, code_map: Option<Rc<CodeMapper>>) -> EmitterWriter {
+    pub fn new(dst: Box<Write + Send>,
+               code_map: Option<Rc<CodeMapper>>,
+               short_message: bool)
+               -> EmitterWriter {
         EmitterWriter {
             dst: Raw(dst),
             cm: code_map,
+            short_message: short_message,
         }
     }
 
@@ -919,6 +945,51 @@ impl EmitterWriter {
         }
         Ok(())
     }
+    // Prints `file:line:col: level[code]: message` for the primary span, with
+    // the messages of the children folded onto the same line. Suggestions are
+    // dropped, since they make no sense without the source.
+    fn emit_message_short(&mut self,
+                          level: &Level,
+                          message: &str,
+                          code: &Option<String>,
+                          span: &MultiSpan,
+                          children: &Vec<SubDiagnostic>) {
+        let mut buffer = StyledBuffer::new();
+
+        if let (Some(cm), Some(primary_span)) = (self.cm.as_ref(), span.primary_span()) {
+            if primary_span != DUMMY_SP && primary_span != COMMAND_LINE_SP {
+                let loc = cm.lookup_char_pos(primary_span.lo);
+                buffer.append(0,
+                              &format!("{}:{}:{}", loc.file.name, loc.line, loc.col.0 + 1),
+                              Style::LineAndColumn);
+                buffer.append(0, ": ", Style::NoStyle);
+            }
+        }
+
+        buffer.append(0, &level.to_string(), Style::Level(level.clone()));
+        if let Some(ref code) = *code {
+            buffer.append(0, "[", Style::Level(level.clone()));
+            buffer.append(0, code, Style::Level(level.clone()));
+            buffer.append(0, "]", Style::Level(level.clone()));
+        }
+        buffer.append(0, ": ", Style::HeaderMsg);
+        buffer.append(0, &single_line(message), Style::HeaderMsg);
+
+        for child in children {
+            if let Some(Suggestion(_)) = child.render_span {
+                continue;
+            }
+            buffer.append(0, "; ", Style::NoStyle);
+            buffer.append(0, &child.level.to_string(), Style::Level(child.level.clone()));
+            buffer.append(0, ": ", Style::NoStyle);
+            buffer.append(0, &single_line(&child.message), Style::NoStyle);
+        }
+
+        if let Err(e) = emit_to_destination(&buffer.render(), level, &mut self.dst) {
+            panic!("failed to emit error: {}", e)
+        }
+    }
+
     fn emit_messages_default(&mut self,
                              level: &Level,
                              message: &String,
@@ -1027,6 +1098,15 @@ fn overlaps(a1: &Annotation, a2: &Annotation) -> bool {
     num_overlap(a1.start_col, a1.end_col, a2.start_col, a2.end_col, false)
 }
 
+// Joins the lines of a message with spaces.
+fn single_line(msg: &str) -> String {
+    msg.lines()
+       .map(|line| line.trim())
+       .filter(|line| !line.is_empty())
+       .collect::<Vec<_>>()
+       .join(" ")
+}
+
 fn emit_to_destination(rendered_buffer: &Vec<Vec<StyledString>>,
                        lvl: &Level,
                        dst: &mut Destination)
@@ -42,7 +42,8 @@ use self::Level::*;
 use emitter::{Emitter, EmitterWriter};
 
 use std::cell::{RefCell, Cell};
-use std::{error, fmt};
+use std::collections::{BTreeMap, HashMap};
+use std::{error, fmt, io, mem};
 use std::rc::Rc;
 
 pub mod diagnostic;
@@ -53,7 +54,7 @@ pub mod registry;
 pub mod styled_buffer;
 mod lock;
 
-use syntax_pos::{BytePos, Loc, FileLinesResult, FileName, MultiSpan, Span, NO_EXPANSION};
+use syntax_pos::{BytePos, Loc, FileLinesResult, FileName, MultiSpan, Span, DUMMY_SP, NO_EXPANSION};
 use syntax_pos::MacroBacktrace;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -74,6 +75,25 @@ pub enum RenderSpan {
 pub struct CodeSuggestion {
     pub msp: MultiSpan,
     pub substitutes: Vec<String>,
+    pub applicability: Applicability,
+}
+
+/// How sure the compiler is that a suggestion is what the user intended,
+/// which decides whether tools may apply it without anyone looking at it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, RustcEncodable, RustcDecodable)]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended, and can be
+    /// applied automatically.
+    MachineApplicable,
+    /// The suggestion may be what the user intended, but that is uncertain.
+    /// Applying it should result in valid code, but its meaning may differ
+    /// from what the user had in mind.
+    MaybeIncorrect,
+    /// The suggestion contains placeholders like `(...)` which the user has
+    /// to fill in, so it cannot be applied as is.
+    HasPlaceholders,
+    /// Nothing is known about the suggestion.
+    Unspecified,
 }
 
 pub trait CodeMapper {
@@ -223,6 +243,25 @@ pub struct Handler {
     treat_err_as_bug: bool,
     continue_after_error: Cell<bool>,
     delayed_span_bug: RefCell<Option<(MultiSpan, String)>>,
+    // The suggestions of the emitted diagnostics, if they are being collected
+    suggestions: RefCell<Option<Vec<CodeSuggestion>>>,
+
+    // The limits set by `set_diagnostic_limits`, and what has been shown and
+    // hidden because of them
+    max_per_code: Cell<Option<usize>>,
+    max_per_span: Cell<Option<usize>>,
+    error_limit: Cell<Option<usize>>,
+    shown_per_code: RefCell<HashMap<String, usize>>,
+    shown_per_span: RefCell<HashMap<Span, usize>>,
+    hidden: RefCell<BTreeMap<(&'static str, HiddenKey), usize>>,
+}
+
+// What a hidden diagnostic was similar to: either other diagnostics with the
+// same code, or other diagnostics at the same span.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum HiddenKey {
+    Code(String),
+    Span(Span),
 }
 
 impl Handler {
@@ -231,7 +270,7 @@ impl Handler {
                             treat_err_as_bug: bool,
                             cm: Option<Rc<CodeMapper>>)
                             -> Handler {
-        let emitter = Box::new(EmitterWriter::stderr(color_config, cm));
+        let emitter = Box::new(EmitterWriter::stderr(color_config, cm, false));
         Handler::with_emitter(can_emit_warnings, treat_err_as_bug, emitter)
     }
 
@@ -246,6 +285,13 @@ impl Handler {
             treat_err_as_bug: treat_err_as_bug,
             continue_after_error: Cell::new(true),
             delayed_span_bug: RefCell::new(None),
+            suggestions: RefCell::new(None),
+            max_per_code: Cell::new(None),
+            max_per_span: Cell::new(None),
+            error_limit: Cell::new(None),
+            shown_per_code: RefCell::new(HashMap::new()),
+            shown_per_span: RefCell::new(HashMap::new()),
+            hidden: RefCell::new(BTreeMap::new()),
         }
     }
 
@@ -253,6 +299,132 @@ impl Handler {
         self.continue_after_error.set(continue_after_error);
     }
 
+    /// Have the emitter write out the diagnostics it holds back until the end
+    /// of the compilation.
+    pub fn flush_emitter(&self) -> io::Result<()> {
+        self.emitter.borrow_mut().flush()
+    }
+
+    /// Limits the errors and warnings which are shown: at most `per_code`
+    /// with the same code, and at most `per_span` at the same primary span.
+    /// Those beyond the limits are still counted, and are summarized by
+    /// `report_hidden_diagnostics`. Once `error_limit` errors have been
+    /// reported, shown or not, compilation is aborted.
+    pub fn set_diagnostic_limits(&self,
+                                 per_code: Option<usize>,
+                                 per_span: Option<usize>,
+                                 error_limit: Option<usize>) {
+        self.max_per_code.set(per_code);
+        self.max_per_span.set(per_span);
+        self.error_limit.set(error_limit);
+    }
+
+    // Decides whether `diagnostic` is shown, and counts it either way.
+    fn should_show(&self, diagnostic: &Diagnostic) -> bool {
+        let level = match diagnostic.level {
+            Error | Warning => diagnostic.level.to_str(),
+            _ => return true,
+        };
+
+        let mut shown_per_code = self.shown_per_code.borrow_mut();
+        let mut shown_per_span = self.shown_per_span.borrow_mut();
+        let code = diagnostic.code.as_ref();
+        let span = diagnostic.span.primary_span().and_then(|span| {
+            if span == DUMMY_SP { None } else { Some(span) }
+        });
+
+        let code_shown = code.map_or(0, |code| shown_per_code.get(code).cloned().unwrap_or(0));
+        let span_shown = span.map_or(0, |span| shown_per_span.get(&span).cloned().unwrap_or(0));
+        let hidden_by = match (code, span, self.max_per_code.get(), self.max_per_span.get()) {
+            (Some(code), _, Some(max), _) if code_shown >= max => {
+                Some(HiddenKey::Code(code.clone()))
+            }
+            (_, Some(span), _, Some(max)) if span_shown >= max => Some(HiddenKey::Span(span)),
+            _ => None,
+        };
+
+        match hidden_by {
+            Some(key) => {
+                *self.hidden.borrow_mut().entry((level, key)).or_insert(0) += 1;
+                false
+            }
+            None => {
+                if let Some(code) = code {
+                    *shown_per_code.entry(code.clone()).or_insert(0) += 1;
+                }
+                if let Some(span) = span {
+                    *shown_per_span.entry(span).or_insert(0) += 1;
+                }
+                true
+            }
+        }
+    }
+
+    /// Emits a note for each group of similar diagnostics which were hidden
+    /// because of the limits of `set_diagnostic_limits`, and forgets them.
+    pub fn report_hidden_diagnostics(&self) {
+        let hidden = mem::replace(&mut *self.hidden.borrow_mut(), BTreeMap::new());
+        for ((level, key), count) in hidden {
+            let plural = if count == 1 { "" } else { "s" };
+            let mut db = match key {
+                HiddenKey::Code(code) => {
+                    DiagnosticBuilder::new(self,
+                                           Note,
+                                           &format!("and {} more similar {}{} with code {}",
+                                                    count, level, plural, code))
+                }
+                HiddenKey::Span(span) => {
+                    let mut db = DiagnosticBuilder::new(self,
+                                                        Note,
+                                                        &format!("and {} more {}{} here",
+                                                                 count, level, plural));
+                    db.set_span(span);
+                    db
+                }
+            };
+            db.emit();
+        }
+    }
+
+    // Aborts compilation once the error limit has been reached.
+    fn abort_at_error_limit(&self) {
+        if let Some(limit) = self.error_limit.get() {
+            if self.err_count.get() >= limit {
+                self.report_hidden_diagnostics();
+                panic!(self.fatal(&format!("aborting after {} errors because of the error limit",
+                                           self.err_count.get())));
+            }
+        }
+    }
+
+    /// Starts keeping the suggestions of all diagnostics emitted from now
+    /// on, for `take_suggestions`.
+    pub fn collect_suggestions(&self) {
+        let mut suggestions = self.suggestions.borrow_mut();
+        if suggestions.is_none() {
+            *suggestions = Some(Vec::new());
+        }
+    }
+
+    /// Returns the suggestions collected so far, in the order in which
+    /// their diagnostics were emitted.
+    pub fn take_suggestions(&self) -> Vec<CodeSuggestion> {
+        match *self.suggestions.borrow_mut() {
+            Some(ref mut suggestions) => mem::replace(suggestions, Vec::new()),
+            None => Vec::new(),
+        }
+    }
+
+    fn record_suggestions(&self, diagnostic: &Diagnostic) {
+        if let Some(ref mut suggestions) = *self.suggestions.borrow_mut() {
+            for child in &diagnostic.children {
+                if let Some(RenderSpan::Suggestion(ref suggestion)) = child.render_span {
+                    suggestions.push(suggestion.clone());
+                }
+            }
+        }
+    }
+
     pub fn struct_dummy<'a>(&'a self) -> DiagnosticBuilder<'a> {
         DiagnosticBuilder::new(self, Level::Cancelled, "")
     }
@@ -457,6 +629,7 @@ impl Handler {
             }
         }
 
+        self.report_hidden_diagnostics();
         panic!(self.fatal(&s));
     }
     pub fn emit(&self, msp: &MultiSpan, msg: &str, lvl: Level) {
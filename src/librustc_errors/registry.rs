@@ -23,4 +23,225 @@ impl Registry {
     pub fn find_description(&self, code: &str) -> Option<&'static str> {
         self.descriptions.get(code).cloned()
     }
+
+    pub fn find_explanation(&self, code: &str) -> Option<Explanation> {
+        self.find_description(code).map(Explanation::parse)
+    }
+
+    /// The codes whose description mentions `keyword`, ignoring case, in
+    /// order.
+    pub fn search(&self, keyword: &str) -> Vec<&'static str> {
+        let keyword = keyword.to_lowercase();
+        let mut codes = self.descriptions
+                            .iter()
+                            .filter(|&(code, description)| {
+                                code.to_lowercase() == keyword ||
+                                description.to_lowercase().contains(&keyword)
+                            })
+                            .map(|(&code, _)| code)
+                            .collect::<Vec<_>>();
+        codes.sort();
+        codes
+    }
+}
+
+/// Whether a code example in an explanation shows the error, or how to fix it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExampleKind {
+    Erroneous,
+    Corrected,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExplanationPart {
+    /// Prose, or a code block which is not a Rust example (such as the
+    /// output of a program), as Markdown.
+    Text(String),
+    /// A Rust code example, without the lines which rustdoc hides.
+    Example(ExampleKind, String),
+}
+
+/// The extended description of an error code, split into prose and examples.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub parts: Vec<ExplanationPart>,
+}
+
+impl Explanation {
+    /// Splits a description into its parts. Rust code blocks tagged with
+    /// `compile_fail` are erroneous examples; all others are corrected ones.
+    pub fn parse(description: &str) -> Explanation {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut example: Option<(Option<ExampleKind>, String)> = None;
+
+        for line in description.lines() {
+            let fence = line.trim_left().starts_with("```");
+            match example.take() {
+                None if fence => {
+                    let tags = line.trim_left()[3..].trim();
+                    let kind = example_kind(tags);
+                    if kind.is_some() {
+                        if !text.trim().is_empty() {
+                            parts.push(ExplanationPart::Text(text.clone()));
+                        }
+                        text.clear();
+                    } else {
+                        text.push_str(line);
+                        text.push('\n');
+                    }
+                    example = Some((kind, String::new()));
+                }
+                None => {
+                    text.push_str(line);
+                    text.push('\n');
+                }
+                Some((kind, code)) => {
+                    if fence {
+                        match kind {
+                            Some(kind) => parts.push(ExplanationPart::Example(kind, code)),
+                            None => {
+                                text.push_str(&code);
+                                text.push_str(line);
+                                text.push('\n');
+                            }
+                        }
+                        continue;
+                    }
+                    let mut code = code;
+                    // Only Rust examples hide lines, for rustdoc.
+                    let trimmed = line.trim_left();
+                    if kind.is_none() || (trimmed != "#" && !trimmed.starts_with("# ")) {
+                        code.push_str(line);
+                        code.push('\n');
+                    }
+                    example = Some((kind, code));
+                }
+            }
+        }
+        // An unterminated code block runs to the end of the description.
+        match example {
+            Some((Some(kind), code)) => parts.push(ExplanationPart::Example(kind, code)),
+            Some((None, code)) => text.push_str(&code),
+            None => {}
+        }
+        if !text.trim().is_empty() {
+            parts.push(ExplanationPart::Text(text));
+        }
+        Explanation { parts: parts }
+    }
+
+    /// The examples of the given kind, in order.
+    pub fn examples(&self, kind: ExampleKind) -> Vec<&str> {
+        self.parts.iter().filter_map(|part| {
+            match *part {
+                ExplanationPart::Example(k, ref code) if k == kind => Some(&code[..]),
+                _ => None,
+            }
+        }).collect()
+    }
+}
+
+// The kind of example in a code block with the given rustdoc tags, or `None`
+// if it is not Rust code.
+fn example_kind(tags: &str) -> Option<ExampleKind> {
+    let mut kind = ExampleKind::Corrected;
+    for tag in tags.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty()) {
+        match tag {
+            "compile_fail" => kind = ExampleKind::Erroneous,
+            "rust" | "ignore" | "no_run" | "should_panic" | "test_harness" => {}
+            _ if tag.starts_with('E') => {}
+            _ => return None,
+        }
+    }
+    Some(kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::ExampleKind::*;
+    use super::ExplanationPart::*;
+
+    fn text(s: &str) -> ExplanationPart {
+        Text(s.to_string())
+    }
+
+    fn example(kind: ExampleKind, code: &str) -> ExplanationPart {
+        Example(kind, code.to_string())
+    }
+
+    #[test]
+    fn test_example_kinds() {
+        let explanation = Explanation::parse("Some prose.\n\
+                                              \n\
+                                              ```compile_fail,E0001\n\
+                                              let x = 1;\n\
+                                              ```\n\
+                                              \n\
+                                              Fixed:\n\
+                                              \n\
+                                              ```\n\
+                                              let y = 2;\n\
+                                              ```\n");
+        assert_eq!(explanation.parts, vec![text("Some prose.\n\n"),
+                                           example(Erroneous, "let x = 1;\n"),
+                                           text("\nFixed:\n\n"),
+                                           example(Corrected, "let y = 2;\n")]);
+        assert_eq!(explanation.examples(Erroneous), vec!["let x = 1;\n"]);
+        assert_eq!(explanation.examples(Corrected), vec!["let y = 2;\n"]);
+    }
+
+    #[test]
+    fn test_hidden_lines() {
+        let explanation = Explanation::parse("```\n\
+                                              # fn main() {\n\
+                                              #[derive(Clone)]\n\
+                                              struct Foo;\n\
+                                              #\n\
+                                              # }\n\
+                                              ```\n\
+                                              ```text\n\
+                                              # not hidden\n\
+                                              ```\n");
+        assert_eq!(explanation.parts, vec![example(Corrected, "#[derive(Clone)]\nstruct Foo;\n"),
+                                           text("```text\n# not hidden\n```\n")]);
+    }
+
+    #[test]
+    fn test_other_code_is_text() {
+        let explanation = Explanation::parse("Output:\n\
+                                              ```text\n\
+                                              error\n\
+                                              ```\n\
+                                              Done.\n");
+        assert_eq!(explanation.parts, vec![text("Output:\n```text\nerror\n```\nDone.\n")]);
+        assert!(explanation.examples(Corrected).is_empty());
+    }
+
+    #[test]
+    fn test_unterminated_code() {
+        let explanation = Explanation::parse("Prose.\n\
+                                              ```compile_fail\n\
+                                              let x;\n");
+        assert_eq!(explanation.parts, vec![text("Prose.\n"), example(Erroneous, "let x;\n")]);
+
+        let explanation = Explanation::parse("Prose.\n\
+                                              ```text\n\
+                                              output\n");
+        assert_eq!(explanation.parts, vec![text("Prose.\n```text\noutput\n")]);
+    }
+
+    #[test]
+    fn test_search() {
+        let registry = Registry::new(&[("E0001", "Foo bar."),
+                                       ("E0002", "Unlike E0001, it is about BAR."),
+                                       ("E0003", "Nothing.")]);
+        assert_eq!(registry.search("bar"), vec!["E0001", "E0002"]);
+        assert_eq!(registry.search("BaR"), vec!["E0001", "E0002"]);
+        assert_eq!(registry.search("e0003"), vec!["E0003"]);
+        // Codes only match as a whole, unlike descriptions.
+        assert_eq!(registry.search("E000"), vec!["E0002"]);
+        assert!(registry.search("baz").is_empty());
+    }
 }
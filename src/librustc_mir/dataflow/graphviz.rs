@@ -27,8 +27,7 @@ use std::marker::PhantomData;
 use std::mem;
 use std::path::Path;
 
-use super::super::MirBorrowckCtxtPreDataflow;
-use super::{BitDenotation, DataflowState};
+use super::{BitDenotation, DataflowAnalysis, DataflowState};
 
 impl<O: BitDenotation> DataflowState<O> {
     fn each_bit<F>(&self, words: &IdxSet<O::Idx>, mut f: F)
@@ -85,13 +84,18 @@ pub trait MirWithFlowState<'tcx> {
     fn flow_state(&self) -> &DataflowState<Self::BD>;
 }
 
-impl<'a, 'tcx: 'a, BD> MirWithFlowState<'tcx> for MirBorrowckCtxtPreDataflow<'a, 'tcx, BD>
-    where 'tcx: 'a, BD: BitDenotation
+struct AnalysisForNode<'b, 'a: 'b, 'tcx: 'a, BD> where BD: 'b + BitDenotation {
+    node_id: NodeId,
+    analysis: &'b DataflowAnalysis<'a, 'tcx, BD>,
+}
+
+impl<'b, 'a: 'b, 'tcx: 'a, BD> MirWithFlowState<'tcx> for AnalysisForNode<'b, 'a, 'tcx, BD>
+    where BD: BitDenotation
 {
     type BD = BD;
     fn node_id(&self) -> NodeId { self.node_id }
-    fn mir(&self) -> &Mir<'tcx> { self.flow_state.mir() }
-    fn flow_state(&self) -> &DataflowState<Self::BD> { &self.flow_state.flow_state }
+    fn mir(&self) -> &Mir<'tcx> { self.analysis.mir() }
+    fn flow_state(&self) -> &DataflowState<Self::BD> { self.analysis.flow_state() }
 }
 
 struct Graph<'a, 'tcx, MWF:'a, P> where
@@ -102,19 +106,23 @@ struct Graph<'a, 'tcx, MWF:'a, P> where
     render_idx: P,
 }
 
-pub fn print_borrowck_graph_to<'a, 'tcx, BD, P>(
-    mbcx: &MirBorrowckCtxtPreDataflow<'a, 'tcx, BD>,
+/// Renders the MIR of the item `node_id` with the sets of `analysis` to
+/// the file at `path`. `render_idx` gives what is shown for a bit.
+pub fn print_dataflow_graph_to<'a, 'tcx, BD, P>(
+    node_id: NodeId,
+    analysis: &DataflowAnalysis<'a, 'tcx, BD>,
     path: &Path,
     render_idx: P)
     -> io::Result<()>
     where BD: BitDenotation,
           P: Fn(&BD, BD::Idx) -> &Debug
 {
-    let g = Graph { mbcx: mbcx, phantom: PhantomData, render_idx: render_idx };
+    let mbcx = AnalysisForNode { node_id: node_id, analysis: analysis };
+    let g = Graph { mbcx: &mbcx, phantom: PhantomData, render_idx: render_idx };
     let mut v = Vec::new();
     dot::render(&g, &mut v)?;
-    debug!("print_borrowck_graph_to path: {} node_id: {}",
-           path.display(), mbcx.node_id);
+    debug!("print_dataflow_graph_to path: {} node_id: {}",
+           path.display(), node_id);
     File::create(path).and_then(|mut f| f.write_all(&v))
 }
 
@@ -219,7 +227,7 @@ impl<'a, 'tcx, MWF, P> dot::Labeller<'a> for Graph<'a, 'tcx, MWF, P>
             }
             Ok(())
         }
-        ::rustc_mir::graphviz::write_node_label(
+        ::graphviz::write_node_label(
             *n, self.mbcx.mir(), &mut v, 4,
             |w| {
                 let flow = self.mbcx.flow_state();
@@ -231,8 +239,10 @@ impl<'a, 'tcx, MWF, P> dot::Labeller<'a> for Graph<'a, 'tcx, MWF, P>
                 let entry = flow.sets.on_entry_set_for(i);
                 debug!("entry set for i={i} bits_per_block: {bpb} entry: {e:?} interp: {ei:?}",
                        i=i, e=entry, bpb=bits_per_block, ei=entry_interp);
-                write!(w, "= ENTRY:</td><td {bg}><FONT {face}>{entrybits:?}</FONT></td>\
+                // The bits of a backward analysis enter a block at its end.
+                write!(w, "= {entry}:</td><td {bg}><FONT {face}>{entrybits:?}</FONT></td>\
                                         <td></td></tr>",
+                       entry = if MWF::BD::is_backward() { "EXIT" } else { "ENTRY" },
                        bg = BG_FLOWCONTENT,
                        face = FACE_MONOSPACE,
                        entrybits=bits_to_string(entry.words(), bits_per_block))
@@ -9,20 +9,21 @@
 // except according to those terms.
 
 use rustc::ty::TyCtxt;
-use rustc::mir::{self, Mir, Location};
+use rustc::mir::{self, Mir, Location, Local};
+use rustc::mir::visit::{LvalueContext, Visitor};
 use rustc_data_structures::bitslice::BitSlice; // adds set_bit/get_bit to &[usize] bitvector rep.
 use rustc_data_structures::bitslice::{BitwiseOperator};
-use rustc_data_structures::indexed_set::{IdxSet};
+use rustc_data_structures::indexed_set::{IdxSet, IdxSetBuf};
 use rustc_data_structures::indexed_vec::Idx;
 
-use super::super::gather_moves::{HasMoveData, MoveData, MoveOutIndex, MovePathIndex};
-use super::super::MoveDataParamEnv;
-use super::super::DropFlagState;
-use super::super::drop_flag_effects_for_function_entry;
-use super::super::drop_flag_effects_for_location;
-use super::super::on_lookup_result_bits;
+use super::move_paths::{HasMoveData, MoveData, MoveOutIndex, MovePathIndex};
+use super::MoveDataParamEnv;
+use super::DropFlagState;
+use super::drop_flag_effects_for_function_entry;
+use super::drop_flag_effects_for_location;
+use super::on_lookup_result_bits;
 
-use super::{BitDenotation, BlockSets, DataflowOperator};
+use super::{BitDenotation, BlockSets, DataflowOperator, DataflowResults};
 
 // Dataflow analyses are built upon some interpretation of the
 // bitvectors attached to each basic block, represented via a
@@ -223,6 +224,69 @@ impl<'a, 'tcx> HasMoveData<'tcx> for MovingOutStatements<'a, 'tcx> {
     fn move_data(&self) -> &MoveData<'tcx> { &self.mdpe.move_data }
 }
 
+/// `LiveLocals` tracks all locals whose current value might be read
+/// later in the control flow for a function. It is a backward analysis:
+/// a read makes a local live before it, and an assignment to the whole
+/// local makes it dead before it.
+///
+/// For example, in code like the following, we have corresponding
+/// dataflow information shown in the right-hand comments.
+///
+/// ```rust
+/// fn foo(pred: bool) -> u32 {                // live:
+///                                            // {pred      }
+///     let mut a = 1; let b = 2;              // {pred,    b}
+///
+///     if pred {
+///         a = b;                             // {      a   }
+///
+///     } else {
+///         a = 3;                             // {      a   }
+///
+///     }                                      // {      a   }
+///
+///     a
+/// }
+/// ```
+///
+/// Only whole locals are tracked, and anything done with a local other
+/// than assigning to it as a whole, such as borrowing it or assigning to
+/// one of its fields, counts as a read. Reads through a borrow are not
+/// seen at all, so the liveness of a borrowed local means nothing.
+pub struct LiveLocals<'a, 'tcx: 'a> {
+    mir: &'a Mir<'tcx>,
+}
+
+impl<'a, 'tcx: 'a> LiveLocals<'a, 'tcx> {
+    pub fn new(mir: &'a Mir<'tcx>) -> Self {
+        LiveLocals { mir: mir }
+    }
+
+    pub fn mir(&self) -> &'a Mir<'tcx> { self.mir }
+}
+
+/// The locals live before each statement of `bb`, before its terminator
+/// and on exit from it, in that order.
+pub fn block_liveness(results: &DataflowResults<LiveLocals>,
+                      mir: &Mir,
+                      bb: mir::BasicBlock)
+                      -> Vec<IdxSetBuf<Local>>
+{
+    let data = &mir[bb];
+    let mut live = results.sets().on_entry_set_for(bb.index()).to_owned();
+    let mut sets = vec![live.clone()];
+    let loc = Location { block: bb, statement_index: data.statements.len() };
+    LocalUses::of_terminator(data.terminator(), loc).apply(&mut live);
+    sets.push(live.clone());
+    for (i, stmt) in data.statements.iter().enumerate().rev() {
+        let loc = Location { block: bb, statement_index: i };
+        LocalUses::of_statement(stmt, loc).apply(&mut live);
+        sets.push(live.clone());
+    }
+    sets.reverse();
+    sets
+}
+
 impl<'a, 'tcx> MaybeInitializedLvals<'a, 'tcx> {
     fn update_bits(sets: &mut BlockSets<MovePathIndex>, path: MovePathIndex,
                    state: DropFlagState)
@@ -520,6 +584,119 @@ fn zero_to_one(bitvec: &mut [usize], move_index: MoveOutIndex) {
     assert!(retval);
 }
 
+impl<'a, 'tcx> BitDenotation for LiveLocals<'a, 'tcx> {
+    type Idx = Local;
+    fn name() -> &'static str { "live_locals" }
+    fn bits_per_block(&self) -> usize {
+        self.mir.local_decls.len()
+    }
+
+    fn is_backward() -> bool { true }
+
+    fn start_block_effect(&self, _sets: &mut BlockSets<Local>) {
+        // not used by backward analyses
+    }
+
+    fn statement_effect(&self,
+                        sets: &mut BlockSets<Local>,
+                        bb: mir::BasicBlock,
+                        idx: usize)
+    {
+        let loc = Location { block: bb, statement_index: idx };
+        LocalUses::of_statement(&self.mir[bb].statements[idx], loc).apply_to_sets(sets);
+    }
+
+    fn terminator_effect(&self,
+                         sets: &mut BlockSets<Local>,
+                         bb: mir::BasicBlock,
+                         statements_len: usize)
+    {
+        let loc = Location { block: bb, statement_index: statements_len };
+        LocalUses::of_terminator(self.mir[bb].terminator(), loc).apply_to_sets(sets);
+    }
+
+    fn propagate_call_return(&self,
+                             in_out: &mut IdxSet<Local>,
+                             _call_bb: mir::BasicBlock,
+                             _dest_bb: mir::BasicBlock,
+                             dest_lval: &mir::Lvalue) {
+        // the returned value overwrites the destination, unless it is
+        // only a part of some local
+        if let mir::Lvalue::Local(local) = *dest_lval {
+            in_out.remove(&local);
+        }
+    }
+}
+
+/// The locals which a statement or a terminator reads, and the local
+/// which it assigns as a whole, as seen by `LiveLocals`.
+///
+/// The destination of a call is assigned when the call returns, so it
+/// is neither a read nor the assigned local of the call terminator.
+#[derive(Debug)]
+pub struct LocalUses {
+    pub def: Option<Local>,
+    pub uses: Vec<Local>,
+}
+
+impl LocalUses {
+    pub fn of_statement(stmt: &mir::Statement, loc: Location) -> LocalUses {
+        let mut uses = LocalUses { def: None, uses: vec![] };
+        match stmt.kind {
+            mir::StatementKind::Assign(mir::Lvalue::Local(local), ref rvalue) => {
+                uses.def = Some(local);
+                uses.visit_rvalue(rvalue, loc);
+            }
+            _ => uses.visit_statement(loc.block, stmt, loc),
+        }
+        uses
+    }
+
+    pub fn of_terminator(term: &mir::Terminator, loc: Location) -> LocalUses {
+        let mut uses = LocalUses { def: None, uses: vec![] };
+        if let mir::TerminatorKind::Return = term.kind {
+            uses.uses.push(mir::RETURN_POINTER);
+        }
+        uses.visit_terminator(loc.block, term, loc);
+        uses
+    }
+
+    /// Turns the set of the locals live after the statement or terminator
+    /// into the set of those live before it.
+    pub fn apply(&self, live: &mut IdxSet<Local>) {
+        if let Some(def) = self.def {
+            live.remove(&def);
+        }
+        for local in &self.uses {
+            live.add(local);
+        }
+    }
+
+    fn apply_to_sets(&self, sets: &mut BlockSets<Local>) {
+        if let Some(def) = self.def {
+            sets.kill(&def);
+        }
+        for local in &self.uses {
+            sets.gen(local);
+        }
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for LocalUses {
+    fn visit_lvalue(&mut self,
+                    lvalue: &mir::Lvalue<'tcx>,
+                    context: LvalueContext<'tcx>,
+                    location: Location) {
+        match (lvalue, context) {
+            (&mir::Lvalue::Local(_), LvalueContext::StorageLive) |
+            (&mir::Lvalue::Local(_), LvalueContext::StorageDead) |
+            (&mir::Lvalue::Local(_), LvalueContext::Call) => {}
+            (&mir::Lvalue::Local(local), _) => self.uses.push(local),
+            _ => self.super_lvalue(lvalue, context, location),
+        }
+    }
+}
+
 impl<'a, 'tcx> BitwiseOperator for MovingOutStatements<'a, 'tcx> {
     #[inline]
     fn join(&self, pred1: usize, pred2: usize) -> usize {
@@ -548,6 +725,13 @@ impl<'a, 'tcx> BitwiseOperator for DefinitelyInitializedLvals<'a, 'tcx> {
     }
 }
 
+impl<'a, 'tcx> BitwiseOperator for LiveLocals<'a, 'tcx> {
+    #[inline]
+    fn join(&self, succ1: usize, succ2: usize) -> usize {
+        succ1 | succ2 // live if it might be read after either succ
+    }
+}
+
 // The way that dataflow fixed point iteration works, you want to
 // start at bottom and work your way to a fixed point. Control-flow
 // merges will apply the `join` operator to each block entry's current
@@ -585,3 +769,10 @@ impl<'a, 'tcx> DataflowOperator for DefinitelyInitializedLvals<'a, 'tcx> {
         true // bottom = initialized (start_block_effect counters this at outset)
     }
 }
+
+impl<'a, 'tcx> DataflowOperator for LiveLocals<'a, 'tcx> {
+    #[inline]
+    fn bottom_value() -> bool {
+        false // bottom = dead
+    }
+}
@@ -1,4 +1,4 @@
-// Copyright 2012-2016 The Rust Project Developers. See the COPYRIGHT
+// Copyright 2012-2017 The Rust Project Developers. See the COPYRIGHT
 // file at the top-level directory of this distribution and at
 // http://rust-lang.org/COPYRIGHT.
 //
@@ -8,34 +8,130 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+//! Bit-vector dataflow analyses over MIR.
+//!
+//! An analysis is a `BitDenotation`, which says what each bit stands for
+//! and what the statements and terminators of a block do to the bits, as
+//! gen and kill sets, together with a `DataflowOperator`, which says how
+//! the bits coming from different blocks are joined. An analysis flows
+//! forward, from the entry of the function, unless it says it flows
+//! backward.
+//!
+//! `DataflowAnalysis` builds the gen and kill sets of every block and
+//! solves the analysis with a worklist, after which the bits on entry to
+//! every block are in its `DataflowResults`. The bits at a point within
+//! a block are found by applying the effects of the statements before it
+//! to the entry set of the block. `do_dataflow` does all of this, and
+//! can render the sets with graphviz along the way.
+//!
+//! The move paths of `move_paths` and the analyses of `impls`, which
+//! track which lvalues are initialized and which locals are live, are
+//! built on this.
+
 use rustc_data_structures::indexed_set::{IdxSet, IdxSetBuf};
 use rustc_data_structures::indexed_vec::Idx;
 use rustc_data_structures::bitslice::{bitwise, BitwiseOperator};
 
+use syntax::ast::{self, MetaItem};
+
 use rustc::ty::TyCtxt;
 use rustc::mir::{self, Mir};
+use rustc::session::Session;
 
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::io;
 use std::mem;
 use std::path::PathBuf;
 use std::usize;
 
-use super::MirBorrowckCtxtPreDataflow;
-
-pub use self::sanity_check::sanity_check_via_rustc_peek;
 pub use self::impls::{MaybeInitializedLvals, MaybeUninitializedLvals};
 pub use self::impls::{DefinitelyInitializedLvals, MovingOutStatements};
-
-mod graphviz;
-mod sanity_check;
+pub use self::impls::{block_liveness, LiveLocals, LocalUses};
+pub use self::drop_flag_effects::{DropFlagState, MoveDataParamEnv};
+pub use self::drop_flag_effects::{drop_flag_effects_for_function_entry};
+pub use self::drop_flag_effects::{drop_flag_effects_for_location};
+pub use self::drop_flag_effects::{move_path_children_matching};
+pub use self::drop_flag_effects::{on_all_children_bits, on_lookup_result_bits};
+
+pub mod graphviz;
+pub mod move_paths;
+mod drop_flag_effects;
 mod impls;
 
-pub trait Dataflow<BD: BitDenotation> {
-    fn dataflow<P>(&mut self, p: P) where P: Fn(&BD, BD::Idx) -> &Debug;
+/// Finds the `#[rustc_mir(name)]` or `#[rustc_mir(name = "...")]`
+/// attribute among `attrs`, which are used to test and debug analyses.
+pub fn has_rustc_mir_with(attrs: &[ast::Attribute], name: &str) -> Option<MetaItem> {
+    for attr in attrs {
+        if attr.check_name("rustc_mir") {
+            let items = attr.meta_item_list();
+            for item in items.iter().flat_map(|l| l.iter()) {
+                match item.meta_item() {
+                    Some(mi) if mi.check_name(name) => return Some(mi.clone()),
+                    _ => continue
+                }
+            }
+        }
+    }
+    return None;
+}
+
+/// Builds the sets of `bd` over `mir` and solves them.
+///
+/// The sets are rendered with graphviz before and after solving if the
+/// item has a `#[rustc_mir(borrowck_graphviz_preflow = "path")]` or a
+/// `#[rustc_mir(borrowck_graphviz_postflow = "path")]` attribute, in a
+/// file named after the analysis next to `path`. `p` gives what is shown
+/// for a bit.
+pub fn do_dataflow<'a, 'tcx, BD, P>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                                    mir: &Mir<'tcx>,
+                                    node_id: ast::NodeId,
+                                    attributes: &[ast::Attribute],
+                                    bd: BD,
+                                    p: P)
+                                    -> DataflowResults<BD>
+    where BD: BitDenotation + DataflowOperator,
+          P: Fn(&BD, BD::Idx) -> &Debug
+{
+    let name_found = |sess: &Session, attrs: &[ast::Attribute], name| -> Option<String> {
+        if let Some(item) = has_rustc_mir_with(attrs, name) {
+            if let Some(s) = item.value_str() {
+                return Some(s.to_string())
+            } else {
+                sess.span_err(
+                    item.span,
+                    &format!("{} attribute requires a path", item.name()));
+                return None;
+            }
+        }
+        return None;
+    };
+
+    let print_preflow_to =
+        name_found(tcx.sess, attributes, "borrowck_graphviz_preflow");
+    let print_postflow_to =
+        name_found(tcx.sess, attributes, "borrowck_graphviz_postflow");
+
+    let mut builder = DataflowBuilder {
+        node_id: node_id,
+        print_preflow_to: print_preflow_to,
+        print_postflow_to: print_postflow_to,
+        flow_state: DataflowAnalysis::new(tcx, mir, bd),
+    };
+
+    builder.dataflow(p);
+    builder.flow_state.results()
+}
+
+struct DataflowBuilder<'a, 'tcx: 'a, BD> where BD: BitDenotation
+{
+    node_id: ast::NodeId,
+    flow_state: DataflowAnalysis<'a, 'tcx, BD>,
+    print_preflow_to: Option<String>,
+    print_postflow_to: Option<String>,
 }
 
-impl<'a, 'tcx: 'a, BD> Dataflow<BD> for MirBorrowckCtxtPreDataflow<'a, 'tcx, BD>
+impl<'a, 'tcx: 'a, BD> DataflowBuilder<'a, 'tcx, BD>
     where BD: BitDenotation + DataflowOperator
 {
     fn dataflow<P>(&mut self, p: P) where P: Fn(&BD, BD::Idx) -> &Debug {
@@ -46,37 +142,83 @@ impl<'a, 'tcx: 'a, BD> Dataflow<BD> for MirBorrowckCtxtPreDataflow<'a, 'tcx, BD>
     }
 }
 
-struct PropagationContext<'b, 'a: 'b, 'tcx: 'a, O>
-    where O: 'b + BitDenotation
-{
-    builder: &'b mut DataflowAnalysis<'a, 'tcx, O>,
-    changed: bool,
+/// The blocks whose entry sets changed since their bits were last
+/// propagated, in the order in which they are to be visited.
+struct WorkQueue {
+    queue: VecDeque<mir::BasicBlock>,
+    queued: Vec<bool>,
+}
+
+impl WorkQueue {
+    fn insert(&mut self, bb: mir::BasicBlock) {
+        if !self.queued[bb.index()] {
+            self.queued[bb.index()] = true;
+            self.queue.push_back(bb);
+        }
+    }
+
+    fn pop(&mut self) -> Option<mir::BasicBlock> {
+        let bb = self.queue.pop_front();
+        if let Some(bb) = bb {
+            self.queued[bb.index()] = false;
+        }
+        bb
+    }
 }
 
 impl<'a, 'tcx: 'a, BD> DataflowAnalysis<'a, 'tcx, BD>
     where BD: BitDenotation + DataflowOperator
 {
-    fn propagate(&mut self) {
-        let mut temp = IdxSetBuf::new_empty(self.flow_state.sets.bits_per_block);
-        let mut propcx = PropagationContext {
-            builder: self,
-            changed: true,
+    /// Solves the analysis, once its gen and kill sets are built.
+    ///
+    /// Every block is visited once, in the direction of the analysis, and
+    /// after that only the blocks whose entry sets changed are, until
+    /// none do.
+    pub fn propagate(&mut self) {
+        let mir = self.mir;
+        let num_blocks = mir.basic_blocks().len();
+        let mut dirty = WorkQueue {
+            queue: VecDeque::with_capacity(num_blocks),
+            queued: vec![false; num_blocks],
         };
-        while propcx.changed {
-            propcx.changed = false;
-            propcx.reset(&mut temp);
-            propcx.walk_cfg(&mut temp);
+        if BD::is_backward() {
+            for bb in mir.basic_blocks().indices().rev() {
+                dirty.insert(bb);
+            }
+        } else {
+            for bb in mir.basic_blocks().indices() {
+                dirty.insert(bb);
+            }
+        }
+
+        let mut in_out = IdxSetBuf::new_empty(self.flow_state.sets.bits_per_block);
+        while let Some(bb) = dirty.pop() {
+            {
+                let sets = self.flow_state.sets.for_block(bb.index());
+                debug_assert!(in_out.words().len() == sets.on_entry.words().len());
+                in_out.clone_from(sets.on_entry);
+                in_out.union(sets.gen_set);
+                in_out.subtract(sets.kill_set);
+            }
+            if BD::is_backward() {
+                self.propagate_bits_into_graph_predecessors_of(&in_out, &mut dirty, bb);
+            } else {
+                self.propagate_bits_into_graph_successors_of(
+                    &mut in_out, &mut dirty, (bb, &mir[bb]));
+            }
         }
     }
 
-    fn build_sets(&mut self) {
+    /// Builds the gen and kill sets of every block, and the entry set of
+    /// the start block.
+    pub fn build_sets(&mut self) {
         // First we need to build the entry-, gen- and kill-sets. The
         // gather_moves information provides a high-level mapping from
         // mir-locations to the MoveOuts (and those correspond
         // directly to gen-sets here). But we still need to figure out
         // the kill-sets.
 
-        {
+        if !BD::is_backward() {
             let sets = &mut self.flow_state.sets.for_block(mir::START_BLOCK.index());
             self.flow_state.operator.start_block_effect(sets);
         }
@@ -85,41 +227,24 @@ impl<'a, 'tcx: 'a, BD> DataflowAnalysis<'a, 'tcx, BD>
             let &mir::BasicBlockData { ref statements, ref terminator, is_cleanup: _ } = data;
 
             let sets = &mut self.flow_state.sets.for_block(bb.index());
-            for j_stmt in 0..statements.len() {
-                self.flow_state.operator.statement_effect(sets, bb, j_stmt);
-            }
-
-            if terminator.is_some() {
-                let stmts_len = statements.len();
-                self.flow_state.operator.terminator_effect(sets, bb, stmts_len);
-            }
-        }
-    }
-}
-
-impl<'b, 'a: 'b, 'tcx: 'a, BD> PropagationContext<'b, 'a, 'tcx, BD>
-    where BD: BitDenotation + DataflowOperator
-{
-    fn reset(&mut self, bits: &mut IdxSet<BD::Idx>) {
-        let e = if BD::bottom_value() {!0} else {0};
-        for b in bits.words_mut() {
-            *b = e;
-        }
-    }
-
-    fn walk_cfg(&mut self, in_out: &mut IdxSet<BD::Idx>) {
-        let mir = self.builder.mir;
-        for (bb_idx, bb_data) in mir.basic_blocks().iter().enumerate() {
-            let builder = &mut self.builder;
-            {
-                let sets = builder.flow_state.sets.for_block(bb_idx);
-                debug_assert!(in_out.words().len() == sets.on_entry.words().len());
-                in_out.clone_from(sets.on_entry);
-                in_out.union(sets.gen_set);
-                in_out.subtract(sets.kill_set);
+            let stmts_len = statements.len();
+            if BD::is_backward() {
+                // The effects are applied in the order in which the
+                // analysis flows through the block.
+                if terminator.is_some() {
+                    self.flow_state.operator.terminator_effect(sets, bb, stmts_len);
+                }
+                for j_stmt in (0..stmts_len).rev() {
+                    self.flow_state.operator.statement_effect(sets, bb, j_stmt);
+                }
+            } else {
+                for j_stmt in 0..stmts_len {
+                    self.flow_state.operator.statement_effect(sets, bb, j_stmt);
+                }
+                if terminator.is_some() {
+                    self.flow_state.operator.terminator_effect(sets, bb, stmts_len);
+                }
             }
-            builder.propagate_bits_into_graph_successors_of(
-                in_out, &mut self.changed, (mir::BasicBlock::new(bb_idx), bb_data));
         }
     }
 }
@@ -135,7 +260,7 @@ fn dataflow_path(context: &str, prepost: &str, path: &str) -> PathBuf {
     path
 }
 
-impl<'a, 'tcx: 'a, BD> MirBorrowckCtxtPreDataflow<'a, 'tcx, BD>
+impl<'a, 'tcx: 'a, BD> DataflowBuilder<'a, 'tcx, BD>
     where BD: BitDenotation
 {
     fn pre_dataflow_instrumentation<P>(&self, p: P) -> io::Result<()>
@@ -143,7 +268,7 @@ impl<'a, 'tcx: 'a, BD> MirBorrowckCtxtPreDataflow<'a, 'tcx, BD>
     {
         if let Some(ref path_str) = self.print_preflow_to {
             let path = dataflow_path(BD::name(), "preflow", path_str);
-            graphviz::print_borrowck_graph_to(self, &path, p)
+            graphviz::print_dataflow_graph_to(self.node_id, &self.flow_state, &path, p)
         } else {
             Ok(())
         }
@@ -154,8 +279,8 @@ impl<'a, 'tcx: 'a, BD> MirBorrowckCtxtPreDataflow<'a, 'tcx, BD>
     {
         if let Some(ref path_str) = self.print_postflow_to {
             let path = dataflow_path(BD::name(), "postflow", path_str);
-            graphviz::print_borrowck_graph_to(self, &path, p)
-        } else{
+            graphviz::print_dataflow_graph_to(self.node_id, &self.flow_state, &path, p)
+        } else {
             Ok(())
         }
     }
@@ -191,6 +316,8 @@ impl<'a, 'tcx: 'a, O> DataflowAnalysis<'a, 'tcx, O>
         DataflowResults(self.flow_state)
     }
 
+    pub fn flow_state(&self) -> &DataflowState<O> { &self.flow_state }
+
     pub fn mir(&self) -> &'a Mir<'tcx> { self.mir }
 }
 
@@ -200,10 +327,12 @@ impl<O: BitDenotation> DataflowResults<O> {
     pub fn sets(&self) -> &AllSets<O::Idx> {
         &self.0.sets
     }
+
+    pub fn operator(&self) -> &O {
+        &self.0.operator
+    }
 }
 
-// FIXME: This type shouldn't be public, but the graphviz::MirWithFlowState trait
-// references it in a method signature. Look into using `pub(crate)` to address this.
 pub struct DataflowState<O: BitDenotation>
 {
     /// All the sets for the analysis. (Factored into its
@@ -234,22 +363,27 @@ pub struct AllSets<E: Idx> {
     /// handled in a flow-specific manner during propagation.)
     kill_sets: Bits<E>,
 
-    /// For each block, bits valid on entry to the block.
+    /// For each block, bits valid on entry to the block. For a
+    /// backward analysis, the flow enters a block at its end, so these
+    /// are the bits valid on exit from the block.
     on_entry_sets: Bits<E>,
 }
 
+/// The sets of one block, which its effects are applied to.
 pub struct BlockSets<'a, E: Idx> {
-    on_entry: &'a mut IdxSet<E>,
-    gen_set: &'a mut IdxSet<E>,
-    kill_set: &'a mut IdxSet<E>,
+    pub on_entry: &'a mut IdxSet<E>,
+    pub gen_set: &'a mut IdxSet<E>,
+    pub kill_set: &'a mut IdxSet<E>,
 }
 
 impl<'a, E:Idx> BlockSets<'a, E> {
-    fn gen(&mut self, e: &E) {
+    /// Sets the bit `e` after the effects applied so far.
+    pub fn gen(&mut self, e: &E) {
         self.gen_set.add(e);
         self.kill_set.remove(e);
     }
-    fn kill(&mut self, e: &E) {
+    /// Clears the bit `e` after the effects applied so far.
+    pub fn kill(&mut self, e: &E) {
         self.gen_set.remove(e);
         self.kill_set.add(e);
     }
@@ -302,6 +436,15 @@ pub trait BitDenotation {
     /// plugged into a filename.
     fn name() -> &'static str;
 
+    /// Whether the analysis flows backward, from the exits of the
+    /// function towards its entry, rather than forward.
+    ///
+    /// The effects of a block are then applied starting with its
+    /// terminator and ending with its first statement, the `on_entry`
+    /// set of a block holds the bits on exit from it, and
+    /// `start_block_effect` is not used.
+    fn is_backward() -> bool { false }
+
     /// Size of each bitvector allocated for each block in the analysis.
     fn bits_per_block(&self) -> usize;
 
@@ -361,6 +504,10 @@ pub trait BitDenotation {
     /// GEN and KILL sets attached to the block, and so instead we add
     /// this extra machinery to represent the flow-dependent effect.
     ///
+    /// In a backward analysis, this is instead called on the entry
+    /// flow-state of BB_y in order to set up the exit flow-state of
+    /// BB_x.
+    ///
     /// FIXME: Right now this is a bit of a wart in the API. It might
     /// be better to represent this as an additional gen- and
     /// kill-sets associated with each edge coming out of the basic
@@ -431,7 +578,7 @@ impl<'a, 'tcx: 'a, D> DataflowAnalysis<'a, 'tcx, D>
     fn propagate_bits_into_graph_successors_of(
         &mut self,
         in_out: &mut IdxSet<D::Idx>,
-        changed: &mut bool,
+        dirty: &mut WorkQueue,
         (bb, bb_data): (mir::BasicBlock, &mir::BasicBlockData))
     {
         match bb_data.terminator().kind {
@@ -444,51 +591,79 @@ impl<'a, 'tcx: 'a, D> DataflowAnalysis<'a, 'tcx, D>
             mir::TerminatorKind::DropAndReplace {
                 ref target, value: _, location: _, unwind: None
             } => {
-                self.propagate_bits_into_entry_set_for(in_out, changed, target);
+                self.propagate_bits_into_entry_set_for(in_out, dirty, target);
             }
             mir::TerminatorKind::Assert { ref target, cleanup: Some(ref unwind), .. } |
             mir::TerminatorKind::Drop { ref target, location: _, unwind: Some(ref unwind) } |
             mir::TerminatorKind::DropAndReplace {
                 ref target, value: _, location: _, unwind: Some(ref unwind)
             } => {
-                self.propagate_bits_into_entry_set_for(in_out, changed, target);
-                self.propagate_bits_into_entry_set_for(in_out, changed, unwind);
+                self.propagate_bits_into_entry_set_for(in_out, dirty, target);
+                self.propagate_bits_into_entry_set_for(in_out, dirty, unwind);
             }
             mir::TerminatorKind::If { ref targets, .. } => {
-                self.propagate_bits_into_entry_set_for(in_out, changed, &targets.0);
-                self.propagate_bits_into_entry_set_for(in_out, changed, &targets.1);
+                self.propagate_bits_into_entry_set_for(in_out, dirty, &targets.0);
+                self.propagate_bits_into_entry_set_for(in_out, dirty, &targets.1);
             }
             mir::TerminatorKind::Switch { ref targets, .. } |
             mir::TerminatorKind::SwitchInt { ref targets, .. } => {
                 for target in targets {
-                    self.propagate_bits_into_entry_set_for(in_out, changed, target);
+                    self.propagate_bits_into_entry_set_for(in_out, dirty, target);
                 }
             }
             mir::TerminatorKind::Call { ref cleanup, ref destination, func: _, args: _ } => {
                 if let Some(ref unwind) = *cleanup {
-                    self.propagate_bits_into_entry_set_for(in_out, changed, unwind);
+                    self.propagate_bits_into_entry_set_for(in_out, dirty, unwind);
                 }
                 if let Some((ref dest_lval, ref dest_bb)) = *destination {
                     // N.B.: This must be done *last*, after all other
                     // propagation, as documented in comment above.
                     self.flow_state.operator.propagate_call_return(
                         in_out, bb, *dest_bb, dest_lval);
-                    self.propagate_bits_into_entry_set_for(in_out, changed, dest_bb);
+                    self.propagate_bits_into_entry_set_for(in_out, dirty, dest_bb);
+                }
+            }
+        }
+    }
+
+    /// Propagates the bits of `in_out`, which hold on entry to `bb`,
+    /// into the exit sets of all the predecessors of `bb`, for a backward
+    /// analysis.
+    ///
+    /// As in the forward direction, the effect of a call returning
+    /// successfully is only applied along the edge of that return.
+    fn propagate_bits_into_graph_predecessors_of(
+        &mut self,
+        in_out: &IdxSet<D::Idx>,
+        dirty: &mut WorkQueue,
+        bb: mir::BasicBlock)
+    {
+        let mir = self.mir;
+        for &pred in mir.predecessors_for(bb).iter() {
+            match mir[pred].terminator().kind {
+                mir::TerminatorKind::Call {
+                    destination: Some((ref dest_lval, dest_bb)), ..
+                } if dest_bb == bb => {
+                    let mut bits = in_out.to_owned();
+                    self.flow_state.operator.propagate_call_return(
+                        &mut bits, pred, bb, dest_lval);
+                    self.propagate_bits_into_entry_set_for(&bits, dirty, &pred);
                 }
+                _ => self.propagate_bits_into_entry_set_for(in_out, dirty, &pred),
             }
         }
     }
 
     fn propagate_bits_into_entry_set_for(&mut self,
                                          in_out: &IdxSet<D::Idx>,
-                                         changed: &mut bool,
+                                         dirty: &mut WorkQueue,
                                          bb: &mir::BasicBlock) {
         let entry_set = self.flow_state.sets.for_block(bb.index()).on_entry;
         let set_changed = bitwise(entry_set.words_mut(),
                                   in_out.words(),
                                   &self.flow_state.operator);
         if set_changed {
-            *changed = true;
+            dirty.insert(*bb);
         }
     }
 }
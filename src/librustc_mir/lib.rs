@@ -22,6 +22,7 @@ Rust MIR: a lowered representation of Rust. Also: an experiment!
 
 #![feature(associated_consts)]
 #![feature(box_patterns)]
+#![feature(nonzero)]
 #![feature(rustc_diagnostic_macros)]
 #![feature(rustc_private)]
 #![feature(staged_api)]
@@ -43,9 +44,12 @@ extern crate rustc_const_eval;
 
 extern crate rustc_i128;
 
+extern crate core; // for NonZero
+
 pub mod diagnostics;
 
 pub mod build;
+pub mod dataflow;
 pub mod def_use;
 pub mod graphviz;
 mod hair;
@@ -0,0 +1,495 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Constant propagation.
+//!
+//! A local which is assigned exactly once, and is never borrowed mutably,
+//! holds the value of that assignment whenever it is read. If the assigned
+//! rvalue can be computed from constants and from other such locals, the
+//! local is a constant. Arithmetic, comparisons, casts of integers and the
+//! lengths of arrays are computed; anything else is left alone.
+//!
+//! `ConstProp` replaces the reads of constant locals by their values and
+//! folds the operations on them. The conditions of `if`s and assertions
+//! become constants for `SimplifyBranches` to fold, and switches on constant
+//! integers become gotos.
+//!
+//! `ConstPropLint` reports the assertions which always fail as `const_err`
+//! lints, since the code panics whenever it reaches them. Expressions which
+//! are constant by themselves are checked on the HIR already, so only the
+//! failures which depend on the value of a variable are reported here.
+
+use def_use::DefUseAnalysis;
+use rustc::lint::builtin::CONST_ERR;
+use rustc::middle::const_val::ConstVal;
+use rustc::mir::*;
+use rustc::mir::transform::{MirPass, MirSource, Pass};
+use rustc::mir::visit::{LvalueContext, MutVisitor};
+use rustc::ty::{self, Ty, TyCtxt};
+use rustc_const_eval::cast_const;
+use rustc_const_math::{ConstInt, ConstMathErr, ConstUsize};
+use rustc_data_structures::indexed_vec::{Idx, IndexVec};
+use syntax_pos::Span;
+use transform::qualify_consts;
+
+use std::cmp::Ordering;
+
+pub struct ConstProp;
+
+impl Pass for ConstProp {}
+
+impl<'tcx> MirPass<'tcx> for ConstProp {
+    fn run_pass<'a>(&mut self,
+                    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                    source: MirSource,
+                    mir: &mut Mir<'tcx>) {
+        match source {
+            MirSource::Fn(id) => {
+                // Trans evaluates the MIR of const functions, which must stay
+                // acceptable to it.
+                if qualify_consts::is_const_fn(tcx, tcx.map.local_def_id(id)) {
+                    return;
+                }
+            }
+            MirSource::Const(_) | MirSource::Static(..) | MirSource::Promoted(..) => return,
+        }
+
+        // We only run when the MIR optimization level is > 1, like copy
+        // propagation, which makes more locals constant.
+        if tcx.sess.opts.debugging_opts.mir_opt_level <= 1 {
+            return;
+        }
+
+        let mut folder = ConstFolder {
+            constants: Constants::new(tcx, mir),
+            span: mir.span,
+        };
+        folder.visit_mir(mir);
+    }
+}
+
+pub struct ConstPropLint;
+
+impl Pass for ConstPropLint {}
+
+impl<'tcx> MirPass<'tcx> for ConstPropLint {
+    fn run_pass<'a>(&mut self,
+                    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                    source: MirSource,
+                    mir: &mut Mir<'tcx>) {
+        // Failures in constants are errors of their evaluation.
+        let id = match source {
+            MirSource::Fn(id) => id,
+            MirSource::Const(_) | MirSource::Static(..) | MirSource::Promoted(..) => return,
+        };
+
+        let constants = Constants::new(tcx, mir);
+        for data in mir.basic_blocks() {
+            let terminator = data.terminator();
+            if let TerminatorKind::Assert { ref cond, expected, ref msg, .. } = terminator.kind {
+                match constants.eval_operand(cond) {
+                    Some(Known { value: Value::Scalar(ConstVal::Bool(cond)), from_var })
+                        if cond != expected => {
+                        if let Some(message) = constants.failure_message(msg, from_var) {
+                            tcx.sess.add_lint(CONST_ERR, id, terminator.source_info.span, message);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// What is known about the value of a local.
+#[derive(Clone, Debug)]
+enum Value {
+    Scalar(ConstVal),
+    /// The result of a checked operation: its value, unless it overflows.
+    Checked(Option<ConstVal>),
+}
+
+#[derive(Clone, Debug)]
+struct Known {
+    value: Value,
+    /// Whether the value was computed from the value of a variable, which
+    /// the constant evaluation of the HIR knows nothing about.
+    from_var: bool,
+}
+
+/// The constant locals of a body.
+struct Constants<'a, 'tcx: 'a> {
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    local_decls: IndexVec<Local, LocalDecl<'tcx>>,
+    values: IndexVec<Local, Option<Known>>,
+}
+
+impl<'a, 'tcx> Constants<'a, 'tcx> {
+    fn new(tcx: TyCtxt<'a, 'tcx, 'tcx>, mir: &Mir<'tcx>) -> Constants<'a, 'tcx> {
+        let mut def_use_analysis = DefUseAnalysis::new(mir);
+        def_use_analysis.analyze(mir);
+
+        // The locals which are assigned once, with their assignments.
+        let mut assignments = Vec::new();
+        for local in mir.local_decls.indices() {
+            match mir.local_kind(local) {
+                LocalKind::Var | LocalKind::Temp => {}
+                LocalKind::Arg | LocalKind::ReturnPointer => continue,
+            }
+            let info = def_use_analysis.local_info(local);
+            if info.def_count() != 1 {
+                continue;
+            }
+            let def = info.defs_and_uses.iter().find(|u| u.context.is_mutating_use()).unwrap();
+            if let LvalueContext::Store = def.context {
+                let statement = &mir[def.location.block].statements[def.location.statement_index];
+                if let StatementKind::Assign(Lvalue::Local(_), ref rvalue) = statement.kind {
+                    assignments.push((local, rvalue));
+                }
+            }
+        }
+
+        let mut constants = Constants {
+            tcx: tcx,
+            local_decls: mir.local_decls.clone(),
+            values: IndexVec::from_elem_n(None, mir.local_decls.len()),
+        };
+        // A local becomes constant once the locals its value is computed
+        // from have, so repeat until no more do.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &(local, rvalue) in &assignments {
+                if constants.values[local].is_some() {
+                    continue;
+                }
+                if let Some(mut known) = constants.eval_rvalue(rvalue) {
+                    known.from_var |= mir.local_kind(local) == LocalKind::Var;
+                    constants.values[local] = Some(known);
+                    changed = true;
+                }
+            }
+        }
+        constants
+    }
+
+    fn eval_operand(&self, operand: &Operand<'tcx>) -> Option<Known> {
+        match *operand {
+            Operand::Constant(Constant { literal: Literal::Value { ref value }, .. }) => {
+                match *value {
+                    ConstVal::Integral(_) | ConstVal::Float(_) |
+                    ConstVal::Bool(_) | ConstVal::Char(_) => Some(Known {
+                        value: Value::Scalar(value.clone()),
+                        from_var: false,
+                    }),
+                    _ => None,
+                }
+            }
+            Operand::Constant(_) => None,
+            Operand::Consume(ref lvalue) => self.eval_lvalue(lvalue),
+        }
+    }
+
+    fn eval_lvalue(&self, lvalue: &Lvalue<'tcx>) -> Option<Known> {
+        match *lvalue {
+            Lvalue::Local(local) => self.values[local].clone(),
+            Lvalue::Projection(box LvalueProjection {
+                base: Lvalue::Local(local),
+                elem: ProjectionElem::Field(field, _),
+            }) => {
+                let known = match self.values[local] {
+                    Some(ref known) => known,
+                    None => return None,
+                };
+                let value = match (&known.value, field.index()) {
+                    (&Value::Checked(Some(ref value)), 0) => value.clone(),
+                    (&Value::Checked(ref value), 1) => ConstVal::Bool(value.is_none()),
+                    _ => return None,
+                };
+                Some(Known {
+                    value: Value::Scalar(value),
+                    from_var: known.from_var,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn eval_rvalue(&self, rvalue: &Rvalue<'tcx>) -> Option<Known> {
+        match *rvalue {
+            Rvalue::Use(ref operand) => self.eval_operand(operand),
+            Rvalue::Len(Lvalue::Local(local)) => {
+                let len = match self.local_decls[local].ty.sty {
+                    ty::TyArray(_, len) => len,
+                    _ => return None,
+                };
+                ConstUsize::new(len as u64, self.tcx.sess.target.uint_type).ok().map(|len| {
+                    Known {
+                        value: Value::Scalar(ConstVal::Integral(ConstInt::Usize(len))),
+                        from_var: self.local_decls[local].name.is_some(),
+                    }
+                })
+            }
+            Rvalue::Cast(CastKind::Misc, ref operand, ty) => {
+                let (value, from_var) = match self.eval_operand(operand) {
+                    Some(known) => (known.value, known.from_var),
+                    None => return None,
+                };
+                match value {
+                    // Casts of floats to integers which do not fit are
+                    // undefined, so leave them to run time.
+                    Value::Scalar(value @ ConstVal::Integral(_)) |
+                    Value::Scalar(value @ ConstVal::Bool(_)) |
+                    Value::Scalar(value @ ConstVal::Char(_)) => {
+                        cast_const(self.tcx, value, ty).ok().map(|value| Known {
+                            value: Value::Scalar(value),
+                            from_var: from_var,
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            Rvalue::BinaryOp(op, ref left, ref right) => {
+                self.eval_binop(op, left, right).and_then(|(result, from_var)| {
+                    result.ok().map(|value| Known {
+                        value: Value::Scalar(value),
+                        from_var: from_var,
+                    })
+                })
+            }
+            Rvalue::CheckedBinaryOp(op, ref left, ref right) => {
+                self.eval_binop(op, left, right).and_then(|(result, from_var)| {
+                    let value = match result {
+                        Ok(value) => Some(value),
+                        Err(ConstMathErr::Overflow(_)) | Err(ConstMathErr::ShiftNegative) => None,
+                        Err(_) => return None,
+                    };
+                    Some(Known {
+                        value: Value::Checked(value),
+                        from_var: from_var,
+                    })
+                })
+            }
+            Rvalue::UnaryOp(op, ref operand) => {
+                let known = match self.eval_operand(operand) {
+                    Some(known) => known,
+                    None => return None,
+                };
+                let value = match (op, known.value) {
+                    (UnOp::Not, Value::Scalar(ConstVal::Bool(b))) => ConstVal::Bool(!b),
+                    (UnOp::Not, Value::Scalar(ConstVal::Integral(i))) => {
+                        match !i {
+                            Ok(i) => ConstVal::Integral(i),
+                            Err(_) => return None,
+                        }
+                    }
+                    // An overflowing negation is asserted against separately.
+                    (UnOp::Neg, Value::Scalar(ConstVal::Integral(i))) => {
+                        match -i {
+                            Ok(i) => ConstVal::Integral(i),
+                            Err(_) => return None,
+                        }
+                    }
+                    _ => return None,
+                };
+                Some(Known {
+                    value: Value::Scalar(value),
+                    from_var: known.from_var,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The result of a binary operation on two known values, or the error
+    /// of the operation, and whether a value came from a variable.
+    fn eval_binop(&self, op: BinOp, left: &Operand<'tcx>, right: &Operand<'tcx>)
+                  -> Option<(Result<ConstVal, ConstMathErr>, bool)> {
+        let (left, right) = match (self.eval_operand(left), self.eval_operand(right)) {
+            (Some(left), Some(right)) => (left, right),
+            _ => return None,
+        };
+        let from_var = left.from_var || right.from_var;
+        let result = match (left.value, right.value) {
+            (Value::Scalar(ConstVal::Integral(l)), Value::Scalar(ConstVal::Integral(r))) => {
+                let result = match op {
+                    BinOp::Add => l + r,
+                    BinOp::Sub => l - r,
+                    BinOp::Mul => l * r,
+                    BinOp::Div => l / r,
+                    BinOp::Rem => l % r,
+                    BinOp::BitXor => l ^ r,
+                    BinOp::BitAnd => l & r,
+                    BinOp::BitOr => l | r,
+                    BinOp::Shl => l << r,
+                    BinOp::Shr => l >> r,
+                    BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt => {
+                        return Some((l.try_cmp(r).map(|ordering| {
+                            ConstVal::Bool(compare(op, ordering))
+                        }), from_var));
+                    }
+                    BinOp::Offset => return None,
+                };
+                result.map(ConstVal::Integral)
+            }
+            (Value::Scalar(ConstVal::Bool(l)), Value::Scalar(ConstVal::Bool(r))) => {
+                Ok(ConstVal::Bool(match op {
+                    BinOp::BitXor => l ^ r,
+                    BinOp::BitAnd => l & r,
+                    BinOp::BitOr => l | r,
+                    BinOp::Eq | BinOp::Lt | BinOp::Le | BinOp::Ne | BinOp::Ge | BinOp::Gt => {
+                        compare(op, l.cmp(&r))
+                    }
+                    _ => return None,
+                }))
+            }
+            _ => return None,
+        };
+        Some((result, from_var))
+    }
+
+    /// The message of an assertion which fails, if it should be reported.
+    fn failure_message(&self, msg: &AssertMessage<'tcx>, from_var: bool) -> Option<String> {
+        match *msg {
+            AssertMessage::BoundsCheck { ref len, ref index } => {
+                // Without `#![feature(const_indexing)]`, the HIR does not
+                // evaluate indexing.
+                if !from_var && self.tcx.sess.features.borrow().const_indexing {
+                    return None;
+                }
+                match (self.eval_operand(len), self.eval_operand(index)) {
+                    (Some(Known { value: Value::Scalar(ConstVal::Integral(len)), .. }),
+                     Some(Known { value: Value::Scalar(ConstVal::Integral(index)), .. })) => {
+                        Some(format!("index out of bounds: the len is {} but the index is {}",
+                                     len.to_u128_unchecked(), index.to_u128_unchecked()))
+                    }
+                    _ => None,
+                }
+            }
+            AssertMessage::Math(ref err) if from_var => Some(err.description().to_string()),
+            AssertMessage::Math(_) => None,
+        }
+    }
+
+    fn constant(&self, known: Option<Known>, ty: Ty<'tcx>, span: Span) -> Option<Operand<'tcx>> {
+        match known {
+            Some(Known { value: Value::Scalar(value), .. }) => {
+                Some(Operand::Constant(Constant {
+                    span: span,
+                    ty: ty,
+                    literal: Literal::Value { value: value },
+                }))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn compare(op: BinOp, ordering: Ordering) -> bool {
+    match op {
+        BinOp::Eq => ordering == Ordering::Equal,
+        BinOp::Ne => ordering != Ordering::Equal,
+        BinOp::Lt => ordering == Ordering::Less,
+        BinOp::Le => ordering != Ordering::Greater,
+        BinOp::Gt => ordering == Ordering::Greater,
+        BinOp::Ge => ordering != Ordering::Less,
+        _ => bug!("`{:?}` is not a comparison", op),
+    }
+}
+
+/// Replaces the reads of constant locals and the operations on them by
+/// constants.
+struct ConstFolder<'a, 'tcx: 'a> {
+    constants: Constants<'a, 'tcx>,
+    /// The span of the statement or terminator being folded.
+    span: Span,
+}
+
+impl<'a, 'tcx> MutVisitor<'tcx> for ConstFolder<'a, 'tcx> {
+    fn visit_statement(&mut self,
+                       block: BasicBlock,
+                       statement: &mut Statement<'tcx>,
+                       location: Location) {
+        self.span = statement.source_info.span;
+        self.super_statement(block, statement, location);
+    }
+
+    fn visit_terminator(&mut self,
+                        block: BasicBlock,
+                        terminator: &mut Terminator<'tcx>,
+                        location: Location) {
+        self.span = terminator.source_info.span;
+        self.super_terminator(block, terminator, location);
+    }
+
+    fn visit_assign(&mut self,
+                    block: BasicBlock,
+                    lvalue: &mut Lvalue<'tcx>,
+                    rvalue: &mut Rvalue<'tcx>,
+                    location: Location) {
+        self.super_assign(block, lvalue, rvalue, location);
+
+        let foldable = match *rvalue {
+            Rvalue::Len(_) | Rvalue::Cast(..) | Rvalue::BinaryOp(..) | Rvalue::UnaryOp(..) => true,
+            _ => false,
+        };
+        if let (true, &mut Lvalue::Local(local)) = (foldable, lvalue) {
+            let known = self.constants.eval_rvalue(rvalue);
+            let ty = self.constants.local_decls[local].ty;
+            if let Some(constant) = self.constants.constant(known, ty, self.span) {
+                *rvalue = Rvalue::Use(constant);
+            }
+        }
+    }
+
+    fn visit_operand(&mut self, operand: &mut Operand<'tcx>, location: Location) {
+        let constant = match *operand {
+            Operand::Consume(ref lvalue) => {
+                let ty = match *lvalue {
+                    Lvalue::Local(local) => Some(self.constants.local_decls[local].ty),
+                    Lvalue::Projection(box LvalueProjection {
+                        elem: ProjectionElem::Field(_, ty), ..
+                    }) => Some(ty),
+                    _ => None,
+                };
+                ty.and_then(|ty| {
+                    self.constants.constant(self.constants.eval_lvalue(lvalue), ty, self.span)
+                })
+            }
+            Operand::Constant(_) => None,
+        };
+        match constant {
+            Some(constant) => *operand = constant,
+            None => self.super_operand(operand, location),
+        }
+    }
+
+    fn visit_terminator_kind(&mut self,
+                             block: BasicBlock,
+                             kind: &mut TerminatorKind<'tcx>,
+                             location: Location) {
+        self.super_terminator_kind(block, kind, location);
+
+        let target = match *kind {
+            TerminatorKind::SwitchInt { ref discr, ref values, ref targets, .. } => {
+                match self.constants.eval_lvalue(discr) {
+                    Some(Known { value: Value::Scalar(ref value), .. }) => {
+                        let index = values.iter().position(|v| v == value);
+                        // The last target is taken if no value matches.
+                        targets[index.unwrap_or(values.len())]
+                    }
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+        *kind = TerminatorKind::Goto { target: target };
+    }
+}
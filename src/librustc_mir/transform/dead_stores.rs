@@ -0,0 +1,310 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Dead store elimination, and the placement of storage markers by
+//! liveness.
+//!
+//! An assignment to a local which is not live after it is removed, along
+//! with the assignments which become dead because of that. Then the
+//! storage of each local is made to start right before it is assigned,
+//! and to end right after it is last read, so that the storage of locals
+//! which are never live at the same time can be shared.
+//!
+//! Neither is done for a local which is borrowed, since it can be read
+//! through the borrow, and storage is only moved for locals which are
+//! only ever assigned as a whole, by assignments and calls.
+
+use dataflow::{block_liveness, do_dataflow, DataflowResults, LiveLocals, LocalUses};
+use rustc::mir::*;
+use rustc::mir::transform::{MirPass, MirSource, Pass};
+use rustc::mir::visit::Visitor;
+use rustc::ty::TyCtxt;
+use rustc_data_structures::bitvec::BitVector;
+use rustc_data_structures::indexed_vec::Idx;
+use transform::qualify_consts;
+use syntax::ast;
+
+pub struct DeadStoreElimination;
+
+impl<'tcx> MirPass<'tcx> for DeadStoreElimination {
+    fn run_pass<'a>(&mut self, tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                    src: MirSource, mir: &mut Mir<'tcx>)
+    {
+        debug!("dead_store_elimination({:?} @ {:?})", src, mir.span);
+        match src {
+            MirSource::Fn(id) => {
+                if qualify_consts::is_const_fn(tcx, tcx.map.local_def_id(id)) {
+                    return;
+                }
+            }
+            _ => return
+        }
+
+        // Like copy propagation, this only runs when the MIR optimization
+        // level is > 1.
+        if tcx.sess.opts.debugging_opts.mir_opt_level <= 1 {
+            return;
+        }
+
+        let id = src.item_id();
+        let accesses = Accesses::new(mir);
+        // Removing a store can make the stores of the values it read dead.
+        while remove_dead_stores(tcx, mir, id, &accesses) {}
+        place_storage_markers(tcx, mir, id, &accesses);
+    }
+}
+
+impl Pass for DeadStoreElimination {}
+
+fn liveness<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                      mir: &'a Mir<'tcx>,
+                      id: ast::NodeId)
+                      -> DataflowResults<LiveLocals<'a, 'tcx>>
+{
+    do_dataflow(tcx, mir, id, &[], LiveLocals::new(mir),
+                |bd, local| &bd.mir().local_decls[local])
+}
+
+fn remove_dead_stores<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                                mir: &mut Mir<'tcx>,
+                                id: ast::NodeId,
+                                accesses: &Accesses)
+                                -> bool
+{
+    let dead = {
+        let mir = &*mir;
+        let results = liveness(tcx, mir, id);
+        let mut dead = vec![];
+        for bb in mir.basic_blocks().indices() {
+            let live = block_liveness(&results, mir, bb);
+            for (i, stmt) in mir[bb].statements.iter().enumerate() {
+                if let StatementKind::Assign(Lvalue::Local(local), ref rvalue) = stmt.kind {
+                    let removable = match *rvalue {
+                        Rvalue::InlineAsm { .. } => false,
+                        _ => true,
+                    };
+                    if removable && !live[i + 1].contains(&local) &&
+                        !accesses.borrowed.contains(local.index()) {
+                        debug!("removing dead store {:?} at {:?}", stmt, (bb, i));
+                        dead.push(Location { block: bb, statement_index: i });
+                    }
+                }
+            }
+        }
+        dead
+    };
+
+    for loc in &dead {
+        mir[loc.block].statements[loc.statement_index].make_nop();
+    }
+    !dead.is_empty()
+}
+
+fn place_storage_markers<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                                   mir: &mut Mir<'tcx>,
+                                   id: ast::NodeId,
+                                   accesses: &Accesses)
+{
+    let new_statements: Vec<_> = {
+        let mir = &*mir;
+        let results = liveness(tcx, mir, id);
+        let mut placed = BitVector::new(mir.local_decls.len());
+        for i in accesses.has_storage.iter() {
+            if !accesses.borrowed.contains(i) && !accesses.partially_written.contains(i) {
+                placed.insert(i);
+            }
+        }
+        let live_before_terminator: Vec<_> = mir.basic_blocks().indices().map(|bb| {
+            let mut live = results.sets().on_entry_set_for(bb.index()).to_owned();
+            let loc = Location { block: bb, statement_index: mir[bb].statements.len() };
+            LocalUses::of_terminator(mir[bb].terminator(), loc).apply(&mut live);
+            live
+        }).collect();
+
+        mir.basic_blocks().indices().map(|bb| {
+            let data = &mir[bb];
+            let live = block_liveness(&results, mir, bb);
+            let mut statements = Vec::with_capacity(data.statements.len());
+            let storage = |source_info, kind: fn(Lvalue<'tcx>) -> StatementKind<'tcx>, local| {
+                Statement { source_info: source_info, kind: kind(Lvalue::Local(local)) }
+            };
+
+            // The storage of a local ends on entry to the block if it was
+            // live before the terminator of a predecessor, or assigned by
+            // it, but is dead now.
+            let entry_info = data.statements.first().map_or(data.terminator().source_info,
+                                                             |stmt| stmt.source_info);
+            let preds = mir.predecessors_for(bb);
+            for local in placed.iter().map(Local::new) {
+                if live[0].contains(&local) {
+                    continue;
+                }
+                let ends = preds.iter().any(|&pred| {
+                    live_before_terminator[pred.index()].contains(&local) ||
+                        match mir[pred].terminator().kind {
+                            TerminatorKind::Call {
+                                destination: Some((Lvalue::Local(dest), target)), ..
+                            } => dest == local && target == bb,
+                            _ => false,
+                        }
+                });
+                if ends {
+                    statements.push(storage(entry_info, StatementKind::StorageDead, local));
+                }
+            }
+
+            for (i, stmt) in data.statements.iter().enumerate() {
+                match stmt.kind {
+                    StatementKind::StorageLive(Lvalue::Local(local)) |
+                    StatementKind::StorageDead(Lvalue::Local(local))
+                        if placed.contains(local.index()) => continue,
+                    StatementKind::Nop => continue,
+                    _ => {}
+                }
+
+                let loc = Location { block: bb, statement_index: i };
+                let uses = LocalUses::of_statement(stmt, loc);
+                if let Some(def) = uses.def {
+                    if placed.contains(def.index()) && !live[i].contains(&def) {
+                        statements.push(storage(stmt.source_info, StatementKind::StorageLive, def));
+                    }
+                }
+                statements.push(stmt.clone());
+
+                // The storage ends after the last read of a local, or after
+                // an assignment which is never read.
+                let mut ended = vec![];
+                for &local in uses.def.iter().chain(&uses.uses) {
+                    if placed.contains(local.index()) && !live[i + 1].contains(&local) &&
+                        !ended.contains(&local) {
+                        statements.push(storage(stmt.source_info, StatementKind::StorageDead,
+                                                local));
+                        ended.push(local);
+                    }
+                }
+            }
+
+            // A call assigns its destination when it returns.
+            let term = data.terminator();
+            if let TerminatorKind::Call { destination: Some((Lvalue::Local(dest), _)), .. } =
+                term.kind {
+                if placed.contains(dest.index()) && !live[data.statements.len()].contains(&dest) {
+                    statements.push(storage(term.source_info, StatementKind::StorageLive, dest));
+                }
+            }
+            statements
+        }).collect()
+    };
+
+    for (data, statements) in mir.basic_blocks_mut().iter_mut().zip(new_statements) {
+        data.statements = statements;
+    }
+}
+
+/// The accesses to locals which the liveness analysis does not see as
+/// such.
+struct Accesses {
+    /// Locals which are borrowed, as a whole or in part.
+    borrowed: BitVector,
+    /// Locals which are assigned in part, or other than by an assignment
+    /// or a call.
+    partially_written: BitVector,
+    /// Locals which have storage markers.
+    has_storage: BitVector,
+}
+
+impl Accesses {
+    fn new(mir: &Mir) -> Accesses {
+        let n = mir.local_decls.len();
+        let mut accesses = Accesses {
+            borrowed: BitVector::new(n),
+            partially_written: BitVector::new(n),
+            has_storage: BitVector::new(n),
+        };
+        accesses.visit_mir(mir);
+        accesses
+    }
+
+    fn write(&mut self, lvalue: &Lvalue) {
+        match *lvalue {
+            Lvalue::Local(_) => {}
+            _ => self.write_in_place(lvalue),
+        }
+    }
+
+    fn write_in_place(&mut self, lvalue: &Lvalue) {
+        if let Some(local) = base_local(lvalue) {
+            self.partially_written.insert(local.index());
+        }
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for Accesses {
+    fn visit_statement(&mut self,
+                       block: BasicBlock,
+                       statement: &Statement<'tcx>,
+                       location: Location) {
+        match statement.kind {
+            StatementKind::Assign(ref lvalue, _) => self.write(lvalue),
+            StatementKind::SetDiscriminant { ref lvalue, .. } => self.write_in_place(lvalue),
+            StatementKind::StorageLive(Lvalue::Local(local)) |
+            StatementKind::StorageDead(Lvalue::Local(local)) => {
+                self.has_storage.insert(local.index());
+            }
+            _ => {}
+        }
+        self.super_statement(block, statement, location);
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &Rvalue<'tcx>, location: Location) {
+        match *rvalue {
+            Rvalue::Ref(_, _, ref lvalue) => {
+                if let Some(local) = base_local(lvalue) {
+                    self.borrowed.insert(local.index());
+                }
+            }
+            Rvalue::InlineAsm { ref outputs, .. } => {
+                for output in outputs {
+                    self.write_in_place(output);
+                }
+            }
+            _ => {}
+        }
+        self.super_rvalue(rvalue, location);
+    }
+
+    fn visit_terminator_kind(&mut self,
+                             block: BasicBlock,
+                             kind: &TerminatorKind<'tcx>,
+                             location: Location) {
+        match *kind {
+            TerminatorKind::Call { destination: Some((ref lvalue, _)), .. } => {
+                self.write(lvalue);
+            }
+            TerminatorKind::DropAndReplace { ref location, .. } => self.write_in_place(location),
+            _ => {}
+        }
+        self.super_terminator_kind(block, kind, location);
+    }
+}
+
+/// The local which holds the value of `lvalue`, if any.
+fn base_local(lvalue: &Lvalue) -> Option<Local> {
+    match *lvalue {
+        Lvalue::Local(local) => Some(local),
+        Lvalue::Projection(ref proj) => {
+            match proj.elem {
+                ProjectionElem::Deref => None,
+                _ => base_local(&proj.base),
+            }
+        }
+        Lvalue::Static(_) => None,
+    }
+}
@@ -0,0 +1,505 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Inlining of calls to small functions.
+//!
+//! A call is inlined by copying the MIR of the callee into the caller, with
+//! the type parameters of the callee substituted by those of the call. The
+//! callee can be a function of this crate or of another crate whose MIR is
+//! available, because it is generic or `#[inline]`, or because that crate was
+//! built with `-Z always-encode-mir`. Calls in the inlined MIR are considered
+//! for inlining in turn, but a function is never inlined into itself.
+//!
+//! Whether a call is inlined is decided by a cost model: the statements and
+//! terminators of the callee which run when it does not unwind each have a
+//! cost, and the call is inlined if their sum is no more than a threshold.
+//! The threshold is higher for `#[inline]` functions, `#[inline(always)]`
+//! functions are always inlined and `#[inline(never)]` ones never are.
+//!
+//! Calls of trait methods are not inlined, since they would first have to be
+//! resolved to the method of an impl, and neither are calls of closures.
+//!
+//! The pass only runs with `-Z mir-opt-level=2` or higher.
+
+use rustc::dep_graph::DepNode;
+use rustc::hir::def_id::{CrateNum, DefId};
+use rustc::infer::TransNormalize;
+use rustc::mir::*;
+use rustc::mir::transform::{MirMapPass, MirPassHook, MirSource, Pass};
+use rustc::mir::visit::{LvalueContext, MutVisitor, Visitor};
+use rustc::ty::{self, TyCtxt, Ty};
+use rustc::ty::fold::TypeFoldable;
+use rustc::ty::subst::{Subst, Substs};
+use rustc::util::nodemap::{FxHashMap, FxHashSet};
+use rustc_data_structures::indexed_vec::{Idx, IndexVec};
+use syntax::attr::{self, InlineAttr};
+use transform::qualify_consts;
+
+use std::collections::VecDeque;
+
+const DEFAULT_THRESHOLD: usize = 50;
+const HINT_THRESHOLD: usize = 100;
+
+const INSTR_COST: usize = 5;
+const CALL_PENALTY: usize = 25;
+
+pub struct Inline;
+
+impl Pass for Inline {}
+
+impl<'tcx> MirMapPass<'tcx> for Inline {
+    fn run_pass<'a>(&mut self,
+                    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                    hooks: &mut [Box<for<'s> MirPassHook<'s>>]) {
+        if tcx.sess.opts.debugging_opts.mir_opt_level < 2 {
+            return;
+        }
+
+        let mut inliner = Inliner {
+            tcx: tcx,
+            exported_symbols: FxHashMap(),
+        };
+
+        let def_ids = tcx.mir_map.borrow().keys();
+        for def_id in def_ids {
+            if !def_id.is_local() {
+                continue;
+            }
+
+            let _task = tcx.dep_graph.in_task(DepNode::Mir(def_id));
+            let mir = &mut tcx.mir_map.borrow()[&def_id].borrow_mut();
+            tcx.dep_graph.write(DepNode::Mir(def_id));
+
+            let id = tcx.map.as_local_node_id(def_id).unwrap();
+            let src = MirSource::from_node(tcx, id);
+
+            for hook in &mut *hooks {
+                hook.on_mir_pass(tcx, src, mir, self, false);
+            }
+            match src {
+                // Constants are evaluated from their MIR, which must stay
+                // acceptable to constant qualification.
+                MirSource::Fn(_) if !qualify_consts::is_const_fn(tcx, def_id) => {
+                    inliner.inline_calls(def_id, mir);
+                }
+                _ => {}
+            }
+            for hook in &mut *hooks {
+                hook.on_mir_pass(tcx, src, mir, self, true);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CallSite<'tcx> {
+    callee: DefId,
+    substs: &'tcx Substs<'tcx>,
+    bb: BasicBlock,
+    /// The functions which were inlined to bring this call into the caller.
+    history: Vec<DefId>,
+}
+
+struct Inliner<'a, 'tcx: 'a> {
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    exported_symbols: FxHashMap<CrateNum, FxHashSet<DefId>>,
+}
+
+impl<'a, 'tcx> Inliner<'a, 'tcx> {
+    fn inline_calls(&mut self, caller: DefId, caller_mir: &mut Mir<'tcx>) {
+        let mut callsites: VecDeque<_> = caller_mir.basic_blocks().iter_enumerated()
+            .filter_map(|(bb, data)| call_site(bb, data, &[]))
+            .collect();
+
+        while let Some(callsite) = callsites.pop_front() {
+            if callsite.callee == caller || callsite.history.contains(&callsite.callee) {
+                continue;
+            }
+            if !self.is_mir_available(callsite.callee) ||
+               self.tcx.trait_of_item(callsite.callee).is_some() {
+                continue;
+            }
+
+            let start = caller_mir.basic_blocks().len();
+            {
+                let callee_mir = self.tcx.item_mir(callsite.callee);
+                if !self.should_inline(&callsite, &callee_mir) {
+                    continue;
+                }
+                debug!("inlining {:?} into {:?}", callsite, caller);
+                inline_call(self.tcx, &callsite, caller_mir, &callee_mir);
+            }
+
+            let mut history = callsite.history.clone();
+            history.push(callsite.callee);
+            for index in start..caller_mir.basic_blocks().len() {
+                let bb = BasicBlock::new(index);
+                if let Some(callsite) = call_site(bb, &caller_mir[bb], &history) {
+                    callsites.push_back(callsite);
+                }
+            }
+        }
+    }
+
+    fn is_mir_available(&self, def_id: DefId) -> bool {
+        if def_id.is_local() {
+            self.tcx.mir_map.borrow().contains_key(&def_id)
+        } else {
+            self.tcx.sess.cstore.is_item_mir_available(def_id)
+        }
+    }
+
+    fn should_inline(&mut self, callsite: &CallSite<'tcx>, callee_mir: &Mir<'tcx>) -> bool {
+        // Closures and other "rust-call" functions take their arguments
+        // spread out, which the integration does not handle.
+        if callee_mir.spread_arg.is_some() || !callee_mir.upvar_decls.is_empty() {
+            return false;
+        }
+        if !self.is_reachable_from_here(callsite.callee, callee_mir) {
+            return false;
+        }
+
+        let threshold = match attr::find_inline_attr(None, &self.tcx.get_attrs(callsite.callee)) {
+            InlineAttr::Never => return false,
+            InlineAttr::Always => return true,
+            InlineAttr::Hint => HINT_THRESHOLD,
+            InlineAttr::None => DEFAULT_THRESHOLD,
+        };
+        let cost = body_cost(callee_mir);
+        debug!("cost of {:?} is {} (threshold {})", callsite.callee, cost, threshold);
+        cost <= threshold
+    }
+
+    /// Whether the items the body of `callee` uses can be used from this
+    /// crate. The MIR of a function which is neither generic nor `#[inline]`
+    /// may use private items of its crate, which have no exported symbol.
+    fn is_reachable_from_here(&mut self, callee: DefId, callee_mir: &Mir<'tcx>) -> bool {
+        let tcx = self.tcx;
+        if callee.is_local() || tcx.sess.cstore.can_have_local_instance(tcx, callee) {
+            return true;
+        }
+
+        let mut finder = ItemFinder { items: Vec::new() };
+        finder.visit_mir(callee_mir);
+        for promoted in &callee_mir.promoted {
+            finder.visit_mir(promoted);
+        }
+        finder.items.into_iter().all(|def_id| {
+            def_id.is_local() || tcx.sess.cstore.can_have_local_instance(tcx, def_id) ||
+            self.exported_symbols.entry(def_id.krate).or_insert_with(|| {
+                tcx.sess.cstore.exported_symbols(def_id.krate).into_iter().collect()
+            }).contains(&def_id)
+        })
+    }
+}
+
+fn call_site<'tcx>(bb: BasicBlock, data: &BasicBlockData<'tcx>, history: &[DefId])
+                   -> Option<CallSite<'tcx>> {
+    // The inlined MIR would have to be turned into cleanup blocks.
+    if data.is_cleanup {
+        return None;
+    }
+    if let TerminatorKind::Call { func: Operand::Constant(ref func), .. } = data.terminator().kind {
+        if let ty::TyFnDef(callee, substs, _) = func.ty.sty {
+            return Some(CallSite {
+                callee: callee,
+                substs: substs,
+                bb: bb,
+                history: history.to_vec(),
+            });
+        }
+    }
+    None
+}
+
+/// The cost of the code of `mir` which runs when it does not unwind.
+fn body_cost(mir: &Mir) -> usize {
+    let mut cost = 0;
+    for data in mir.basic_blocks() {
+        if data.is_cleanup {
+            continue;
+        }
+        for statement in &data.statements {
+            cost += match statement.kind {
+                StatementKind::StorageLive(_) |
+                StatementKind::StorageDead(_) |
+                StatementKind::Nop => 0,
+                _ => INSTR_COST,
+            };
+        }
+        cost += match data.terminator().kind {
+            TerminatorKind::Call { .. } |
+            TerminatorKind::Drop { .. } |
+            TerminatorKind::DropAndReplace { .. } |
+            TerminatorKind::Assert { .. } => CALL_PENALTY,
+            TerminatorKind::Resume | TerminatorKind::Unreachable => 0,
+            _ => INSTR_COST,
+        };
+    }
+    cost
+}
+
+/// Replace the call at `callsite` by the MIR of the callee.
+fn inline_call<'a, 'tcx>(tcx: TyCtxt<'a, 'tcx, 'tcx>,
+                         callsite: &CallSite<'tcx>,
+                         caller_mir: &mut Mir<'tcx>,
+                         callee_mir: &Mir<'tcx>) {
+    let terminator = caller_mir[callsite.bb].terminator.take().unwrap();
+    let source_info = terminator.source_info;
+    let (args, destination, cleanup) = match terminator.kind {
+        TerminatorKind::Call { args, destination, cleanup, .. } => (args, destination, cleanup),
+        kind => bug!("inlining a non-call terminator {:?}", kind),
+    };
+
+    let mut substitutor = Substitutor {
+        tcx: tcx,
+        substs: callsite.substs,
+    };
+    let block_offset = caller_mir.basic_blocks().len();
+    // The callee returns to a block which moves its return value to the
+    // destination of the call. Using the destination as the return pointer
+    // would let the callee overwrite it while its arguments still borrow it.
+    let return_block = destination.as_ref().map(|_| {
+        BasicBlock::new(block_offset + callee_mir.basic_blocks().len())
+    });
+    let mut integrator = Integrator {
+        block_offset: block_offset,
+        local_offset: caller_mir.local_decls.len(),
+        scope_offset: caller_mir.visibility_scopes.len(),
+        promoted_offset: caller_mir.promoted.len(),
+        return_block: return_block,
+        cleanup_block: cleanup,
+        in_cleanup_block: false,
+    };
+
+    for scope in &callee_mir.visibility_scopes {
+        let mut scope = scope.clone();
+        integrator.visit_visibility_scope_data(&mut scope);
+        if scope.parent_scope.is_none() {
+            scope.parent_scope = Some(source_info.scope);
+        }
+        caller_mir.visibility_scopes.push(scope);
+    }
+
+    for (local, decl) in callee_mir.local_decls.iter_enumerated() {
+        let mut decl = decl.clone();
+        // The arguments become variables of the caller, which need a scope
+        // to be described in the debuginfo.
+        if decl.name.is_some() && decl.source_info.is_none() {
+            assert!(callee_mir.local_kind(local) == LocalKind::Arg);
+            decl.source_info = Some(SourceInfo {
+                span: callee_mir.span,
+                scope: ARGUMENT_VISIBILITY_SCOPE,
+            });
+        }
+        substitutor.visit_local_decl(&mut decl);
+        integrator.visit_local_decl(&mut decl);
+        caller_mir.local_decls.push(decl);
+    }
+
+    for promoted in &callee_mir.promoted {
+        let mut promoted = copy_mir(promoted);
+        substitutor.visit_mir(&mut promoted);
+        caller_mir.promoted.push(promoted);
+    }
+
+    for (bb, data) in callee_mir.basic_blocks().iter_enumerated() {
+        let mut data = data.clone();
+        substitutor.visit_basic_block_data(bb, &mut data);
+        integrator.visit_basic_block_data(bb, &mut data);
+        caller_mir.basic_blocks_mut().push(data);
+    }
+
+    if let Some((dest, target)) = destination {
+        let return_pointer = Lvalue::Local(Local::new(integrator.local_offset));
+        caller_mir.basic_blocks_mut().push(BasicBlockData {
+            statements: vec![Statement {
+                source_info: source_info,
+                kind: StatementKind::Assign(dest, Rvalue::Use(Operand::Consume(return_pointer))),
+            }],
+            terminator: Some(Terminator {
+                source_info: source_info,
+                kind: TerminatorKind::Goto { target: target },
+            }),
+            is_cleanup: false,
+        });
+    }
+
+    assert_eq!(args.len(), callee_mir.arg_count);
+    let data = &mut caller_mir[callsite.bb];
+    for (i, arg) in args.into_iter().enumerate() {
+        let arg_local = Local::new(integrator.local_offset + i + 1);
+        data.statements.push(Statement {
+            source_info: source_info,
+            kind: StatementKind::Assign(Lvalue::Local(arg_local), Rvalue::Use(arg)),
+        });
+    }
+    data.terminator = Some(Terminator {
+        source_info: source_info,
+        kind: TerminatorKind::Goto { target: BasicBlock::new(block_offset) },
+    });
+}
+
+// `Mir` is deliberately not `Clone`; promoted MIR has no promoted MIR of its
+// own, so its parts are all there is to copy.
+fn copy_mir<'tcx>(mir: &Mir<'tcx>) -> Mir<'tcx> {
+    Mir::new(mir.basic_blocks().clone(),
+             mir.visibility_scopes.clone(),
+             IndexVec::new(),
+             mir.return_ty,
+             mir.local_decls.clone(),
+             mir.arg_count,
+             mir.upvar_decls.clone(),
+             mir.span)
+}
+
+/// Substitutes the type parameters of the callee by those of the call.
+struct Substitutor<'a, 'tcx: 'a> {
+    tcx: TyCtxt<'a, 'tcx, 'tcx>,
+    substs: &'tcx Substs<'tcx>,
+}
+
+impl<'a, 'tcx> Substitutor<'a, 'tcx> {
+    fn apply<T: TransNormalize<'tcx>>(&self, value: &T) -> T {
+        let value = value.subst(self.tcx, self.substs);
+        // Projections can only be normalized once all the types are known.
+        if value.needs_subst() {
+            self.tcx.erase_regions(&value)
+        } else {
+            self.tcx.normalize_associated_type(&value)
+        }
+    }
+}
+
+impl<'a, 'tcx> MutVisitor<'tcx> for Substitutor<'a, 'tcx> {
+    fn visit_ty(&mut self, ty: &mut Ty<'tcx>) {
+        *ty = self.apply(ty);
+    }
+
+    fn visit_substs(&mut self, substs: &mut &'tcx Substs<'tcx>) {
+        *substs = self.apply(substs);
+    }
+
+    fn visit_closure_substs(&mut self, substs: &mut ty::ClosureSubsts<'tcx>) {
+        *substs = self.apply(substs);
+    }
+}
+
+/// Moves the locals, scopes, promoted constants and blocks of the callee to
+/// their place in the caller, and links its blocks to those of the caller.
+struct Integrator {
+    block_offset: usize,
+    local_offset: usize,
+    scope_offset: usize,
+    promoted_offset: usize,
+    return_block: Option<BasicBlock>,
+    cleanup_block: Option<BasicBlock>,
+    in_cleanup_block: bool,
+}
+
+impl<'tcx> MutVisitor<'tcx> for Integrator {
+    fn visit_lvalue(&mut self,
+                    lvalue: &mut Lvalue<'tcx>,
+                    context: LvalueContext<'tcx>,
+                    location: Location) {
+        if let Lvalue::Local(ref mut local) = *lvalue {
+            *local = Local::new(local.index() + self.local_offset);
+        }
+        self.super_lvalue(lvalue, context, location);
+    }
+
+    fn visit_visibility_scope(&mut self, scope: &mut VisibilityScope) {
+        *scope = VisibilityScope::new(scope.index() + self.scope_offset);
+    }
+
+    fn visit_literal(&mut self, literal: &mut Literal<'tcx>, location: Location) {
+        if let Literal::Promoted { ref mut index } = *literal {
+            *index = Promoted::new(index.index() + self.promoted_offset);
+        }
+        self.super_literal(literal, location);
+    }
+
+    fn visit_basic_block_data(&mut self, block: BasicBlock, data: &mut BasicBlockData<'tcx>) {
+        self.in_cleanup_block = data.is_cleanup;
+        self.super_basic_block_data(block, data);
+    }
+
+    fn visit_terminator_kind(&mut self,
+                             block: BasicBlock,
+                             kind: &mut TerminatorKind<'tcx>,
+                             location: Location) {
+        self.super_terminator_kind(block, kind, location);
+
+        for target in kind.successors_mut() {
+            *target = BasicBlock::new(target.index() + self.block_offset);
+        }
+
+        // Unwinding out of the callee continues with the cleanup of the call.
+        let cleanup_block = if self.in_cleanup_block { None } else { self.cleanup_block };
+        match *kind {
+            TerminatorKind::Drop { ref mut unwind, .. } |
+            TerminatorKind::DropAndReplace { ref mut unwind, .. } => {
+                if unwind.is_none() {
+                    *unwind = cleanup_block;
+                }
+            }
+            TerminatorKind::Call { ref mut cleanup, .. } |
+            TerminatorKind::Assert { ref mut cleanup, .. } => {
+                if cleanup.is_none() {
+                    *cleanup = cleanup_block;
+                }
+            }
+            TerminatorKind::Return => {
+                *kind = match self.return_block {
+                    Some(target) => TerminatorKind::Goto { target: target },
+                    // The call diverges, so the callee cannot return.
+                    None => TerminatorKind::Unreachable,
+                };
+            }
+            TerminatorKind::Resume => {
+                if let Some(target) = self.cleanup_block {
+                    *kind = TerminatorKind::Goto { target: target };
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collects the functions, closures and statics which a body uses.
+struct ItemFinder {
+    items: Vec<DefId>,
+}
+
+impl<'tcx> Visitor<'tcx> for ItemFinder {
+    fn visit_lvalue(&mut self,
+                    lvalue: &Lvalue<'tcx>,
+                    context: LvalueContext<'tcx>,
+                    location: Location) {
+        if let Lvalue::Static(def_id) = *lvalue {
+            self.items.push(def_id);
+        }
+        self.super_lvalue(lvalue, context, location);
+    }
+
+    fn visit_constant(&mut self, constant: &Constant<'tcx>, location: Location) {
+        if let ty::TyFnDef(def_id, ..) = constant.ty.sty {
+            self.items.push(def_id);
+        }
+        self.super_constant(constant, location);
+    }
+
+    fn visit_rvalue(&mut self, rvalue: &Rvalue<'tcx>, location: Location) {
+        if let Rvalue::Aggregate(AggregateKind::Closure(def_id, _), _) = *rvalue {
+            self.items.push(def_id);
+        }
+        self.super_rvalue(rvalue, location);
+    }
+}
@@ -20,3 +20,6 @@ pub mod dump_mir;
 pub mod deaggregator;
 pub mod instcombine;
 pub mod copy_prop;
+pub mod const_prop;
+pub mod inline;
+pub mod dead_stores;
@@ -74,7 +74,7 @@ use syntax::{abi, ast};
 use syntax::feature_gate::{GateIssue, emit_feature_err};
 use syntax::symbol::{Symbol, keywords};
 use syntax_pos::{Span, Pos};
-use errors::DiagnosticBuilder;
+use errors::{Applicability, DiagnosticBuilder};
 
 pub trait AstConv<'gcx, 'tcx> {
     fn tcx<'a>(&'a self) -> TyCtxt<'a, 'gcx, 'tcx>;
@@ -996,8 +996,11 @@ impl<'o, 'gcx: 'tcx, 'tcx> AstConv<'gcx, 'tcx>+'o {
                             s.print_bounds(" +", bounds)?;
                             s.pclose()
                         });
-                        err.span_suggestion(full_span, "try adding parentheses (per RFC 438):",
-                                            ty_str);
+                        err.span_suggestion_with_applicability(
+                            full_span,
+                            "try adding parentheses (per RFC 438):",
+                            ty_str,
+                            Applicability::MachineApplicable);
                     }
 
                     _ => {
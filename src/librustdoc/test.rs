@@ -212,7 +212,8 @@ fn runtest(test: &str, cratename: &str, cfgs: Vec<String>, libs: SearchPaths,
     let data = Arc::new(Mutex::new(Vec::new()));
     let codemap = Rc::new(CodeMap::new());
     let emitter = errors::emitter::EmitterWriter::new(box Sink(data.clone()),
-                                                      Some(codemap.clone()));
+                                                      Some(codemap.clone()),
+                                                      false);
     let old = io::set_panic(Some(box Sink(data.clone())));
     let _bomb = Bomb(data.clone(), old.unwrap_or(box io::stdout()));
 
@@ -426,21 +427,22 @@ impl Collector {
                 ignore: should_ignore,
                 // compiler failures are test failures
                 should_panic: testing::ShouldPanic::No,
+                timeout: None,
             },
-            testfn: testing::DynTestFn(box move |()| {
+            testfn: testing::DynRetryableTestFn(Arc::new(move || {
                 runtest(&test,
                         &cratename,
-                        cfgs,
-                        libs,
-                        externs,
+                        cfgs.clone(),
+                        libs.clone(),
+                        externs.clone(),
                         should_panic,
                         no_run,
                         as_test_harness,
                         compile_fail,
-                        error_codes,
+                        error_codes.clone(),
                         &opts,
-                        maybe_sysroot);
-            })
+                        maybe_sysroot.clone());
+            }))
         });
     }
 
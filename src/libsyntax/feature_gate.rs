@@ -327,6 +327,9 @@ declare_features! (
 
     // Allows attributes on struct literal fields.
     (active, struct_field_attributes, "1.16.0", Some(38814)),
+
+    // Allows `#[timeout = "secs"]` on tests to override the time limit
+    (active, test_timeout, "1.16.0", None),
 );
 
 declare_features! (
@@ -23,6 +23,7 @@ use codemap::CodeMap;
 use syntax_pos::{self, MacroBacktrace, Span, SpanLabel, MultiSpan};
 use errors::registry::Registry;
 use errors::{DiagnosticBuilder, SubDiagnostic, RenderSpan, CodeSuggestion, CodeMapper};
+use errors::Applicability;
 use errors::emitter::Emitter;
 
 use std::rc::Rc;
@@ -71,7 +72,8 @@ impl Emitter for JsonEmitter {
     }
 }
 
-// The following data types are provided just for serialisation.
+// The following data types are provided just for serialisation. The spans
+// are also used by the SARIF emitter.
 
 #[derive(RustcEncodable)]
 struct Diagnostic<'a> {
@@ -90,54 +92,61 @@ struct Diagnostic<'a> {
 }
 
 #[derive(RustcEncodable)]
-struct DiagnosticSpan {
-    file_name: String,
-    byte_start: u32,
-    byte_end: u32,
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    /// 0-based, byte offsets into the codemap, which holds every file
+    /// loaded during the compilation.
+    pub byte_start: u32,
+    pub byte_end: u32,
+    /// 0-based, byte offsets from the start of the file.
+    pub file_byte_start: u32,
+    pub file_byte_end: u32,
     /// 1-based.
-    line_start: usize,
-    line_end: usize,
+    pub line_start: usize,
+    pub line_end: usize,
     /// 1-based, character offset.
-    column_start: usize,
-    column_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
     /// Is this a "primary" span -- meaning the point, or one of the points,
     /// where the error occurred?
-    is_primary: bool,
+    pub is_primary: bool,
     /// Source text from the start of line_start to the end of line_end.
-    text: Vec<DiagnosticSpanLine>,
+    pub text: Vec<DiagnosticSpanLine>,
     /// Label that should be placed at this location (if any)
-    label: Option<String>,
+    pub label: Option<String>,
     /// If we are suggesting a replacement, this will contain text
     /// that should be sliced in atop this span. You may prefer to
     /// load the fully rendered version from the parent `Diagnostic`,
     /// however.
-    suggested_replacement: Option<String>,
+    pub suggested_replacement: Option<String>,
+    /// If the suggested replacement can be applied automatically.
+    pub suggestion_applicability: Option<Applicability>,
     /// Macro invocations that created the code at this span, if any.
-    expansion: Option<Box<DiagnosticSpanMacroExpansion>>,
+    pub expansion: Option<Box<DiagnosticSpanMacroExpansion>>,
 }
 
 #[derive(RustcEncodable)]
-struct DiagnosticSpanLine {
-    text: String,
+pub struct DiagnosticSpanLine {
+    pub text: String,
 
     /// 1-based, character offset in self.text.
-    highlight_start: usize,
+    pub highlight_start: usize,
 
-    highlight_end: usize,
+    pub highlight_end: usize,
 }
 
 #[derive(RustcEncodable)]
-struct DiagnosticSpanMacroExpansion {
+pub struct DiagnosticSpanMacroExpansion {
     /// span where macro was applied to generate this code; note that
     /// this may itself derive from a macro (if
     /// `span.expansion.is_some()`)
-    span: DiagnosticSpan,
+    pub span: DiagnosticSpan,
 
     /// name of macro that was applied (e.g., "foo!" or "#[derive(Eq)]")
-    macro_decl_name: String,
+    pub macro_decl_name: String,
 
     /// span where macro was defined (if known)
-    def_site_span: Option<DiagnosticSpan>,
+    pub def_site_span: Option<DiagnosticSpan>,
 }
 
 #[derive(RustcEncodable)]
@@ -156,7 +165,7 @@ impl<'a> Diagnostic<'a> {
             message: &db.message,
             code: DiagnosticCode::map_opt_string(db.code.clone(), je),
             level: db.level.to_str(),
-            spans: DiagnosticSpan::from_multispan(&db.span, je),
+            spans: DiagnosticSpan::from_multispan(&db.span, &*je.cm),
             children: db.children.iter().map(|c| {
                 Diagnostic::from_sub_diagnostic(c, je)
             }).collect(),
@@ -170,8 +179,8 @@ impl<'a> Diagnostic<'a> {
             code: None,
             level: db.level.to_str(),
             spans: db.render_span.as_ref()
-                     .map(|sp| DiagnosticSpan::from_render_span(sp, je))
-                     .unwrap_or_else(|| DiagnosticSpan::from_multispan(&db.span, je)),
+                     .map(|sp| DiagnosticSpan::from_render_span(sp, &*je.cm))
+                     .unwrap_or_else(|| DiagnosticSpan::from_multispan(&db.span, &*je.cm)),
             children: vec![],
             rendered: db.render_span.as_ref()
                                     .and_then(|rsp| je.render(rsp)),
@@ -181,45 +190,45 @@ impl<'a> Diagnostic<'a> {
 
 impl DiagnosticSpan {
     fn from_span_label(span: SpanLabel,
-                       suggestion: Option<&String>,
-                       je: &JsonEmitter)
+                       suggestion: Option<(&String, Applicability)>,
+                       cm: &CodeMapper)
                        -> DiagnosticSpan {
         Self::from_span_etc(span.span,
                             span.is_primary,
                             span.label,
                             suggestion,
-                            je)
+                            cm)
     }
 
     fn from_span_etc(span: Span,
                      is_primary: bool,
                      label: Option<String>,
-                     suggestion: Option<&String>,
-                     je: &JsonEmitter)
+                     suggestion: Option<(&String, Applicability)>,
+                     cm: &CodeMapper)
                      -> DiagnosticSpan {
         // obtain the full backtrace from the `macro_backtrace`
         // helper; in some ways, it'd be better to expand the
         // backtrace ourselves, but the `macro_backtrace` helper makes
         // some decision, such as dropping some frames, and I don't
         // want to duplicate that logic here.
-        let backtrace = je.cm.macro_backtrace(span).into_iter();
+        let backtrace = cm.macro_backtrace(span).into_iter();
         DiagnosticSpan::from_span_full(span,
                                        is_primary,
                                        label,
                                        suggestion,
                                        backtrace,
-                                       je)
+                                       cm)
     }
 
     fn from_span_full(span: Span,
                       is_primary: bool,
                       label: Option<String>,
-                      suggestion: Option<&String>,
+                      suggestion: Option<(&String, Applicability)>,
                       mut backtrace: vec::IntoIter<MacroBacktrace>,
-                      je: &JsonEmitter)
+                      cm: &CodeMapper)
                       -> DiagnosticSpan {
-        let start = je.cm.lookup_char_pos(span.lo);
-        let end = je.cm.lookup_char_pos(span.hi);
+        let start = cm.lookup_char_pos(span.lo);
+        let end = cm.lookup_char_pos(span.hi);
         let backtrace_step = backtrace.next().map(|bt| {
             let call_site =
                 Self::from_span_full(bt.call_site,
@@ -227,14 +236,14 @@ impl DiagnosticSpan {
                                      None,
                                      None,
                                      backtrace,
-                                     je);
+                                     cm);
             let def_site_span = bt.def_site_span.map(|sp| {
                 Self::from_span_full(sp,
                                      false,
                                      None,
                                      None,
                                      vec![].into_iter(),
-                                     je)
+                                     cm)
             });
             Box::new(DiagnosticSpanMacroExpansion {
                 span: call_site,
@@ -246,45 +255,49 @@ impl DiagnosticSpan {
             file_name: start.file.name.clone(),
             byte_start: span.lo.0,
             byte_end: span.hi.0,
+            file_byte_start: span.lo.0 - start.file.start_pos.0,
+            file_byte_end: span.hi.0 - start.file.start_pos.0,
             line_start: start.line,
             line_end: end.line,
             column_start: start.col.0 + 1,
             column_end: end.col.0 + 1,
             is_primary: is_primary,
-            text: DiagnosticSpanLine::from_span(span, je),
-            suggested_replacement: suggestion.cloned(),
+            text: DiagnosticSpanLine::from_span(span, cm),
+            suggested_replacement: suggestion.map(|x| x.0.clone()),
+            suggestion_applicability: suggestion.map(|x| x.1),
             expansion: backtrace_step,
             label: label,
         }
     }
 
-    fn from_multispan(msp: &MultiSpan, je: &JsonEmitter) -> Vec<DiagnosticSpan> {
+    pub fn from_multispan(msp: &MultiSpan, cm: &CodeMapper) -> Vec<DiagnosticSpan> {
         msp.span_labels()
            .into_iter()
-           .map(|span_str| Self::from_span_label(span_str, None, je))
+           .map(|span_str| Self::from_span_label(span_str, None, cm))
            .collect()
     }
 
-    fn from_suggestion(suggestion: &CodeSuggestion, je: &JsonEmitter)
-                       -> Vec<DiagnosticSpan> {
+    pub fn from_suggestion(suggestion: &CodeSuggestion, cm: &CodeMapper)
+                           -> Vec<DiagnosticSpan> {
         assert_eq!(suggestion.msp.span_labels().len(), suggestion.substitutes.len());
         suggestion.msp.span_labels()
                       .into_iter()
                       .zip(&suggestion.substitutes)
-                      .map(|(span_label, suggestion)| {
+                      .map(|(span_label, substitute)| {
                           DiagnosticSpan::from_span_label(span_label,
-                                                          Some(suggestion),
-                                                          je)
+                                                          Some((substitute,
+                                                                suggestion.applicability)),
+                                                          cm)
                       })
                       .collect()
     }
 
-    fn from_render_span(rsp: &RenderSpan, je: &JsonEmitter) -> Vec<DiagnosticSpan> {
+    fn from_render_span(rsp: &RenderSpan, cm: &CodeMapper) -> Vec<DiagnosticSpan> {
         match *rsp {
             RenderSpan::FullSpan(ref msp) =>
-                DiagnosticSpan::from_multispan(msp, je),
+                DiagnosticSpan::from_multispan(msp, cm),
             RenderSpan::Suggestion(ref suggestion) =>
-                DiagnosticSpan::from_suggestion(suggestion, je),
+                DiagnosticSpan::from_suggestion(suggestion, cm),
         }
     }
 }
@@ -305,8 +318,8 @@ impl DiagnosticSpanLine {
     /// Create a list of DiagnosticSpanLines from span - each line with any part
     /// of `span` gets a DiagnosticSpanLine, with the highlight indicating the
     /// `span` within the line.
-    fn from_span(span: Span, je: &JsonEmitter) -> Vec<DiagnosticSpanLine> {
-        je.cm.span_to_lines(span)
+    fn from_span(span: Span, cm: &CodeMapper) -> Vec<DiagnosticSpanLine> {
+        cm.span_to_lines(span)
              .map(|lines| {
                  let fm = &*lines.file;
                  lines.lines
@@ -96,6 +96,7 @@ pub mod util {
 }
 
 pub mod json;
+pub mod sarif;
 
 pub mod syntax {
     pub use ext;
@@ -123,6 +124,7 @@ pub mod tokenstream;
 pub mod visit;
 
 pub mod print {
+    pub mod highlight;
     pub mod pp;
     pub mod pprust;
 }
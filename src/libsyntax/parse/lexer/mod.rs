@@ -1713,7 +1713,8 @@ mod tests {
     fn mk_sh(cm: Rc<CodeMap>) -> errors::Handler {
         // FIXME (#22405): Replace `Box::new` with `box` here when/if possible.
         let emitter = errors::emitter::EmitterWriter::new(Box::new(io::sink()),
-                                                Some(cm));
+                                                Some(cm),
+                                                false);
         errors::Handler::with_emitter(true, false, Box::new(emitter))
     }
 
@@ -0,0 +1,244 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! A SARIF emitter for errors.
+//!
+//! SARIF (Static Analysis Results Interchange Format, version 2.1.0) is the
+//! format understood by code scanning services and review bots. The whole
+//! compilation is a single run of a single tool, so its diagnostics are
+//! collected as they are emitted and written out as one log when the driver
+//! flushes the emitter at the end of the compilation. If the compilation is
+//! cut short before that, the log is written when the emitter is dropped.
+//!
+//! Each diagnostic becomes a result. Its error code is the result's rule, its
+//! primary spans are the locations, its other spans and its children are the
+//! related locations, and its suggestions are the fixes.
+
+use codemap::CodeMap;
+use errors::registry::Registry;
+use errors::{DiagnosticBuilder, Level, RenderSpan, CodeMapper};
+use errors::emitter::Emitter;
+use json::DiagnosticSpan;
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::mem;
+use std::rc::Rc;
+
+use rustc_serialize::json::{Json, ToJson};
+
+const SCHEMA: &'static str = "https://json.schemastore.org/sarif-2.1.0.json";
+const ERROR_INDEX: &'static str = "https://doc.rust-lang.org/error-index.html";
+
+pub struct SarifEmitter {
+    dst: Box<Write + Send>,
+    registry: Option<Registry>,
+    cm: Rc<CodeMapper + 'static>,
+    results: Vec<Json>,
+    /// The rules of the results so far, by error code.
+    rules: BTreeMap<String, Json>,
+    /// Whether the log has been written out.
+    flushed: bool,
+}
+
+impl SarifEmitter {
+    pub fn stderr(registry: Option<Registry>,
+                  code_map: Rc<CodeMap>) -> SarifEmitter {
+        SarifEmitter::new(Box::new(io::stderr()), registry, code_map)
+    }
+
+    pub fn basic() -> SarifEmitter {
+        SarifEmitter::stderr(None, Rc::new(CodeMap::new()))
+    }
+
+    pub fn new(dst: Box<Write + Send>,
+               registry: Option<Registry>,
+               code_map: Rc<CodeMap>) -> SarifEmitter {
+        SarifEmitter {
+            dst: dst,
+            registry: registry,
+            cm: code_map,
+            results: Vec::new(),
+            rules: BTreeMap::new(),
+            flushed: false,
+        }
+    }
+
+    fn result(&mut self, db: &DiagnosticBuilder) -> Json {
+        let mut locations = Vec::new();
+        let mut related_locations = Vec::new();
+        let mut fixes = Vec::new();
+
+        for span in DiagnosticSpan::from_multispan(&db.span, &*self.cm) {
+            let location = location(&span, span.label.as_ref().map(|l| &l[..]));
+            if span.is_primary {
+                locations.push(location);
+            } else {
+                related_locations.push(location);
+            }
+        }
+
+        for child in &db.children {
+            let message = format!("{}: {}", child.level.to_str(), child.message);
+            let spans = match child.render_span {
+                Some(RenderSpan::Suggestion(ref suggestion)) => {
+                    let spans = DiagnosticSpan::from_suggestion(suggestion, &*self.cm);
+                    fixes.push(fix(&child.message, &spans));
+                    continue;
+                }
+                Some(RenderSpan::FullSpan(ref msp)) => {
+                    DiagnosticSpan::from_multispan(msp, &*self.cm)
+                }
+                None => DiagnosticSpan::from_multispan(&child.span, &*self.cm),
+            };
+            if spans.is_empty() {
+                related_locations.push(object(vec![("message", text(&message))]));
+            }
+            for span in spans.iter().filter(|span| span.is_primary) {
+                related_locations.push(location(span, Some(&message)));
+            }
+        }
+
+        let mut result = vec![("level", level(db.level).to_json()),
+                              ("message", text(&db.message)),
+                              ("locations", Json::Array(locations))];
+        if let Some(ref code) = db.code {
+            self.add_rule(code);
+            result.push(("ruleId", code.to_json()));
+        }
+        if !related_locations.is_empty() {
+            result.push(("relatedLocations", Json::Array(related_locations)));
+        }
+        if !fixes.is_empty() {
+            result.push(("fixes", Json::Array(fixes)));
+        }
+        object(result)
+    }
+
+    fn add_rule(&mut self, code: &str) {
+        if self.rules.contains_key(code) {
+            return;
+        }
+        let mut rule = vec![("id", code.to_json()),
+                            ("helpUri", format!("{}#{}", ERROR_INDEX, code).to_json())];
+        let explanation = self.registry
+                              .as_ref()
+                              .and_then(|registry| registry.find_description(code));
+        if let Some(explanation) = explanation {
+            rule.push(("fullDescription", text(explanation.trim())));
+        }
+        self.rules.insert(code.to_owned(), object(rule));
+    }
+
+    fn log(&mut self) -> Json {
+        let results = mem::replace(&mut self.results, Vec::new());
+        let rules = mem::replace(&mut self.rules, BTreeMap::new());
+        let driver = object(vec![("name", "rustc".to_json()),
+                                 ("informationUri", "https://www.rust-lang.org/".to_json()),
+                                 ("rules", Json::Array(rules.into_iter()
+                                                            .map(|(_, rule)| rule)
+                                                            .collect()))]);
+        let run = object(vec![("tool", object(vec![("driver", driver)])),
+                              ("results", Json::Array(results))]);
+        object(vec![("$schema", SCHEMA.to_json()),
+                    ("version", "2.1.0".to_json()),
+                    ("runs", Json::Array(vec![run]))])
+    }
+}
+
+impl Emitter for SarifEmitter {
+    fn emit(&mut self, db: &DiagnosticBuilder) {
+        let result = self.result(db);
+        self.results.push(result);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.flushed {
+            return Ok(());
+        }
+        self.flushed = true;
+        let log = self.log();
+        writeln!(&mut self.dst, "{}", log)?;
+        self.dst.flush()
+    }
+}
+
+impl Drop for SarifEmitter {
+    fn drop(&mut self) {
+        // There is nowhere left to report a failure to write the log to.
+        let _ = Emitter::flush(self);
+    }
+}
+
+fn level(level: Level) -> &'static str {
+    match level {
+        Level::Bug | Level::Fatal | Level::PhaseFatal | Level::Error => "error",
+        Level::Warning => "warning",
+        Level::Note | Level::Help | Level::Cancelled => "note",
+    }
+}
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+}
+
+fn text(text: &str) -> Json {
+    object(vec![("text", text.to_json())])
+}
+
+fn artifact_location(span: &DiagnosticSpan) -> Json {
+    object(vec![("uri", span.file_name.replace('\\', "/").to_json())])
+}
+
+fn region(span: &DiagnosticSpan) -> Json {
+    object(vec![("startLine", span.line_start.to_json()),
+                ("startColumn", span.column_start.to_json()),
+                ("endLine", span.line_end.to_json()),
+                ("endColumn", span.column_end.to_json()),
+                ("byteOffset", span.file_byte_start.to_json()),
+                ("byteLength", (span.file_byte_end - span.file_byte_start).to_json())])
+}
+
+fn location(span: &DiagnosticSpan, message: Option<&str>) -> Json {
+    let physical_location = object(vec![("artifactLocation", artifact_location(span)),
+                                        ("region", region(span))]);
+    let mut location = vec![("physicalLocation", physical_location)];
+    if let Some(message) = message {
+        location.push(("message", text(message)));
+    }
+    object(location)
+}
+
+// A fix which replaces each of `spans` with its suggested replacement.
+fn fix(description: &str, spans: &[DiagnosticSpan]) -> Json {
+    // The replacements of each file, in the order the files first appear.
+    let mut changes: Vec<(&DiagnosticSpan, Vec<Json>)> = Vec::new();
+    for span in spans {
+        let inserted = span.suggested_replacement.as_ref().map(|r| &r[..]).unwrap_or("");
+        let replacement = object(vec![("deletedRegion", region(span)),
+                                      ("insertedContent", text(inserted))]);
+        match changes.iter().position(|&(first, _)| first.file_name == span.file_name) {
+            Some(i) => changes[i].1.push(replacement),
+            None => changes.push((span, vec![replacement])),
+        }
+    }
+
+    let artifact_changes = changes.into_iter().map(|(first, replacements)| {
+        object(vec![("artifactLocation", artifact_location(first)),
+                    ("replacements", Json::Array(replacements))])
+    }).collect();
+    let mut fix = vec![("description", text(description)),
+                       ("artifactChanges", Json::Array(artifact_changes))];
+    if let Some(applicability) = spans.first().and_then(|span| span.suggestion_applicability) {
+        let applicability = format!("{:?}", applicability);
+        fix.push(("properties", object(vec![("applicability", applicability.to_json())])));
+    }
+    object(fix)
+}
@@ -0,0 +1,232 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Saved benchmark results (`--save-baseline`), and the comparison of later
+//! runs against them (`--baseline`).
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::path::PathBuf;
+
+use stats::Summary;
+
+/// The statistics kept in a baseline for a single benchmark, in
+/// nanoseconds per iteration.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BaselineSample {
+    pub median: f64,
+    pub median_abs_dev: f64,
+}
+
+impl<'a> From<&'a Summary> for BaselineSample {
+    fn from(summ: &'a Summary) -> BaselineSample {
+        BaselineSample {
+            median: summ.median,
+            median_abs_dev: summ.median_abs_dev,
+        }
+    }
+}
+
+/// A named set of benchmark results.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Baseline(BTreeMap<String, BaselineSample>);
+
+impl Baseline {
+    pub fn new() -> Baseline {
+        Baseline(BTreeMap::new())
+    }
+
+    pub fn insert(&mut self, name: &str, sample: BaselineSample) {
+        self.0.insert(name.to_owned(), sample);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&BaselineSample> {
+        self.0.get(name)
+    }
+
+    pub fn iter<'a>(&'a self) -> Box<Iterator<Item = (&'a str, &'a BaselineSample)> + 'a> {
+        Box::new(self.0.iter().map(|(k, v)| (&**k, v)))
+    }
+
+    /// Load the baseline saved under `name` by an earlier run.
+    pub fn load(name: &str) -> io::Result<Baseline> {
+        let path = baseline_path(name)?;
+        File::open(&path)
+            .and_then(|f| Baseline::read_from(BufReader::new(f)))
+            .map_err(|e| {
+                io::Error::new(e.kind(),
+                               format!("could not read benchmark baseline `{}` from {}: {}",
+                                       name,
+                                       path.display(),
+                                       e))
+            })
+    }
+
+    /// Save this baseline under `name`, replacing any earlier one.
+    pub fn save(&self, name: &str) -> io::Result<()> {
+        let path = baseline_path(name)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        self.write_to(File::create(&path)?)
+    }
+
+    // One benchmark per line: the median and deviation, then the name, which
+    // goes last so that it may contain spaces.
+    fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for (name, sample) in &self.0 {
+            writeln!(out, "{} {} {}", sample.median, sample.median_abs_dev, name)?;
+        }
+        out.flush()
+    }
+
+    fn read_from<R: BufRead>(input: R) -> io::Result<Baseline> {
+        let mut baseline = Baseline::new();
+        for line in input.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(3, ' ');
+            let median = parts.next().and_then(|s| s.parse::<f64>().ok());
+            let median_abs_dev = parts.next().and_then(|s| s.parse::<f64>().ok());
+            match (median, median_abs_dev, parts.next()) {
+                (Some(median), Some(median_abs_dev), Some(name)) => {
+                    baseline.insert(name,
+                                    BaselineSample {
+                                        median: median,
+                                        median_abs_dev: median_abs_dev,
+                                    });
+                }
+                _ => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                              format!("malformed baseline entry `{}`", line)))
+                }
+            }
+        }
+        Ok(baseline)
+    }
+}
+
+// Baselines live in `$RUST_BENCH_BASELINE_DIR` if set, otherwise in a
+// `bench-baselines` directory next to the test executable.
+fn baseline_path(name: &str) -> io::Result<PathBuf> {
+    let dir = match env::var_os("RUST_BENCH_BASELINE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let exe = env::current_exe()?;
+            match exe.parent() {
+                Some(parent) => parent.join("bench-baselines"),
+                None => PathBuf::from("bench-baselines"),
+            }
+        }
+    };
+    Ok(dir.join(format!("{}.txt", name)))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Verdict {
+    Improved,
+    Regressed,
+    NoChange,
+}
+
+/// How a benchmark changed relative to its baseline.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Comparison {
+    /// Change of the median, as a percentage of the baseline median.
+    pub change_pct: f64,
+    pub verdict: Verdict,
+}
+
+impl Comparison {
+    pub fn new(old: &BaselineSample, new: &BaselineSample) -> Comparison {
+        let diff = new.median - old.median;
+        let change_pct = if old.median != 0.0 {
+            diff / old.median * 100.0
+        } else {
+            0.0
+        };
+        // A change is only significant if the medians are further apart than
+        // the spread of both runs combined.
+        let verdict = if diff.abs() <= old.median_abs_dev + new.median_abs_dev {
+            Verdict::NoChange
+        } else if diff > 0.0 {
+            Verdict::Regressed
+        } else {
+            Verdict::Improved
+        };
+        Comparison {
+            change_pct: change_pct,
+            verdict: verdict,
+        }
+    }
+
+    /// Whether this is a significant slowdown of more than `threshold_pct`.
+    pub fn is_regression(&self, threshold_pct: f64) -> bool {
+        self.verdict == Verdict::Regressed && self.change_pct > threshold_pct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Baseline, BaselineSample, Comparison, Verdict};
+
+    fn sample(median: f64, median_abs_dev: f64) -> BaselineSample {
+        BaselineSample {
+            median: median,
+            median_abs_dev: median_abs_dev,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut baseline = Baseline::new();
+        baseline.insert("bench::simple", sample(1234.5, 12.25));
+        baseline.insert("bench::with spaces", sample(1.0, 0.0));
+
+        let mut out = Vec::new();
+        baseline.write_to(&mut out).unwrap();
+        let read = Baseline::read_from(&out[..]).unwrap();
+        assert_eq!(read, baseline);
+    }
+
+    #[test]
+    fn malformed_entry() {
+        assert!(Baseline::read_from(&b"12 not-a-number name\n"[..]).is_err());
+        assert!(Baseline::read_from(&b"12 3\n"[..]).is_err());
+    }
+
+    #[test]
+    fn comparison_verdicts() {
+        let old = sample(1000.0, 10.0);
+
+        let same = Comparison::new(&old, &sample(1015.0, 10.0));
+        assert_eq!(same.verdict, Verdict::NoChange);
+        assert!(!same.is_regression(0.0));
+
+        let slower = Comparison::new(&old, &sample(1200.0, 10.0));
+        assert_eq!(slower.verdict, Verdict::Regressed);
+        assert!((slower.change_pct - 20.0).abs() < 1e-9);
+        assert!(slower.is_regression(5.0));
+        assert!(!slower.is_regression(25.0));
+
+        let faster = Comparison::new(&old, &sample(500.0, 10.0));
+        assert_eq!(faster.verdict, Verdict::Improved);
+        assert!(!faster.is_regression(0.0));
+    }
+}
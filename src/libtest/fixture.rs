@@ -0,0 +1,207 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Setup and teardown code which runs around the tests of a suite, or
+//! around each test, and the values it shares with them.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use panic_message;
+
+// The value set up by a fixture. It holds an `Arc<T>`, so that `fixture`
+// can hand out references to it which outlive the borrow of the list.
+type FixtureValue = Arc<Any + Send + Sync>;
+
+/// The values of the fixtures which are currently set up, by name.
+pub type FixtureValues = Vec<(String, FixtureValue)>;
+
+thread_local! {
+    // The fixture values visible to the test running on this thread
+    static CURRENT_VALUES: RefCell<FixtureValues> = RefCell::new(Vec::new())
+}
+
+/// A named pair of setup and teardown functions.
+#[derive(Clone)]
+pub struct TestFixture {
+    name: String,
+    setup: Arc<Fn() -> FixtureValue + Send + Sync>,
+    teardown: Arc<Fn(&FixtureValue) + Send + Sync>,
+}
+
+impl TestFixture {
+    /// Creates a fixture called `name`. The value returned by `setup` is
+    /// available to tests through `test::fixture`, and is passed to
+    /// `teardown` once they are done with it.
+    pub fn new<T, S, D>(name: &str, setup: S, teardown: D) -> TestFixture
+        where T: Any + Send + Sync,
+              S: Fn() -> T + Send + Sync + 'static,
+              D: Fn(&T) + Send + Sync + 'static
+    {
+        TestFixture {
+            name: name.to_owned(),
+            setup: Arc::new(move || Arc::new(Arc::new(setup())) as FixtureValue),
+            teardown: Arc::new(move |value: &FixtureValue| {
+                let value: &Any = &**value;
+                if let Some(value) = value.downcast_ref::<Arc<T>>() {
+                    teardown(&**value)
+                }
+            }),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The fixtures of a test run, set with `TestOpts::fixtures`.
+#[derive(Clone)]
+pub struct TestFixtures {
+    /// Set up once before the first test and torn down after the last one,
+    /// in the process which runs the tests. With `--isolate` this is each
+    /// test's own process.
+    pub suite: Vec<TestFixture>,
+    /// Set up on the thread of each test before it runs, and torn down
+    /// after it has finished, even if it panicked. Benchmarks run without
+    /// them.
+    pub each_test: Vec<TestFixture>,
+}
+
+impl TestFixtures {
+    pub fn new() -> TestFixtures {
+        TestFixtures {
+            suite: Vec::new(),
+            each_test: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.suite.is_empty() && self.each_test.is_empty()
+    }
+}
+
+/// Returns the value set up for the current test by the fixture called
+/// `name`, or `None` if there is no such fixture or its value is not a `T`.
+///
+/// Per-test fixtures shadow suite fixtures of the same name. The values are
+/// only visible from the thread which runs the test.
+pub fn fixture<T: Any + Send + Sync>(name: &str) -> Option<Arc<T>> {
+    CURRENT_VALUES.with(|values| {
+        values.borrow()
+              .iter()
+              .rev()
+              .find(|&&(ref n, _)| n == name)
+              .and_then(|&(_, ref value)| {
+                  let value: &Any = &**value;
+                  value.downcast_ref::<Arc<T>>().cloned()
+              })
+    })
+}
+
+// Makes `values` visible to `fixture` on this thread, until `clear_current`.
+pub fn set_current(values: FixtureValues) {
+    CURRENT_VALUES.with(|current| *current.borrow_mut() = values);
+}
+
+pub fn clear_current() {
+    CURRENT_VALUES.with(|current| current.borrow_mut().clear());
+}
+
+// Sets up `fixtures` in order, stopping at the first whose setup panics.
+// Returns the values of those which were set up, and the failure if any.
+pub fn set_up(fixtures: &[TestFixture]) -> (FixtureValues, Option<String>) {
+    let mut values = Vec::new();
+    for fixture in fixtures {
+        match catch_unwind(AssertUnwindSafe(|| (*fixture.setup)())) {
+            Ok(value) => values.push((fixture.name.clone(), value)),
+            Err(err) => return (values, Some(failure("setup", &fixture.name, &err))),
+        }
+    }
+    (values, None)
+}
+
+// Tears down, in reverse order, the fixtures for which `set_up` returned
+// `values`. All of them are torn down even if some panic; the first
+// failure is returned.
+pub fn tear_down(fixtures: &[TestFixture], values: FixtureValues) -> Option<String> {
+    let mut first_failure = None;
+    for (fixture, (_, value)) in fixtures.iter().zip(values).rev() {
+        if let Err(err) = catch_unwind(AssertUnwindSafe(|| (*fixture.teardown)(&value))) {
+            if first_failure.is_none() {
+                first_failure = Some(failure("teardown", &fixture.name, &err));
+            }
+        }
+    }
+    first_failure
+}
+
+fn failure(stage: &str, name: &str, err: &Box<Any + Send>) -> String {
+    match panic_message(err) {
+        Some(msg) => format!("{} of fixture `{}` panicked: {}", stage, name, msg),
+        None => format!("{} of fixture `{}` panicked", stage, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TestFixture, fixture, set_up, tear_down, set_current, clear_current};
+    use std::sync::{Arc, Mutex};
+
+    fn logging_fixture(name: &'static str, log: Arc<Mutex<Vec<String>>>) -> TestFixture {
+        let setup_log = log.clone();
+        TestFixture::new(name,
+                         move || {
+                             setup_log.lock().unwrap().push(format!("setup {}", name));
+                             name.len()
+                         },
+                         move |_| log.lock().unwrap().push(format!("teardown {}", name)))
+    }
+
+    #[test]
+    fn teardown_runs_in_reverse_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let fixtures = vec![logging_fixture("a", log.clone()), logging_fixture("b", log.clone())];
+        let (values, failure) = set_up(&fixtures);
+        assert!(failure.is_none());
+        assert_eq!(tear_down(&fixtures, values), None);
+        assert_eq!(*log.lock().unwrap(),
+                   vec!["setup a", "setup b", "teardown b", "teardown a"]);
+    }
+
+    #[test]
+    fn failed_setup_tears_down_earlier_fixtures() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let fixtures = vec![logging_fixture("a", log.clone()),
+                            TestFixture::new("broken", || -> () { panic!("no database") }, |_| {}),
+                            logging_fixture("c", log.clone())];
+        let (values, failure) = set_up(&fixtures);
+        assert_eq!(failure,
+                   Some("setup of fixture `broken` panicked: no database".to_owned()));
+        assert_eq!(tear_down(&fixtures, values), None);
+        assert_eq!(*log.lock().unwrap(), vec!["setup a", "teardown a"]);
+    }
+
+    #[test]
+    fn values_are_visible_to_the_test() {
+        let fixtures = vec![TestFixture::new("dir", || "/tmp/x".to_owned(), |_| {})];
+        let (values, _) = set_up(&fixtures);
+        set_current(values.clone());
+        assert_eq!(fixture::<String>("dir").map(|s| (*s).clone()),
+                   Some("/tmp/x".to_owned()));
+        assert!(fixture::<u32>("dir").is_none());
+        assert!(fixture::<String>("other").is_none());
+        clear_current();
+        assert!(fixture::<String>("dir").is_none());
+        tear_down(&fixtures, values);
+    }
+}
@@ -49,14 +49,14 @@ pub use self::TestFn::*;
 pub use self::ColorConfig::*;
 pub use self::TestResult::*;
 pub use self::TestName::*;
-use self::TestEvent::*;
+pub use self::TestEvent::*;
 use self::NamePadding::*;
 use self::OutputLocation::*;
 
 use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::any::Any;
 use std::cmp;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fmt;
 use std::fs::File;
@@ -64,13 +64,26 @@ use std::io::prelude::*;
 use std::io;
 use std::iter::repeat;
 use std::path::PathBuf;
+use std::process::{self, Child, ChildStderr, ChildStdout, Command, Stdio};
+use std::str;
 use std::sync::mpsc::{channel, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::{Instant, Duration};
+use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
 
 const TEST_WARN_TIMEOUT_S: u64 = 60;
 
+// Written to stdout by a test process spawned by `--isolate` once its test
+// has finished, followed by the result of the test and its captured output.
+const ISOLATED_RESULT_MARKER: &'static [u8] = b"\n%%rust-isolated-test-result%% ";
+
+// Set by `--isolate` to the name of the test which a process is spawned for.
+const ISOLATED_TEST_VAR: &'static str = "RUST_TEST_ISOLATED_TEST";
+
+/// Percentage by which a benchmark may slow down relative to `--baseline`
+/// before the run fails, unless `--regression-threshold` says otherwise.
+pub const DEFAULT_REGRESSION_THRESHOLD: f64 = 5.0;
+
 // to be used by rustc to compile tests in libtest
 pub mod test {
     pub use {Bencher, TestName, TestResult, TestDesc, TestDescAndFn, TestOpts, TrFailed,
@@ -80,6 +93,13 @@ pub mod test {
 }
 
 pub mod stats;
+pub mod baseline;
+mod fixture;
+
+pub use fixture::{TestFixture, TestFixtures, fixture};
+use fixture::FixtureValues;
+
+use baseline::{Baseline, BaselineSample, Comparison, Verdict};
 
 // The name of a test. By convention this follows the rules for rust
 // paths; i.e. it should be a series of identifiers separated by double
@@ -106,7 +126,7 @@ impl fmt::Display for TestName {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum NamePadding {
+pub enum NamePadding {
     PadNone,
     PadOnRight,
 }
@@ -150,6 +170,9 @@ pub enum TestFn {
     StaticBenchFn(fn(&mut Bencher)),
     StaticMetricFn(fn(&mut MetricMap)),
     DynTestFn(Box<FnBox<()>>),
+    /// A dynamic test which can be run more than once, so that it can be
+    /// retried with `--retries`.
+    DynRetryableTestFn(Arc<Fn() + Send + Sync>),
     DynMetricFn(Box<for<'a> FnBox<&'a mut MetricMap>>),
     DynBenchFn(Box<TDynBenchFn + 'static>),
 }
@@ -161,10 +184,20 @@ impl TestFn {
             StaticBenchFn(..) => PadOnRight,
             StaticMetricFn(..) => PadOnRight,
             DynTestFn(..) => PadNone,
+            DynRetryableTestFn(..) => PadNone,
             DynMetricFn(..) => PadOnRight,
             DynBenchFn(..) => PadOnRight,
         }
     }
+
+    // Another handle on the test function, if it can be run again.
+    fn try_clone(&self) -> Option<TestFn> {
+        match *self {
+            StaticTestFn(f) => Some(StaticTestFn(f)),
+            DynRetryableTestFn(ref f) => Some(DynRetryableTestFn(f.clone())),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Debug for TestFn {
@@ -174,6 +207,7 @@ impl fmt::Debug for TestFn {
             StaticBenchFn(..) => "StaticBenchFn(..)",
             StaticMetricFn(..) => "StaticMetricFn(..)",
             DynTestFn(..) => "DynTestFn(..)",
+            DynRetryableTestFn(..) => "DynRetryableTestFn(..)",
             DynMetricFn(..) => "DynMetricFn(..)",
             DynBenchFn(..) => "DynBenchFn(..)",
         })
@@ -212,6 +246,8 @@ pub struct TestDesc {
     pub name: TestName,
     pub ignore: bool,
     pub should_panic: ShouldPanic,
+    // Overrides the `--test-timeout` limit for this test, in seconds
+    pub timeout: Option<u64>,
 }
 
 #[derive(Clone)]
@@ -255,8 +291,15 @@ impl Clone for MetricMap {
 // The default console test runner. It accepts the command line
 // arguments and a vector of test_descs.
 pub fn test_main(args: &[String], tests: Vec<TestDescAndFn>) {
+    test_main_with_fixtures(args, tests, TestFixtures::new())
+}
+
+/// Like `test_main`, but runs the tests with the given fixtures.
+pub fn test_main_with_fixtures(args: &[String],
+                               tests: Vec<TestDescAndFn>,
+                               fixtures: TestFixtures) {
     let opts = match parse_opts(args) {
-        Some(Ok(o)) => o,
+        Some(Ok(o)) => TestOpts { fixtures: fixtures, ..o },
         Some(Err(msg)) => panic!("{:?}", msg),
         None => return,
     };
@@ -311,6 +354,16 @@ pub enum ColorConfig {
     NeverColor,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum OutputFormat {
+    Pretty,
+    Json,
+}
+
+/// The options of a test run.
+///
+/// Start from `TestOpts::new` or `parse_opts` and set the options which
+/// matter, as options are added over time.
 pub struct TestOpts {
     pub list: bool,
     pub filter: Option<String>,
@@ -319,29 +372,55 @@ pub struct TestOpts {
     pub run_tests: bool,
     pub bench_benchmarks: bool,
     pub logfile: Option<PathBuf>,
+    pub junit_report: Option<PathBuf>,
     pub nocapture: bool,
     pub color: ColorConfig,
+    pub format: OutputFormat,
     pub quiet: bool,
     pub test_threads: Option<usize>,
+    pub test_timeout: Option<u64>,
     pub skip: Vec<String>,
+    pub isolate: bool,
+    pub shard: Option<(usize, usize)>,
+    pub shuffle_seed: Option<u64>,
+    pub save_baseline: Option<String>,
+    pub baseline: Option<String>,
+    pub regression_threshold: f64,
+    pub retries: usize,
+    pub fixtures: TestFixtures,
 }
 
 impl TestOpts {
-    #[cfg(test)]
-    fn new() -> TestOpts {
+    /// Options which run every test, as with an empty command line.
+    ///
+    /// Unlike `parse_opts` this does not read `RUST_TEST_NOCAPTURE`, but as
+    /// long as `test_threads` is `None`, the number of threads still comes
+    /// from `RUST_TEST_THREADS` when it is set.
+    pub fn new() -> TestOpts {
         TestOpts {
             list: false,
             filter: None,
             filter_exact: false,
             run_ignored: false,
-            run_tests: false,
+            run_tests: true,
             bench_benchmarks: false,
             logfile: None,
+            junit_report: None,
             nocapture: false,
             color: AutoColor,
+            format: OutputFormat::Pretty,
             quiet: false,
             test_threads: None,
+            test_timeout: None,
             skip: vec![],
+            isolate: false,
+            shard: None,
+            shuffle_seed: None,
+            save_baseline: None,
+            baseline: None,
+            regression_threshold: DEFAULT_REGRESSION_THRESHOLD,
+            retries: 0,
+            fixtures: TestFixtures::new(),
         }
     }
 }
@@ -358,18 +437,42 @@ fn optgroups() -> Vec<getopts::OptGroup> {
       getopts::optflag("h", "help", "Display this message (longer with --help)"),
       getopts::optopt("", "logfile", "Write logs to the specified file instead \
                           of stdout", "PATH"),
+      getopts::optopt("", "junit-report", "Write a JUnit XML report of the test run to \
+                          the specified file", "PATH"),
       getopts::optflag("", "nocapture", "don't capture stdout/stderr of each \
                                          task, allow printing directly"),
       getopts::optopt("", "test-threads", "Number of threads used for running tests \
                                            in parallel", "n_threads"),
+      getopts::optopt("", "test-timeout", "Fail tests which run for longer than the given \
+                                           number of seconds", "SECS"),
       getopts::optmulti("", "skip", "Skip tests whose names contain FILTER (this flag can \
                                      be used multiple times)","FILTER"),
       getopts::optflag("q", "quiet", "Display one character per test instead of one line"),
+      getopts::optopt("", "retries", "Run each failing test again up to N times, reporting \
+                                      tests which pass on a later attempt as flaky", "N"),
       getopts::optflag("", "exact", "Exactly match filters rather than by substring"),
+      getopts::optopt("", "shard", "Only run the tests in shard INDEX out of TOTAL shards, \
+                                    counting from 1. Tests are assigned to shards by \
+                                    name", "INDEX/TOTAL"),
+      getopts::optflag("", "shuffle", "Run tests in a random order"),
+      getopts::optopt("", "save-baseline", "Save the benchmark results under NAME for later \
+                                            comparison with --baseline", "NAME"),
+      getopts::optopt("", "baseline", "Compare benchmark results with those saved under NAME \
+                                       by --save-baseline", "NAME"),
+      getopts::optopt("", "regression-threshold", "Fail when a benchmark is significantly \
+                                                   slower than the baseline by more than PCT \
+                                                   percent (default 5)", "PCT"),
+      getopts::optopt("", "shuffle-seed", "Run tests in a random order determined by SEED, \
+                                           as printed by an earlier --shuffle run", "SEED"),
+      getopts::optflag("", "isolate", "Run each test in a separate process, so that a \
+                                       test which crashes does not take down the others"),
       getopts::optopt("", "color", "Configure coloring of output:
             auto   = colorize if stdout is a tty and tests are run on serially (default);
             always = always colorize output;
-            never  = never colorize output;", "auto|always|never")]
+            never  = never colorize output;", "auto|always|never"),
+      getopts::optopt("", "format", "Configure formatting of output:
+            pretty = print verbose output (default);
+            json   = print one JSON object per test event;", "pretty|json")]
 }
 
 fn usage(binary: &str) {
@@ -400,7 +503,10 @@ Test Attributes:
     #[ignore]      - When applied to a function which is already attributed as a
                      test, then the test runner will ignore these tests during
                      normal test runs. Running with --ignored will run these
-                     tests."#,
+                     tests.
+    #[timeout = "SECS"] - Fail this test (also labeled with #[test]) if it runs
+                     for longer than SECS seconds, overriding --test-timeout.
+                     Requires #![feature(test_timeout)]."#,
              usage = getopts::usage(&message, &optgroups()));
 }
 
@@ -431,6 +537,9 @@ pub fn parse_opts(args: &[String]) -> Option<OptRes> {
     let logfile = matches.opt_str("logfile");
     let logfile = logfile.map(|s| PathBuf::from(&s));
 
+    let junit_report = matches.opt_str("junit-report");
+    let junit_report = junit_report.map(|s| PathBuf::from(&s));
+
     let bench_benchmarks = matches.opt_present("bench");
     let run_tests = !bench_benchmarks || matches.opt_present("test");
 
@@ -454,6 +563,76 @@ pub fn parse_opts(args: &[String]) -> Option<OptRes> {
             None,
     };
 
+    let test_timeout = match matches.opt_str("test-timeout") {
+        Some(secs) =>
+            match secs.parse::<u64>() {
+                Ok(n) if n > 0 => Some(n),
+                _ =>
+                    return Some(Err(format!("argument for --test-timeout must be a number of \
+                                             seconds > 0 (was {})", secs)))
+            },
+        None =>
+            None,
+    };
+
+    let isolate = matches.opt_present("isolate");
+    if isolate && cfg!(target_os = "emscripten") {
+        return Some(Err("--isolate is not supported on this platform".to_string()));
+    }
+
+    let shard = match matches.opt_str("shard") {
+        Some(shard) => {
+            let mut parts = shard.splitn(2, '/').map(|s| s.parse::<usize>());
+            match (parts.next(), parts.next()) {
+                (Some(Ok(index)), Some(Ok(total))) if index >= 1 && index <= total => {
+                    Some((index, total))
+                }
+                _ => {
+                    return Some(Err(format!("argument for --shard must be of the form \
+                                             INDEX/TOTAL with 1 <= INDEX <= TOTAL (was {})",
+                                            shard)))
+                }
+            }
+        }
+        None => None,
+    };
+
+    let shuffle_seed = match matches.opt_str("shuffle-seed") {
+        Some(seed) =>
+            match seed.parse::<u64>() {
+                Ok(n) => Some(n),
+                Err(e) =>
+                    return Some(Err(format!("argument for --shuffle-seed must be a number \
+                                             (error: {})", e)))
+            },
+        None if matches.opt_present("shuffle") => Some(random_seed()),
+        None => None,
+    };
+
+    let retries = match matches.opt_str("retries") {
+        Some(n_str) =>
+            match n_str.parse::<usize>() {
+                Ok(n) => n,
+                Err(e) =>
+                    return Some(Err(format!("argument for --retries must be a number \
+                                             (error: {})", e)))
+            },
+        None =>
+            0,
+    };
+
+    let regression_threshold = match matches.opt_str("regression-threshold") {
+        Some(pct) =>
+            match pct.parse::<f64>() {
+                Ok(n) if n >= 0.0 => n,
+                _ =>
+                    return Some(Err(format!("argument for --regression-threshold must be a \
+                                             percentage >= 0 (was {})", pct)))
+            },
+        None =>
+            DEFAULT_REGRESSION_THRESHOLD,
+    };
+
     let color = match matches.opt_str("color").as_ref().map(|s| &**s) {
         Some("auto") | None => AutoColor,
         Some("always") => AlwaysColor,
@@ -466,6 +645,17 @@ pub fn parse_opts(args: &[String]) -> Option<OptRes> {
         }
     };
 
+    let format = match matches.opt_str("format").as_ref().map(|s| &**s) {
+        Some("pretty") | None => OutputFormat::Pretty,
+        Some("json") => OutputFormat::Json,
+
+        Some(v) => {
+            return Some(Err(format!("argument for --format must be pretty or json (was \
+                                     {})",
+                                    v)))
+        }
+    };
+
     let test_opts = TestOpts {
         list: list,
         filter: filter,
@@ -474,20 +664,51 @@ pub fn parse_opts(args: &[String]) -> Option<OptRes> {
         run_tests: run_tests,
         bench_benchmarks: bench_benchmarks,
         logfile: logfile,
+        junit_report: junit_report,
         nocapture: nocapture,
         color: color,
+        format: format,
         quiet: quiet,
         test_threads: test_threads,
+        test_timeout: test_timeout,
         skip: matches.opt_strs("skip"),
+        isolate: isolate,
+        shard: shard,
+        shuffle_seed: shuffle_seed,
+        save_baseline: matches.opt_str("save-baseline"),
+        baseline: matches.opt_str("baseline"),
+        regression_threshold: regression_threshold,
+        retries: retries,
+        fixtures: TestFixtures::new(),
     };
 
     Some(Ok(test_opts))
 }
 
+// A seed for `--shuffle` which differs between runs
+fn random_seed() -> u64 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::new(0, 0));
+    ShuffleRng(now.as_secs() ^ ((now.subsec_nanos() as u64) << 32)).next_u64()
+}
+
+// A small deterministic pseudo-random number generator (splitmix64), so that
+// a shuffled test order can be replayed from its seed on any platform.
+struct ShuffleRng(u64);
+
+impl ShuffleRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
 #[derive(Clone, PartialEq)]
 pub struct BenchSamples {
-    ns_iter_summ: stats::Summary,
-    mb_s: usize,
+    pub ns_iter_summ: stats::Summary,
+    pub mb_s: usize,
 }
 
 #[derive(Clone, PartialEq)]
@@ -495,6 +716,8 @@ pub enum TestResult {
     TrOk,
     TrFailed,
     TrFailedMsg(String),
+    TrTimedOut,
+    TrFixtureFailed(String),
     TrIgnored,
     TrMetrics(MetricMap),
     TrBench(BenchSamples),
@@ -509,17 +732,28 @@ enum OutputLocation<T> {
 
 struct ConsoleTestState<T> {
     log_out: Option<File>,
+    junit_out: Option<File>,
     out: OutputLocation<T>,
     use_color: bool,
+    format: OutputFormat,
     quiet: bool,
     total: usize,
     passed: usize,
     failed: usize,
     ignored: usize,
     measured: usize,
+    fixture_errors: usize,
     metrics: MetricMap,
     failures: Vec<(TestDesc, Vec<u8>)>,
+    // What each failed fixture was set up for, and the output
+    fixture_failures: Vec<(String, Vec<u8>)>,
+    // Failure messages of the earlier attempts at tests which were retried
+    retried: HashMap<TestName, Vec<String>>,
+    flaky: Vec<String>,
+    regressions: Vec<String>,
+    junit_results: Vec<(TestDesc, TestResult, Vec<u8>, Duration)>,
     max_name_len: usize, // number of columns to fill when aligning names
+    shuffle_seed: Option<u64>,
 }
 
 impl<T: Write> ConsoleTestState<T> {
@@ -528,6 +762,10 @@ impl<T: Write> ConsoleTestState<T> {
             Some(ref path) => Some(File::create(path)?),
             None => None,
         };
+        let junit_out = match opts.junit_report {
+            Some(ref path) => Some(File::create(path)?),
+            None => None,
+        };
         let out = match term::stdout() {
             None => Raw(io::stdout()),
             Some(t) => Pretty(t),
@@ -536,16 +774,25 @@ impl<T: Write> ConsoleTestState<T> {
         Ok(ConsoleTestState {
             out: out,
             log_out: log_out,
+            junit_out: junit_out,
             use_color: use_color(opts),
+            format: opts.format,
             quiet: opts.quiet,
             total: 0,
             passed: 0,
             failed: 0,
             ignored: 0,
             measured: 0,
+            fixture_errors: 0,
             metrics: MetricMap::new(),
             failures: Vec::new(),
+            fixture_failures: Vec::new(),
+            retried: HashMap::new(),
+            flaky: Vec::new(),
+            regressions: Vec::new(),
+            junit_results: Vec::new(),
             max_name_len: 0,
+            shuffle_seed: opts.shuffle_seed,
         })
     }
 
@@ -557,6 +804,18 @@ impl<T: Write> ConsoleTestState<T> {
         self.write_short_result("FAILED", "F", term::color::RED)
     }
 
+    pub fn write_timed_out(&mut self) -> io::Result<()> {
+        self.write_short_result("TIMED OUT", "T", term::color::RED)
+    }
+
+    pub fn write_fixture_failed(&mut self) -> io::Result<()> {
+        self.write_short_result("FIXTURE FAILED", "E", term::color::RED)
+    }
+
+    pub fn write_retrying(&mut self) -> io::Result<()> {
+        self.write_short_result("FAILED (retrying)", "r", term::color::YELLOW)
+    }
+
     pub fn write_ignored(&mut self) -> io::Result<()> {
         self.write_short_result("ignored", "i", term::color::YELLOW)
     }
@@ -614,16 +873,39 @@ impl<T: Write> ConsoleTestState<T> {
 
     pub fn write_run_start(&mut self, len: usize) -> io::Result<()> {
         self.total = len;
+        if self.format == OutputFormat::Json {
+            let seed = match self.shuffle_seed {
+                Some(seed) => format!(", \"shuffle_seed\": {}", seed),
+                None => String::new(),
+            };
+            return self.write_plain(&format!("{{ \"type\": \"suite\", \"event\": \"started\", \
+                                              \"test_count\": {}{} }}\n",
+                                             len,
+                                             seed));
+        }
         let noun = if len != 1 {
             "tests"
         } else {
             "test"
         };
-        self.write_plain(&format!("\nrunning {} {}\n", len, noun))
+        self.write_plain(&format!("\nrunning {} {}\n", len, noun))?;
+        match self.shuffle_seed {
+            Some(seed) => {
+                self.write_plain(&format!("shuffling with seed {} (pass --shuffle-seed {} to \
+                                           run them in this order again)\n",
+                                          seed,
+                                          seed))
+            }
+            None => Ok(()),
+        }
     }
 
     pub fn write_test_start(&mut self, test: &TestDesc, align: NamePadding) -> io::Result<()> {
-        if self.quiet && align != PadOnRight {
+        if self.format == OutputFormat::Json {
+            self.write_plain(&format!("{{ \"type\": \"test\", \"event\": \"started\", \
+                                       \"name\": {} }}\n",
+                                      json_str(test.name.as_slice())))
+        } else if self.quiet && align != PadOnRight {
             Ok(())
         } else {
             let name = test.padded_name(self.max_name_len, align);
@@ -631,10 +913,20 @@ impl<T: Write> ConsoleTestState<T> {
         }
     }
 
-    pub fn write_result(&mut self, result: &TestResult) -> io::Result<()> {
+    pub fn write_result(&mut self,
+                        test: &TestDesc,
+                        result: &TestResult,
+                        stdout: &[u8],
+                        exec_time: Duration)
+                        -> io::Result<()> {
+        if self.format == OutputFormat::Json {
+            return self.write_json_result(test, result, stdout, exec_time);
+        }
         match *result {
             TrOk => self.write_ok(),
             TrFailed | TrFailedMsg(_) => self.write_failed(),
+            TrTimedOut => self.write_timed_out(),
+            TrFixtureFailed(_) => self.write_fixture_failed(),
             TrIgnored => self.write_ignored(),
             TrMetrics(ref mm) => {
                 self.write_metric()?;
@@ -647,12 +939,135 @@ impl<T: Write> ConsoleTestState<T> {
         }
     }
 
+    pub fn write_json_result(&mut self,
+                             test: &TestDesc,
+                             result: &TestResult,
+                             stdout: &[u8],
+                             exec_time: Duration)
+                             -> io::Result<()> {
+        let name = json_str(test.name.as_slice());
+        let mut line = match *result {
+            TrOk => format!("{{ \"type\": \"test\", \"event\": \"ok\", \"name\": {}", name),
+            TrFailed => {
+                format!("{{ \"type\": \"test\", \"event\": \"failed\", \"name\": {}", name)
+            }
+            TrFailedMsg(ref msg) => {
+                format!("{{ \"type\": \"test\", \"event\": \"failed\", \"name\": {}, \
+                         \"message\": {}",
+                        name,
+                        json_str(msg))
+            }
+            TrTimedOut => {
+                format!("{{ \"type\": \"test\", \"event\": \"timed_out\", \"name\": {}", name)
+            }
+            TrFixtureFailed(ref msg) => {
+                format!("{{ \"type\": \"test\", \"event\": \"fixture_failed\", \"name\": {}, \
+                         \"message\": {}",
+                        name,
+                        json_str(msg))
+            }
+            TrIgnored => {
+                format!("{{ \"type\": \"test\", \"event\": \"ignored\", \"name\": {}", name)
+            }
+            TrMetrics(ref mm) => {
+                format!("{{ \"type\": \"metric\", \"name\": {}, \"metrics\": {}",
+                        name,
+                        mm.fmt_metrics_json())
+            }
+            TrBench(ref bs) => {
+                format!("{{ \"type\": \"bench\", \"name\": {}, {}",
+                        name,
+                        fmt_bench_samples_json(bs))
+            }
+        };
+        if let TrOk = *result {
+            if self.retried.contains_key(&test.name) {
+                line.push_str(", \"flaky\": true");
+            }
+        }
+        line.push_str(&format!(", \"exec_time\": {}", fmt_secs(exec_time)));
+        if !stdout.is_empty() {
+            line.push_str(&format!(", \"stdout\": {}",
+                                   json_str(&String::from_utf8_lossy(stdout))));
+        }
+        line.push_str(" }\n");
+        self.write_plain(&line)
+    }
+
+    pub fn write_retry(&mut self,
+                       test: &TestDesc,
+                       result: &TestResult,
+                       stdout: &[u8],
+                       exec_time: Duration)
+                       -> io::Result<()> {
+        let msg = match *result {
+            TrFailedMsg(ref msg) => msg.clone(),
+            _ => "test failed".to_owned(),
+        };
+        self.write_log(format!("failed (retrying) {}\n", test.name))?;
+        let attempt = {
+            let attempts = self.retried.entry(test.name.clone()).or_insert(Vec::new());
+            attempts.push(msg.clone());
+            attempts.len()
+        };
+
+        if self.format != OutputFormat::Json {
+            return self.write_retrying();
+        }
+        let mut line = format!("{{ \"type\": \"test\", \"event\": \"retry\", \"name\": {}, \
+                                \"attempt\": {}, \"message\": {}, \"exec_time\": {}",
+                               json_str(test.name.as_slice()),
+                               attempt,
+                               json_str(&msg),
+                               fmt_secs(exec_time));
+        if !stdout.is_empty() {
+            line.push_str(&format!(", \"stdout\": {}",
+                                   json_str(&String::from_utf8_lossy(stdout))));
+        }
+        line.push_str(" }\n");
+        self.write_plain(&line)
+    }
+
     pub fn write_timeout(&mut self, desc: &TestDesc) -> io::Result<()> {
+        if self.format == OutputFormat::Json {
+            return self.write_plain(&format!("{{ \"type\": \"test\", \"event\": \"timeout\", \
+                                              \"name\": {} }}\n",
+                                             json_str(desc.name.as_slice())));
+        }
         self.write_plain(&format!("test {} has been running for over {} seconds\n",
                                   desc.name,
                                   TEST_WARN_TIMEOUT_S))
     }
 
+    pub fn write_still_running(&mut self, tests: &[TestDesc]) -> io::Result<()> {
+        if self.format == OutputFormat::Json {
+            let names: Vec<String> = tests.iter()
+                                          .map(|t| json_str(t.name.as_slice()))
+                                          .collect();
+            return self.write_plain(&format!("{{ \"type\": \"suite\", \
+                                              \"event\": \"still_running\", \
+                                              \"names\": [{}] }}\n",
+                                             names.join(", ")));
+        }
+        self.write_plain("\ntests still running after exceeding their time limit:\n")?;
+        for test in tests {
+            self.write_plain(&format!("    {}\n", test.name))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_suite_fixture_failed(&mut self, msg: &str) -> io::Result<()> {
+        self.write_log(format!("suite fixture failed: {}\n", msg))?;
+        self.fixture_failures.push(("suite".to_owned(), format!("note: {}", msg).into_bytes()));
+        if self.format == OutputFormat::Json {
+            return self.write_plain(&format!("{{ \"type\": \"suite\", \
+                                              \"event\": \"fixture_failed\", \
+                                              \"message\": {} }}\n",
+                                             json_str(msg)));
+        }
+        Ok(())
+    }
+
     pub fn write_log<S: AsRef<str>>(&mut self, msg: S) -> io::Result<()> {
         let msg = msg.as_ref();
         match self.log_out {
@@ -668,6 +1083,8 @@ impl<T: Write> ConsoleTestState<T> {
                         TrOk => "ok".to_owned(),
                         TrFailed => "failed".to_owned(),
                         TrFailedMsg(ref msg) => format!("failed: {}", msg),
+                        TrTimedOut => "timed out".to_owned(),
+                        TrFixtureFailed(ref msg) => format!("fixture failed: {}", msg),
                         TrIgnored => "ignored".to_owned(),
                         TrMetrics(ref mm) => mm.fmt_metrics(),
                         TrBench(ref bs) => fmt_bench_samples(bs),
@@ -675,6 +1092,101 @@ impl<T: Write> ConsoleTestState<T> {
                     test.name))
     }
 
+    pub fn record_junit_result(&mut self,
+                               test: &TestDesc,
+                               result: &TestResult,
+                               stdout: &[u8],
+                               exec_time: Duration) {
+        if self.junit_out.is_some() {
+            self.junit_results.push((test.clone(), result.clone(), stdout.to_vec(), exec_time));
+        }
+    }
+
+    pub fn write_junit_report(&mut self) -> io::Result<()> {
+        let out = match self.junit_out {
+            Some(ref mut o) => o,
+            None => return Ok(()),
+        };
+
+        let suite_name = env::current_exe()
+                             .ok()
+                             .and_then(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                             .unwrap_or("test".to_owned());
+
+        let mut failures = 0;
+        let mut errors = 0;
+        let mut skipped = 0;
+        let mut total_time = Duration::new(0, 0);
+        let mut cases = String::new();
+        for &(ref test, ref result, ref stdout, exec_time) in &self.junit_results {
+            total_time = total_time + exec_time;
+
+            let full_name = test.name.as_slice();
+            let (class_name, name) = match full_name.rfind("::") {
+                Some(i) => (&full_name[..i], &full_name[i + 2..]),
+                None => (&suite_name[..], full_name),
+            };
+            cases.push_str(&format!("    <testcase classname=\"{}\" name=\"{}\" time=\"{}\">\n",
+                                    xml_escape(class_name),
+                                    xml_escape(name),
+                                    fmt_secs(exec_time)));
+
+            let mut output = String::from_utf8_lossy(stdout).into_owned();
+            match *result {
+                TrOk => {
+                    let retries = self.retried.get(&test.name).map(|v| &v[..]).unwrap_or(&[]);
+                    for msg in retries {
+                        cases.push_str(&format!("      <flakyFailure message=\"{}\"/>\n",
+                                                xml_escape(msg)));
+                    }
+                }
+                TrFailed => {
+                    failures += 1;
+                    cases.push_str("      <failure message=\"test failed\"/>\n");
+                }
+                TrFailedMsg(ref msg) => {
+                    failures += 1;
+                    cases.push_str(&format!("      <failure message=\"{0}\">{0}</failure>\n",
+                                            xml_escape(msg)));
+                }
+                TrTimedOut => {
+                    failures += 1;
+                    cases.push_str("      <failure message=\"test timed out\"/>\n");
+                }
+                TrFixtureFailed(ref msg) => {
+                    errors += 1;
+                    cases.push_str(&format!("      <error message=\"{0}\">{0}</error>\n",
+                                            xml_escape(msg)));
+                }
+                TrIgnored => {
+                    skipped += 1;
+                    cases.push_str("      <skipped/>\n");
+                }
+                TrMetrics(ref mm) => output.push_str(&mm.fmt_metrics()),
+                TrBench(ref bs) => output.push_str(&fmt_bench_samples(bs)),
+            }
+            if !output.is_empty() {
+                cases.push_str(&format!("      <system-out>{}</system-out>\n",
+                                        xml_escape(&output)));
+            }
+            cases.push_str("    </testcase>\n");
+        }
+
+        out.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n")?;
+        out.write_all(format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" \
+                               errors=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+                              xml_escape(&suite_name),
+                              self.junit_results.len(),
+                              failures,
+                              errors,
+                              skipped,
+                              fmt_secs(total_time))
+                          .as_bytes())?;
+        out.write_all(cases.as_bytes())?;
+        out.write_all(b"  </testsuite>\n</testsuites>\n")?;
+        out.flush()
+    }
+
     pub fn write_failures(&mut self) -> io::Result<()> {
         self.write_plain("\nfailures:\n")?;
         let mut failures = Vec::new();
@@ -701,13 +1213,118 @@ impl<T: Write> ConsoleTestState<T> {
         Ok(())
     }
 
+    pub fn write_fixture_failures(&mut self) -> io::Result<()> {
+        self.write_plain("\nfixture failures:\n")?;
+        let mut fixture_failures = self.fixture_failures.clone();
+        fixture_failures.sort();
+        for &(ref name, ref output) in &fixture_failures {
+            self.write_plain(&format!("---- {} ----\n\t{}\n",
+                                      name,
+                                      String::from_utf8_lossy(output)))?;
+        }
+        Ok(())
+    }
+
+    pub fn write_baseline_comparison(&mut self,
+                                     baseline_name: &str,
+                                     baseline: &Baseline,
+                                     results: &Baseline,
+                                     threshold_pct: f64)
+                                     -> io::Result<()> {
+        if self.format != OutputFormat::Json {
+            self.write_plain(&format!("\nbenchmarks compared with baseline `{}`:\n",
+                                      baseline_name))?;
+        }
+        for (name, new) in results.iter() {
+            let old = match baseline.get(name) {
+                Some(old) => old,
+                None => {
+                    if self.format == OutputFormat::Json {
+                        self.write_plain(&format!("{{ \"type\": \"bench_comparison\", \
+                                                   \"name\": {}, \"verdict\": \"new\" }}\n",
+                                                  json_str(name)))?;
+                    } else {
+                        self.write_plain(&format!("    {}: not in baseline\n", name))?;
+                    }
+                    continue;
+                }
+            };
+            let comparison = Comparison::new(old, new);
+            let verdict = match comparison.verdict {
+                Verdict::Improved => "improved",
+                Verdict::Regressed => "regressed",
+                Verdict::NoChange => "no change",
+            };
+            if self.format == OutputFormat::Json {
+                self.write_plain(&format!("{{ \"type\": \"bench_comparison\", \"name\": {}, \
+                                           \"baseline_median\": {}, \"median\": {}, \
+                                           \"change_pct\": {}, \"verdict\": \"{}\" }}\n",
+                                          json_str(name),
+                                          json_f64(old.median),
+                                          json_f64(new.median),
+                                          json_f64(comparison.change_pct),
+                                          verdict))?;
+            } else {
+                self.write_plain(&format!("    {}: {} -> {} ns/iter ({:+.2}%, {})\n",
+                                          name,
+                                          fmt_thousands_sep(old.median as usize, ','),
+                                          fmt_thousands_sep(new.median as usize, ','),
+                                          comparison.change_pct,
+                                          verdict))?;
+            }
+            if comparison.is_regression(threshold_pct) {
+                self.regressions.push(name.to_owned());
+            }
+        }
+        Ok(())
+    }
+
     pub fn write_run_finish(&mut self) -> io::Result<bool> {
-        assert!(self.passed + self.failed + self.ignored + self.measured == self.total);
+        assert!(self.passed + self.failed + self.ignored + self.measured +
+                self.fixture_errors == self.total);
+
+        let success = self.failed == 0 && self.regressions.is_empty() &&
+                      self.fixture_failures.is_empty();
+        self.write_junit_report()?;
+
+        if self.format == OutputFormat::Json {
+            let flaky: Vec<String> = self.flaky.iter().map(|name| json_str(name)).collect();
+            let s = format!("{{ \"type\": \"suite\", \"event\": \"{}\", \"passed\": {}, \
+                             \"failed\": {}, \"ignored\": {}, \"measured\": {}, \
+                             \"fixture_failed\": {}, \"regressed\": {}, \"flaky\": [{}] }}\n",
+                            if success { "ok" } else { "failed" },
+                            self.passed,
+                            self.failed,
+                            self.ignored,
+                            self.measured,
+                            self.fixture_failures.len(),
+                            self.regressions.len(),
+                            flaky.join(", "));
+            self.write_plain(&s)?;
+            return Ok(success);
+        }
 
-        let success = self.failed == 0;
-        if !success {
+        if self.failed != 0 {
             self.write_failures()?;
         }
+        if !self.fixture_failures.is_empty() {
+            self.write_fixture_failures()?;
+        }
+        if !self.flaky.is_empty() {
+            self.write_plain("\nflaky tests (passed after being retried):\n")?;
+            let mut flaky = self.flaky.clone();
+            flaky.sort();
+            for name in &flaky {
+                self.write_plain(&format!("    {}\n", name))?;
+            }
+        }
+        if !self.regressions.is_empty() {
+            self.write_plain("\nbenchmark regressions:\n")?;
+            let regressions = self.regressions.clone();
+            for name in &regressions {
+                self.write_plain(&format!("    {}\n", name))?;
+            }
+        }
 
         self.write_plain("\ntest result: ")?;
         if success {
@@ -716,11 +1333,18 @@ impl<T: Write> ConsoleTestState<T> {
         } else {
             self.write_pretty("FAILED", term::color::RED)?;
         }
-        let s = format!(". {} passed; {} failed; {} ignored; {} measured\n\n",
-                        self.passed,
-                        self.failed,
-                        self.ignored,
-                        self.measured);
+        let mut s = format!(". {} passed; {} failed; {} ignored; {} measured",
+                            self.passed,
+                            self.failed,
+                            self.ignored,
+                            self.measured);
+        if !self.fixture_failures.is_empty() {
+            s.push_str(&format!("; {} fixture errors", self.fixture_failures.len()));
+        }
+        if !self.flaky.is_empty() {
+            s.push_str(&format!("; {} flaky", self.flaky.len()));
+        }
+        s.push_str("\n\n");
         self.write_plain(&s)?;
         return Ok(success);
     }
@@ -767,6 +1391,71 @@ pub fn fmt_bench_samples(bs: &BenchSamples) -> String {
     output
 }
 
+pub fn fmt_bench_samples_json(bs: &BenchSamples) -> String {
+    let summ = &bs.ns_iter_summ;
+    format!("\"median\": {}, \"deviation\": {}, \"min\": {}, \"max\": {}, \"mean\": {}, \
+             \"median_abs_dev\": {}, \"mb_s\": {}",
+            json_f64(summ.median),
+            json_f64(summ.max - summ.min),
+            json_f64(summ.min),
+            json_f64(summ.max),
+            json_f64(summ.mean),
+            json_f64(summ.median_abs_dev),
+            bs.mb_s)
+}
+
+// Format a duration as fractional seconds
+fn fmt_secs(dur: Duration) -> String {
+    format!("{}.{:09}", dur.as_secs(), dur.subsec_nanos())
+}
+
+// Escape a string for use in XML text or attribute values. Control
+// characters which XML 1.0 cannot represent are replaced.
+fn xml_escape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&apos;"),
+            '\t' | '\n' | '\r' => output.push(c),
+            c if (c as u32) < 0x20 => output.push('\u{fffd}'),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+// Format a string as a quoted JSON string, escaping as required
+fn json_str(s: &str) -> String {
+    let mut output = String::with_capacity(s.len() + 2);
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+// JSON has no representation for NaN or infinities, so those become null
+fn json_f64(n: f64) -> String {
+    if n.is_finite() {
+        format!("{}", n)
+    } else {
+        "null".to_owned()
+    }
+}
+
 // List the tests to console, and optionally to logfile. Filters are honored.
 pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Result<()> {
     let mut st = ConsoleTestState::new(opts, None::<io::Stdout>)?;
@@ -781,7 +1470,10 @@ pub fn list_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Res
         let TestDescAndFn { desc: TestDesc { name, .. }, testfn } = test;
 
         let fntype = match testfn {
-            StaticTestFn(..) | DynTestFn(..) => { ntest += 1; "test" },
+            StaticTestFn(..) | DynTestFn(..) | DynRetryableTestFn(..) => {
+                ntest += 1;
+                "test"
+            }
             StaticBenchFn(..) | DynBenchFn(..) => { nbench += 1; "benchmark" },
             StaticMetricFn(..) | DynMetricFn(..) => { nmetric += 1; "metric" },
         };
@@ -818,11 +1510,22 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
             TeFiltered(ref filtered_tests) => st.write_run_start(filtered_tests.len()),
             TeWait(ref test, padding) => st.write_test_start(test, padding),
             TeTimeout(ref test) => st.write_timeout(test),
-            TeResult(test, result, stdout) => {
+            TeStillRunning(ref tests) => st.write_still_running(tests),
+            TeSuiteFixtureFailed(ref msg) => st.write_suite_fixture_failed(msg),
+            TeRetry(ref test, ref result, ref stdout, exec_time) => {
+                st.write_retry(test, result, stdout, exec_time)
+            }
+            TeResult(test, result, stdout, exec_time) => {
                 st.write_log_result(&test, &result)?;
-                st.write_result(&result)?;
+                st.record_junit_result(&test, &result, &stdout, exec_time);
+                st.write_result(&test, &result, &stdout, exec_time)?;
                 match result {
-                    TrOk => st.passed += 1,
+                    TrOk => {
+                        if st.retried.contains_key(&test.name) {
+                            st.flaky.push(test.name.to_string());
+                        }
+                        st.passed += 1
+                    }
                     TrIgnored => st.ignored += 1,
                     TrMetrics(mm) => {
                         let tname = test.name;
@@ -851,6 +1554,20 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
                         );
                         st.failures.push((test, stdout));
                     }
+                    TrTimedOut => {
+                        st.failed += 1;
+                        let mut stdout = stdout;
+                        stdout.extend_from_slice(
+                            b"note: test did not finish within its time limit"
+                        );
+                        st.failures.push((test, stdout));
+                    }
+                    TrFixtureFailed(msg) => {
+                        st.fixture_errors += 1;
+                        let mut stdout = stdout;
+                        stdout.extend_from_slice(format!("note: {}", msg).as_bytes());
+                        st.fixture_failures.push((test.name.to_string(), stdout));
+                    }
                 }
                 Ok(())
             }
@@ -868,7 +1585,28 @@ pub fn run_tests_console(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> io::Resu
         let n = t.desc.name.as_slice();
         st.max_name_len = n.len();
     }
-    run_tests(opts, tests, |x| callback(&x, &mut st))?;
+    let mut bench_results = Baseline::new();
+    run_tests(opts, tests, |x| {
+        if let TeResult(ref test, TrBench(ref bs), _, _) = x {
+            bench_results.insert(test.name.as_slice(), BaselineSample::from(&bs.ns_iter_summ));
+        }
+        callback(&x, &mut st)
+    })?;
+
+    if let Some(ref name) = opts.baseline {
+        let baseline = Baseline::load(name)?;
+        st.write_baseline_comparison(name,
+                                     &baseline,
+                                     &bench_results,
+                                     opts.regression_threshold)?;
+    }
+    // A run without benchmarks, e.g. one without `--bench`, leaves the saved
+    // baseline alone.
+    if let Some(ref name) = opts.save_baseline {
+        if !bench_results.is_empty() {
+            bench_results.save(name)?;
+        }
+    }
     return st.write_run_finish();
 }
 
@@ -878,27 +1616,38 @@ fn should_sort_failures_before_printing_them() {
         name: StaticTestName("a"),
         ignore: false,
         should_panic: ShouldPanic::No,
+        timeout: None,
     };
 
     let test_b = TestDesc {
         name: StaticTestName("b"),
         ignore: false,
         should_panic: ShouldPanic::No,
+        timeout: None,
     };
 
     let mut st = ConsoleTestState {
         log_out: None,
+        junit_out: None,
         out: Raw(Vec::new()),
         use_color: false,
+        format: OutputFormat::Pretty,
         quiet: false,
         total: 0,
         passed: 0,
         failed: 0,
         ignored: 0,
         measured: 0,
+        fixture_errors: 0,
         max_name_len: 10,
+        shuffle_seed: None,
         metrics: MetricMap::new(),
         failures: vec![(test_b, Vec::new()), (test_a, Vec::new())],
+        fixture_failures: Vec::new(),
+        retried: HashMap::new(),
+        flaky: Vec::new(),
+        regressions: Vec::new(),
+        junit_results: Vec::new(),
     };
 
     st.write_failures().unwrap();
@@ -947,27 +1696,56 @@ fn stdout_isatty() -> bool {
     }
 }
 
+/// The progress of a test run, as reported by `run_tests`.
 #[derive(Clone)]
-enum TestEvent {
+pub enum TestEvent {
+    /// The tests which are going to be run, after filtering.
     TeFiltered(Vec<TestDesc>),
+    /// A test is about to start, or with multiple test threads, has just
+    /// finished and its result follows.
     TeWait(TestDesc, NamePadding),
-    TeResult(TestDesc, TestResult, Vec<u8>),
+    /// The result of a test, its captured output and how long it took.
+    TeResult(TestDesc, TestResult, Vec<u8>, Duration),
+    /// A test has been running for over a minute.
     TeTimeout(TestDesc),
+    /// Tests which exceeded their time limit and were still running when
+    /// the run finished.
+    TeStillRunning(Vec<TestDesc>),
+    /// A failed attempt at a test which is going to be run again.
+    TeRetry(TestDesc, TestResult, Vec<u8>, Duration),
+    /// The teardown of a suite fixture failed after all tests had run.
+    /// Tests which could not run because of a failed setup are reported
+    /// individually, with `TrFixtureFailed`.
+    TeSuiteFixtureFailed(String),
 }
 
 pub type MonitorMsg = (TestDesc, TestResult, Vec<u8>);
 
-
-fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F) -> io::Result<()>
+/// Runs `tests` according to `opts`, passing each `TestEvent` to `callback`
+/// as the run progresses.
+///
+/// This is the runner behind `test_main`, for harnesses which want to
+/// report results themselves: nothing is printed and the process is never
+/// exited, except in a process spawned for a single test by `isolate`,
+/// which runs that test and exits. An error returned by `callback` stops
+/// the run and is returned.
+pub fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F) -> io::Result<()>
     where F: FnMut(TestEvent) -> io::Result<()>
 {
-    use std::collections::HashMap;
+    use std::collections::HashSet;
     use std::sync::mpsc::RecvTimeoutError;
 
+    if let Ok(name) = env::var(ISOLATED_TEST_VAR) {
+        run_isolated_test(opts, &name, tests);
+    }
+
     let mut filtered_tests = filter_tests(opts, tests);
     if !opts.bench_benchmarks {
         filtered_tests = convert_benchmarks_to_tests(filtered_tests);
     }
+    if let Some(seed) = opts.shuffle_seed {
+        shuffle_tests(seed, &mut filtered_tests);
+    }
 
     let filtered_descs = filtered_tests.iter()
                                        .map(|t| t.desc.clone())
@@ -978,10 +1756,42 @@ fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F) ->
     let (filtered_tests, filtered_benchs_and_metrics): (Vec<_>, _) =
         filtered_tests.into_iter().partition(|e| {
             match e.testfn {
-                StaticTestFn(_) | DynTestFn(_) => true,
+                StaticTestFn(_) | DynTestFn(_) | DynRetryableTestFn(_) => true,
                 _ => false,
             }
         });
+    let filtered_benchs_and_metrics = if opts.bench_benchmarks {
+        filtered_benchs_and_metrics
+    } else {
+        Vec::new()
+    };
+
+    // With `--isolate` each test process sets up the suite fixtures itself
+    let no_tests = filtered_tests.is_empty() && filtered_benchs_and_metrics.is_empty();
+    let suite_fixtures: &[TestFixture] = if opts.isolate || no_tests {
+        &[]
+    } else {
+        &opts.fixtures.suite[..]
+    };
+    let (suite_values, setup_failure) = fixture::set_up(suite_fixtures);
+    if let Some(msg) = setup_failure {
+        // None of the tests can run without the suite fixtures
+        for test in filtered_tests.into_iter().chain(filtered_benchs_and_metrics) {
+            callback(TeWait(test.desc.clone(), test.testfn.padding()))?;
+            let result = match test.testfn {
+                StaticTestFn(_) | DynTestFn(_) | DynRetryableTestFn(_) if !opts.run_tests => {
+                    TrIgnored
+                }
+                _ if test.desc.ignore => TrIgnored,
+                _ => TrFixtureFailed(msg.clone()),
+            };
+            callback(TeResult(test.desc, result, Vec::new(), Duration::new(0, 0)))?;
+        }
+        if let Some(msg) = fixture::tear_down(suite_fixtures, suite_values) {
+            callback(TeSuiteFixtureFailed(msg))?;
+        }
+        return Ok(());
+    }
 
     let concurrency = match opts.test_threads {
         Some(n) => n,
@@ -995,6 +1805,19 @@ fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F) ->
     let (tx, rx) = channel::<MonitorMsg>();
 
     let mut running_tests: HashMap<TestDesc, Instant> = HashMap::new();
+    let mut start_times: HashMap<TestDesc, Instant> = HashMap::new();
+    // Tests which have a time limit, and tests which have exceeded it but
+    // whose threads have not yet finished.
+    let mut deadlines: HashMap<TestDesc, Instant> = HashMap::new();
+    let mut hung_tests: HashSet<TestDesc> = HashSet::new();
+    // Tests whose processes were killed for exceeding their time limit, and
+    // the processes of the tests run with `--isolate`, which are killed if
+    // the run stops before they exit.
+    let mut killed_tests: HashSet<TestDesc> = HashSet::new();
+    let isolated_children = KillChildrenOnDrop(Arc::new(Mutex::new(HashMap::new())));
+    // Tests which can be run again after failing, and how often they have been
+    let mut retry_fns: HashMap<TestDesc, TestFn> = HashMap::new();
+    let mut attempts: HashMap<TestDesc, usize> = HashMap::new();
 
     fn get_timed_out_tests(running_tests: &mut HashMap<TestDesc, Instant>) -> Vec<TestDesc> {
         let now = Instant::now();
@@ -1007,8 +1830,9 @@ fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F) ->
         timed_out
     };
 
-    fn calc_timeout(running_tests: &HashMap<TestDesc, Instant>) -> Option<Duration> {
-        running_tests.values().min().map(|next_timeout| {
+    fn calc_timeout(running_tests: &HashMap<TestDesc, Instant>,
+                    deadlines: &HashMap<TestDesc, Instant>) -> Option<Duration> {
+        running_tests.values().chain(deadlines.values()).min().map(|next_timeout| {
             let now = Instant::now();
             if *next_timeout >= now {
                 *next_timeout - now
@@ -1018,7 +1842,10 @@ fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F) ->
     };
 
     while pending > 0 || !remaining.is_empty() {
-        while pending < concurrency && !remaining.is_empty() {
+        // Tests over their time limit keep their threads until they finish,
+        // but one test is always allowed to run so that the run does too.
+        while (pending + hung_tests.len() < concurrency || pending == 0) &&
+              !remaining.is_empty() {
             let test = remaining.pop().unwrap();
             if concurrency == 1 {
                 // We are doing one test at a time so we can print the name
@@ -1028,18 +1855,51 @@ fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F) ->
             }
             let timeout = Instant::now() + Duration::from_secs(TEST_WARN_TIMEOUT_S);
             running_tests.insert(test.desc.clone(), timeout);
-            run_test(opts, !opts.run_tests, test, tx.clone());
+            start_times.insert(test.desc.clone(), Instant::now());
+            if opts.retries > 0 {
+                if let Some(testfn) = test.testfn.try_clone() {
+                    retry_fns.insert(test.desc.clone(), testfn);
+                }
+            }
+            if let Some(secs) = test.desc.timeout.or(opts.test_timeout) {
+                let deadline = Instant::now() + Duration::from_secs(secs);
+                deadlines.insert(test.desc.clone(), deadline);
+            }
+            run_test_in_suite(opts, !opts.run_tests, test, tx.clone(), &suite_values,
+                              &isolated_children.0);
             pending += 1;
         }
 
         let mut res;
         loop {
-            if let Some(timeout) = calc_timeout(&running_tests) {
+            if let Some(timeout) = calc_timeout(&running_tests, &deadlines) {
                 res = rx.recv_timeout(timeout);
                 for test in get_timed_out_tests(&mut running_tests) {
                     callback(TeTimeout(test))?;
                 }
-                if res != Err(RecvTimeoutError::Timeout) {
+                // Tests over their time limit are reported as failed right
+                // away, and anything they send later is discarded. The
+                // process of an isolated test is killed, but threads cannot
+                // be, so the other tests are left running.
+                let timed_out = get_timed_out_tests(&mut deadlines);
+                let any_timed_out = !timed_out.is_empty();
+                for test in timed_out {
+                    running_tests.remove(&test);
+                    let exec_time = start_times.remove(&test)
+                                               .map(|start| start.elapsed())
+                                               .unwrap_or(Duration::new(0, 0));
+                    if kill_isolated_test(&isolated_children.0, &test) {
+                        killed_tests.insert(test.clone());
+                    } else {
+                        hung_tests.insert(test.clone());
+                    }
+                    if concurrency != 1 {
+                        callback(TeWait(test.clone(), PadNone))?;
+                    }
+                    callback(TeResult(test, TrTimedOut, Vec::new(), exec_time))?;
+                    pending -= 1;
+                }
+                if res != Err(RecvTimeoutError::Timeout) || any_timed_out {
                     break;
                 }
             } else {
@@ -1048,25 +1908,80 @@ fn run_tests<F>(opts: &TestOpts, tests: Vec<TestDescAndFn>, mut callback: F) ->
             }
         }
 
-        let (desc, result, stdout) = res.unwrap();
+        let (desc, result, mut stdout) = match res {
+            // Slots were freed up by tests which hit their time limit
+            Err(RecvTimeoutError::Timeout) => continue,
+            res => res.unwrap(),
+        };
+        if hung_tests.remove(&desc) || killed_tests.remove(&desc) {
+            // This test was already reported as timed out
+            continue;
+        }
         running_tests.remove(&desc);
+        deadlines.remove(&desc);
+        let retry_fn = retry_fns.remove(&desc);
+        let exec_time = start_times.remove(&desc)
+                                   .map(|start| start.elapsed())
+                                   .unwrap_or(Duration::new(0, 0));
+
+        let failed = match result {
+            TrFailed | TrFailedMsg(_) => true,
+            _ => false,
+        };
+        if failed && opts.retries > 0 && retry_fn.is_none() {
+            stdout.extend_from_slice(b"note: this test was not retried, as its function \
+                                       (a `DynTestFn`) can only be run once\n");
+        }
+        if failed {
+            let attempt = attempts.entry(desc.clone()).or_insert(0);
+            if let Some(testfn) = retry_fn {
+                if *attempt < opts.retries {
+                    *attempt += 1;
+                    if concurrency != 1 {
+                        callback(TeWait(desc.clone(), PadNone))?;
+                    }
+                    callback(TeRetry(desc.clone(), result, stdout, exec_time))?;
+                    remaining.push(TestDescAndFn {
+                        desc: desc,
+                        testfn: testfn,
+                    });
+                    pending -= 1;
+                    continue;
+                }
+            }
+        }
 
         if concurrency != 1 {
             callback(TeWait(desc.clone(), PadNone))?;
         }
-        callback(TeResult(desc, result, stdout))?;
+        callback(TeResult(desc, result, stdout, exec_time))?;
         pending -= 1;
     }
 
-    if opts.bench_benchmarks {
-        // All benchmarks run at the end, in serial.
-        // (this includes metric fns)
-        for b in filtered_benchs_and_metrics {
-            callback(TeWait(b.desc.clone(), b.testfn.padding()))?;
-            run_test(opts, false, b, tx.clone());
-            let (test, result, stdout) = rx.recv().unwrap();
-            callback(TeResult(test, result, stdout))?;
+    // All benchmarks run at the end, in serial.
+    // (this includes metric fns)
+    for b in filtered_benchs_and_metrics {
+        callback(TeWait(b.desc.clone(), b.testfn.padding()))?;
+        let start = Instant::now();
+        run_test_in_suite(opts, false, b, tx.clone(), &suite_values, &isolated_children.0);
+        let mut msg = rx.recv().unwrap();
+        while hung_tests.remove(&msg.0) || killed_tests.remove(&msg.0) {
+            msg = rx.recv().unwrap();
         }
+        let (test, result, stdout) = msg;
+        callback(TeResult(test, result, stdout, start.elapsed()))?;
+    }
+
+    // Tests which are still running past their time limit may yet use the
+    // suite fixtures, but there is no telling when they will finish.
+    if let Some(msg) = fixture::tear_down(suite_fixtures, suite_values) {
+        callback(TeSuiteFixtureFailed(msg))?;
+    }
+
+    if !hung_tests.is_empty() {
+        let mut still_running: Vec<_> = hung_tests.into_iter().collect();
+        still_running.sort_by(|t1, t2| t1.name.as_slice().cmp(t2.name.as_slice()));
+        callback(TeStillRunning(still_running))?;
     }
     Ok(())
 }
@@ -1241,9 +2156,34 @@ pub fn filter_tests(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> Vec<TestDescA
     // Sort the tests alphabetically
     filtered.sort_by(|t1, t2| t1.desc.name.as_slice().cmp(t2.desc.name.as_slice()));
 
+    // Keep only the tests in our shard. Tests are assigned by a hash of their
+    // name, so adding a test does not move the others between shards.
+    if let Some((index, total)) = opts.shard {
+        filtered.retain(|t| shard_of(t.desc.name.as_slice(), total) == index - 1);
+    }
+
     filtered
 }
 
+// 64-bit FNV-1a, which unlike the std hashers is guaranteed to be stable
+fn shard_of(name: &str, total: usize) -> usize {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in name.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (hash % total as u64) as usize
+}
+
+// Fisher-Yates shuffle driven by `seed`
+pub fn shuffle_tests(seed: u64, tests: &mut [TestDescAndFn]) {
+    let mut rng = ShuffleRng(seed);
+    for i in (1..tests.len()).rev() {
+        let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+        tests.swap(i, j);
+    }
+}
+
 pub fn convert_benchmarks_to_tests(tests: Vec<TestDescAndFn>) -> Vec<TestDescAndFn> {
     // convert benchmarks to tests, if we're not benchmarking them
     tests.into_iter().map(|x| {
@@ -1254,7 +2194,7 @@ pub fn convert_benchmarks_to_tests(tests: Vec<TestDescAndFn>) -> Vec<TestDescAnd
                 }))
             }
             StaticBenchFn(benchfn) => {
-                DynTestFn(Box::new(move |()| {
+                DynRetryableTestFn(Arc::new(move || {
                     bench::run_once(|b| benchfn(b))
                 }))
             }
@@ -1271,6 +2211,35 @@ pub fn run_test(opts: &TestOpts,
                 force_ignore: bool,
                 test: TestDescAndFn,
                 monitor_ch: Sender<MonitorMsg>) {
+    let children = Arc::new(Mutex::new(HashMap::new()));
+    run_test_in_suite(opts, force_ignore, test, monitor_ch, &Vec::new(), &children)
+}
+
+// Runs a test with the values of the suite fixtures which are set up. With
+// `--isolate`, the process of the test is added to `children`.
+fn run_test_in_suite(opts: &TestOpts,
+                     force_ignore: bool,
+                     test: TestDescAndFn,
+                     monitor_ch: Sender<MonitorMsg>,
+                     suite_values: &FixtureValues,
+                     children: &IsolatedChildren) {
+    if opts.isolate && !force_ignore && !test.desc.ignore {
+        match test.testfn {
+            StaticTestFn(..) | DynTestFn(..) | DynRetryableTestFn(..) => {
+                return run_test_isolated(test.desc, monitor_ch, opts.nocapture, children);
+            }
+            _ => {}
+        }
+    }
+    run_test_in_process(opts, force_ignore, test, monitor_ch, suite_values)
+}
+
+// Like `run_test_in_suite`, but never in a process of its own.
+fn run_test_in_process(opts: &TestOpts,
+                       force_ignore: bool,
+                       test: TestDescAndFn,
+                       monitor_ch: Sender<MonitorMsg>,
+                       suite_values: &FixtureValues) {
 
     let TestDescAndFn {desc, testfn} = test;
 
@@ -1282,6 +2251,8 @@ pub fn run_test(opts: &TestOpts,
     fn run_test_inner(desc: TestDesc,
                       monitor_ch: Sender<MonitorMsg>,
                       nocapture: bool,
+                      fixtures: Vec<TestFixture>,
+                      suite_values: FixtureValues,
                       testfn: Box<FnBox<()>>) {
         struct Sink(Arc<Mutex<Vec<u8>>>);
         impl Write for Sink {
@@ -1308,18 +2279,39 @@ pub fn run_test(opts: &TestOpts,
                 None
             };
 
-            let result = catch_unwind(AssertUnwindSafe(|| {
-                testfn.call_box(())
-            }));
+            // The test only runs if all of its fixtures could be set up,
+            // and those which were are torn down whatever the outcome.
+            let (values, setup_failure) = fixture::set_up(&fixtures);
+            fixture::set_current(suite_values.into_iter().chain(values.iter().cloned()).collect());
+            let result = match setup_failure {
+                Some(msg) => Err(msg),
+                None => Ok(catch_unwind(AssertUnwindSafe(|| {
+                    testfn.call_box(())
+                }))),
+            };
+            let teardown_failure = fixture::tear_down(&fixtures, values);
+            fixture::clear_current();
 
             if let Some((printio, panicio)) = oldio {
                 io::set_print(printio);
                 io::set_panic(panicio);
             };
 
-            let test_result = calc_result(&desc, result);
+            // A failure of the test itself takes precedence over one of
+            // its teardown.
+            let test_result = match result {
+                Err(msg) => TrFixtureFailed(msg),
+                Ok(result) => {
+                    match (calc_result(&desc, result), teardown_failure) {
+                        (TrOk, Some(msg)) => TrFixtureFailed(msg),
+                        (test_result, _) => test_result,
+                    }
+                }
+            };
             let stdout = data.lock().unwrap().to_vec();
-            monitor_ch.send((desc.clone(), test_result, stdout)).unwrap();
+            // Nobody is listening any more if the test exceeded its time
+            // limit and the run has finished without it.
+            let _ = monitor_ch.send((desc.clone(), test_result, stdout));
         };
 
 
@@ -1361,22 +2353,281 @@ pub fn run_test(opts: &TestOpts,
             monitor_ch.send((desc, TrMetrics(mm), Vec::new())).unwrap();
             return;
         }
-        DynTestFn(f) => run_test_inner(desc, monitor_ch, opts.nocapture, f),
-        StaticTestFn(f) => run_test_inner(desc, monitor_ch, opts.nocapture,
-                                          Box::new(move |()| f())),
+        DynTestFn(f) => {
+            run_test_inner(desc,
+                           monitor_ch,
+                           opts.nocapture,
+                           opts.fixtures.each_test.clone(),
+                           suite_values.clone(),
+                           f)
+        }
+        DynRetryableTestFn(f) => {
+            run_test_inner(desc,
+                           monitor_ch,
+                           opts.nocapture,
+                           opts.fixtures.each_test.clone(),
+                           suite_values.clone(),
+                           Box::new(move |()| f()))
+        }
+        StaticTestFn(f) => {
+            run_test_inner(desc,
+                           monitor_ch,
+                           opts.nocapture,
+                           opts.fixtures.each_test.clone(),
+                           suite_values.clone(),
+                           Box::new(move |()| f()))
+        }
+    }
+}
+
+// Runs a single test in a process spawned by `--isolate`, reporting its
+// result and captured output on stdout with `write_isolated_result`.
+fn run_isolated_test(opts: &TestOpts, name: &str, tests: Vec<TestDescAndFn>) -> ! {
+    // Test binaries run by the test are not spawned for a test
+    env::remove_var(ISOLATED_TEST_VAR);
+    let test = match tests.into_iter().find(|t| t.desc.name.as_slice() == name) {
+        Some(test) => test,
+        None => {
+            let _ = writeln!(io::stderr(), "error: no test named `{}`", name);
+            process::exit(101);
+        }
+    };
+    // The parent has already dealt with `#[ignore]` and benchmarks
+    let TestDescAndFn { desc, testfn } = convert_benchmarks_to_tests(vec![test]).pop().unwrap();
+    let test = TestDescAndFn {
+        desc: TestDesc { ignore: false, ..desc },
+        testfn: testfn,
+    };
+
+    let (suite_values, setup_failure) = fixture::set_up(&opts.fixtures.suite);
+    let (result, output) = match setup_failure {
+        Some(msg) => (TrFixtureFailed(msg), Vec::new()),
+        None => {
+            let (tx, rx) = channel();
+            run_test_in_process(opts, false, test, tx, &suite_values);
+            let (_, result, output) = rx.recv().unwrap();
+            (result, output)
+        }
+    };
+    let result = match (result, fixture::tear_down(&opts.fixtures.suite, suite_values)) {
+        (TrOk, Some(msg)) => TrFixtureFailed(msg),
+        (result, _) => result,
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    match write_isolated_result(&mut stdout, &result, &output) {
+        Ok(()) => process::exit(0),
+        Err(_) => process::exit(101),
+    }
+}
+
+fn write_isolated_result<W: Write>(out: &mut W, result: &TestResult, output: &[u8])
+                                   -> io::Result<()> {
+    let (kind, msg) = match *result {
+        TrOk => ("ok", ""),
+        TrFailedMsg(ref msg) => ("failed", &msg[..]),
+        TrFixtureFailed(ref msg) => ("fixture_failed", &msg[..]),
+        _ => ("failed", ""),
+    };
+    out.write_all(ISOLATED_RESULT_MARKER)?;
+    write!(out, "{} {}\n", kind, msg.len())?;
+    out.write_all(msg.as_bytes())?;
+    out.write_all(output)?;
+    out.flush()
+}
+
+// Reads the result written by `write_isolated_result` at the end of the
+// stdout of a test process, returning it, the captured output of the test
+// and what the process wrote to stdout before.
+fn read_isolated_result(stdout: &[u8]) -> Option<(TestResult, &[u8], &[u8])> {
+    let marker = ISOLATED_RESULT_MARKER;
+    let start = match (0..stdout.len() + 1).rev().find(|&i| stdout[i..].starts_with(marker)) {
+        Some(start) => start,
+        None => return None,
+    };
+    let record = &stdout[start + marker.len()..];
+    let header_len = match record.iter().position(|&b| b == b'\n') {
+        Some(len) => len,
+        None => return None,
+    };
+    let header = str::from_utf8(&record[..header_len]).unwrap_or("");
+    let rest = &record[header_len + 1..];
+    let mut parts = header.splitn(2, ' ');
+    let kind = parts.next();
+    let msg_len = match parts.next().and_then(|n| n.parse::<usize>().ok()) {
+        Some(len) if len <= rest.len() => len,
+        _ => return None,
+    };
+    let msg = String::from_utf8_lossy(&rest[..msg_len]).into_owned();
+    let result = match kind {
+        Some("ok") => TrOk,
+        Some("failed") if msg.is_empty() => TrFailed,
+        Some("failed") => TrFailedMsg(msg),
+        Some("fixture_failed") => TrFixtureFailed(msg),
+        _ => return None,
+    };
+    Some((result, &rest[msg_len..], &stdout[..start]))
+}
+
+// The processes of the tests run with `--isolate` whose output is still
+// being read, by test.
+type IsolatedChildren = Arc<Mutex<HashMap<TestDesc, Child>>>;
+
+// Kills the test processes which are still running when it is dropped, so
+// that none outlive the run.
+struct KillChildrenOnDrop(IsolatedChildren);
+
+impl Drop for KillChildrenOnDrop {
+    fn drop(&mut self) {
+        for (_, mut child) in self.0.lock().unwrap().drain() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
     }
 }
 
+// Kills the process of the isolated test `desc`, returning whether it was
+// still running.
+fn kill_isolated_test(children: &IsolatedChildren, desc: &TestDesc) -> bool {
+    match children.lock().unwrap().remove(desc) {
+        Some(mut child) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            true
+        }
+        None => false,
+    }
+}
+
+// Runs a test by re-executing the current binary with `ISOLATED_TEST_VAR`
+// set. The process is added to `children`, and a separate thread reads its
+// output and reports the result.
+fn run_test_isolated(desc: TestDesc,
+                     monitor_ch: Sender<MonitorMsg>,
+                     nocapture: bool,
+                     children: &IsolatedChildren) {
+    let mut child = match spawn_isolated_test(&desc, nocapture) {
+        Ok(child) => child,
+        Err(e) => {
+            let msg = format!("failed to spawn test process: {}", e);
+            let _ = monitor_ch.send((desc, TrFailedMsg(msg), Vec::new()));
+            return;
+        }
+    };
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    children.lock().unwrap().insert(desc.clone(), child);
+
+    let children = children.clone();
+    let name = desc.name.clone();
+    let runtest = move || {
+        let res = wait_for_isolated_test(&desc, stdout, stderr, nocapture, &children);
+        let (result, output) = match res {
+            Ok(res) => res,
+            Err(e) => {
+                (TrFailedMsg(format!("failed to read the output of the test process: {}", e)),
+                 Vec::new())
+            }
+        };
+        let _ = monitor_ch.send((desc.clone(), result, output));
+    };
+    let cfg = thread::Builder::new().name(match name {
+        DynTestName(ref name) => name.clone(),
+        StaticTestName(name) => name.to_owned(),
+    });
+    cfg.spawn(runtest).unwrap();
+}
+
+fn spawn_isolated_test(desc: &TestDesc, nocapture: bool) -> io::Result<Child> {
+    let mut cmd = Command::new(env::current_exe()?);
+    cmd.env(ISOLATED_TEST_VAR, desc.name.as_slice())
+       .stdin(Stdio::null())
+       .stdout(Stdio::piped());
+    if nocapture {
+        // Only stdout is needed for the result
+        cmd.env("RUST_TEST_NOCAPTURE", "1").stderr(Stdio::inherit());
+    } else {
+        cmd.env_remove("RUST_TEST_NOCAPTURE").stderr(Stdio::piped());
+    }
+    cmd.spawn()
+}
+
+// Reads the output of the process of an isolated test until it exits, and
+// returns the result which the test reported.
+fn wait_for_isolated_test(desc: &TestDesc,
+                          stdout: Option<ChildStdout>,
+                          stderr: Option<ChildStderr>,
+                          nocapture: bool,
+                          children: &IsolatedChildren)
+                          -> io::Result<(TestResult, Vec<u8>)> {
+    // Both pipes are read at once, so that the process never blocks on a
+    // full one.
+    let stderr_reader = stderr.map(|mut stderr| {
+        thread::spawn(move || {
+            let mut output = Vec::new();
+            stderr.read_to_end(&mut output).map(|_| output)
+        })
+    });
+    let mut process_stdout = Vec::new();
+    if let Some(mut stdout) = stdout {
+        stdout.read_to_end(&mut process_stdout)?;
+    }
+    let process_stderr = match stderr_reader {
+        Some(reader) => reader.join().unwrap()?,
+        None => Vec::new(),
+    };
+
+    // The process is no longer there if it was killed for exceeding its
+    // time limit.
+    let child = children.lock().unwrap().remove(desc);
+    let status = match child {
+        Some(mut child) => child.wait()?,
+        None => {
+            let msg = "test process was killed after exceeding its time limit".to_owned();
+            return Ok((TrFailedMsg(msg), Vec::new()));
+        }
+    };
+
+    // A test which exits the process or crashes never gets to report a
+    // result, whatever the exit status.
+    let (result, captured, stdout) = match (read_isolated_result(&process_stdout),
+                                            status.success()) {
+        (Some(res), true) => res,
+        _ => {
+            let msg = match status.code() {
+                Some(code) => {
+                    format!("test process exited with status code {} without reporting a \
+                             result",
+                            code)
+                }
+                None => format!("test process terminated abnormally ({})", status),
+            };
+            (TrFailedMsg(msg), &[][..], &process_stdout[..])
+        }
+    };
+
+    if nocapture {
+        // A harness which builds its own `TestOpts` may capture anyway
+        io::stdout().write_all(stdout)?;
+        io::stdout().write_all(captured)?;
+        return Ok((result, Vec::new()));
+    }
+    let mut out = stdout.to_vec();
+    out.extend_from_slice(captured);
+    if !process_stderr.is_empty() {
+        out.extend_from_slice(b"\n---- stderr of the test process ----\n");
+        out.extend_from_slice(&process_stderr);
+    }
+    Ok((result, out))
+}
+
 fn calc_result(desc: &TestDesc, task_result: Result<(), Box<Any + Send>>) -> TestResult {
     match (&desc.should_panic, task_result) {
         (&ShouldPanic::No, Ok(())) |
         (&ShouldPanic::Yes, Err(_)) => TrOk,
         (&ShouldPanic::YesWithMessage(msg), Err(ref err)) =>
-            if err.downcast_ref::<String>()
-                  .map(|e| &**e)
-                  .or_else(|| err.downcast_ref::<&'static str>().map(|e| *e))
-                  .map(|e| e.contains(msg))
-                  .unwrap_or(false) {
+            if panic_message(err).map(|e| e.contains(msg)).unwrap_or(false) {
                 TrOk
             } else {
                 TrFailedMsg(format!("Panic did not include expected string '{}'", msg))
@@ -1385,6 +2636,13 @@ fn calc_result(desc: &TestDesc, task_result: Result<(), Box<Any + Send>>) -> Tes
     }
 }
 
+// The message of a panic, if it was raised with one
+fn panic_message(err: &Box<Any + Send>) -> Option<&str> {
+    err.downcast_ref::<String>()
+       .map(|e| &**e)
+       .or_else(|| err.downcast_ref::<&'static str>().map(|e| *e))
+}
+
 impl MetricMap {
     pub fn new() -> MetricMap {
         MetricMap(BTreeMap::new())
@@ -1419,6 +2677,19 @@ impl MetricMap {
                                .collect();
         v.join(", ")
     }
+
+    pub fn fmt_metrics_json(&self) -> String {
+        let MetricMap(ref mm) = *self;
+        let v: Vec<String> = mm.iter()
+                               .map(|(k, v)| {
+                                   format!("{}: {{ \"value\": {}, \"noise\": {} }}",
+                                           json_str(k),
+                                           json_f64(v.value),
+                                           json_f64(v.noise))
+                               })
+                               .collect();
+        format!("{{ {} }}", v.join(", "))
+    }
 }
 
 
@@ -1598,11 +2869,17 @@ pub mod bench {
 #[cfg(test)]
 mod tests {
     use test::{TrFailed, TrFailedMsg, TrIgnored, TrOk, filter_tests, parse_opts, TestDesc,
-               TestDescAndFn, TestOpts, run_test, MetricMap, StaticTestName, DynTestName,
-               DynTestFn, ShouldPanic};
+               TestDescAndFn, TestOpts, TestResult, run_test, MetricMap, StaticTestName,
+               DynTestName, DynTestFn, ShouldPanic};
     use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
     use bench;
     use Bencher;
+    use {ConsoleTestState, OutputFormat, TrTimedOut, TrFixtureFailed, TestFixture, fixture,
+         json_str, run_tests, shuffle_tests, xml_escape};
+    use TestEvent::*;
+    use OutputLocation::Raw;
 
     #[test]
     pub fn do_not_run_ignored_tests() {
@@ -1614,6 +2891,7 @@ mod tests {
                 name: StaticTestName("whatever"),
                 ignore: true,
                 should_panic: ShouldPanic::No,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(move |()| f())),
         };
@@ -1631,6 +2909,7 @@ mod tests {
                 name: StaticTestName("whatever"),
                 ignore: true,
                 should_panic: ShouldPanic::No,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(move |()| f())),
         };
@@ -1650,6 +2929,7 @@ mod tests {
                 name: StaticTestName("whatever"),
                 ignore: false,
                 should_panic: ShouldPanic::Yes,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(move |()| f())),
         };
@@ -1669,6 +2949,7 @@ mod tests {
                 name: StaticTestName("whatever"),
                 ignore: false,
                 should_panic: ShouldPanic::YesWithMessage("error message"),
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(move |()| f())),
         };
@@ -1690,6 +2971,7 @@ mod tests {
                 name: StaticTestName("whatever"),
                 ignore: false,
                 should_panic: ShouldPanic::YesWithMessage(expected),
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(move |()| f())),
         };
@@ -1707,6 +2989,7 @@ mod tests {
                 name: StaticTestName("whatever"),
                 ignore: false,
                 should_panic: ShouldPanic::Yes,
+                timeout: None,
             },
             testfn: DynTestFn(Box::new(move |()| f())),
         };
@@ -1726,6 +3009,476 @@ mod tests {
         assert!((opts.run_ignored));
     }
 
+    #[test]
+    fn parse_format_flag() {
+        let args = vec!["progname".to_string(), "--format".to_string(), "json".to_string()];
+        let opts = match parse_opts(&args) {
+            Some(Ok(o)) => o,
+            _ => panic!("Malformed arg in parse_format_flag"),
+        };
+        assert_eq!(opts.format, OutputFormat::Json);
+
+        let args = vec!["progname".to_string(), "--format".to_string(), "xml".to_string()];
+        assert!(match parse_opts(&args) {
+            Some(Err(_)) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn json_str_escapes() {
+        assert_eq!(json_str("plain"), "\"plain\"");
+        assert_eq!(json_str("a \"b\" \\ c"), "\"a \\\"b\\\" \\\\ c\"");
+        assert_eq!(json_str("line\nnext\t\u{1}"), "\"line\\nnext\\t\\u0001\"");
+    }
+
+    #[test]
+    fn xml_escape_escapes() {
+        assert_eq!(xml_escape("<a href=\"x\">'&'</a>"),
+                   "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;");
+        assert_eq!(xml_escape("tab\tbell\u{7}"), "tab\tbell\u{fffd}");
+    }
+
+    #[test]
+    fn json_result_is_one_object_per_line() {
+        let desc = TestDesc {
+            name: StaticTestName("a::\"quoted\""),
+            ignore: false,
+            should_panic: ShouldPanic::No,
+            timeout: None,
+        };
+        let mut st = ConsoleTestState {
+            log_out: None,
+            junit_out: None,
+            out: Raw(Vec::new()),
+            use_color: false,
+            format: OutputFormat::Json,
+            quiet: false,
+            total: 0,
+            passed: 0,
+            failed: 0,
+            ignored: 0,
+            measured: 0,
+            fixture_errors: 0,
+            max_name_len: 0,
+            shuffle_seed: None,
+            metrics: MetricMap::new(),
+            failures: Vec::new(),
+            fixture_failures: Vec::new(),
+            retried: HashMap::new(),
+            flaky: Vec::new(),
+            regressions: Vec::new(),
+            junit_results: Vec::new(),
+        };
+
+        st.write_result(&desc,
+                        &TrFailedMsg("boom".to_string()),
+                        b"captured\n",
+                        Duration::new(1, 5))
+          .unwrap();
+        let s = match st.out {
+            Raw(ref m) => String::from_utf8_lossy(&m[..]).into_owned(),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(s,
+                   "{ \"type\": \"test\", \"event\": \"failed\", \
+                    \"name\": \"a::\\\"quoted\\\"\", \"message\": \"boom\", \
+                    \"exec_time\": 1.000000005, \"stdout\": \"captured\\n\" }\n");
+    }
+
+    #[test]
+    fn parse_test_timeout() {
+        let args = vec!["progname".to_string(), "--test-timeout".to_string(), "30".to_string()];
+        let opts = match parse_opts(&args) {
+            Some(Ok(o)) => o,
+            _ => panic!("Malformed arg in parse_test_timeout"),
+        };
+        assert_eq!(opts.test_timeout, Some(30));
+
+        let args = vec!["progname".to_string(), "--test-timeout".to_string(), "0".to_string()];
+        assert!(match parse_opts(&args) {
+            Some(Err(_)) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn hung_test_is_reported_as_timed_out() {
+        // The test hangs until `release` is dropped at the end
+        let (release, hang) = channel::<()>();
+        let desc = TestDescAndFn {
+            desc: TestDesc {
+                name: StaticTestName("hangs"),
+                ignore: false,
+                should_panic: ShouldPanic::No,
+                timeout: Some(1),
+            },
+            testfn: DynTestFn(Box::new(move |()| { let _ = hang.recv(); })),
+        };
+        let mut opts = TestOpts::new();
+        opts.run_tests = true;
+        opts.test_threads = Some(1);
+
+        let mut timed_out = false;
+        let mut still_running = Vec::new();
+        run_tests(&opts, vec![desc], |event| {
+            match event {
+                TeResult(_, TrTimedOut, _, _) => timed_out = true,
+                TeStillRunning(tests) => still_running = tests,
+                _ => {}
+            }
+            Ok(())
+        }).unwrap();
+        assert!(timed_out);
+        assert_eq!(still_running.len(), 1);
+        assert_eq!(still_running[0].name.to_string(), "hangs");
+        drop(release);
+    }
+
+    #[test]
+    fn hung_tests_count_toward_test_threads() {
+        use std::sync::{Arc, Mutex};
+
+        // The number of tests running, and the most there have been at once
+        let running = Arc::new(Mutex::new((0, 0)));
+        fn start(running: &Mutex<(usize, usize)>) {
+            let mut running = running.lock().unwrap();
+            running.0 += 1;
+            running.1 = ::std::cmp::max(running.0, running.1);
+        }
+
+        let (release, hang) = channel::<()>();
+        let hung = running.clone();
+        let mut tests = vec![TestDescAndFn {
+            desc: TestDesc {
+                name: StaticTestName("hangs"),
+                ignore: false,
+                should_panic: ShouldPanic::No,
+                timeout: Some(1),
+            },
+            testfn: DynTestFn(Box::new(move |()| {
+                start(&hung);
+                let _ = hang.recv();
+                hung.lock().unwrap().0 -= 1;
+            })),
+        }];
+        for i in 0..6 {
+            let running = running.clone();
+            tests.push(TestDescAndFn {
+                desc: TestDesc {
+                    name: DynTestName(format!("sleeps_{}", i)),
+                    ignore: false,
+                    should_panic: ShouldPanic::No,
+                    timeout: None,
+                },
+                testfn: DynTestFn(Box::new(move |()| {
+                    start(&running);
+                    thread::sleep(Duration::from_millis(300));
+                    running.lock().unwrap().0 -= 1;
+                })),
+            });
+        }
+        let mut opts = TestOpts::new();
+        opts.test_threads = Some(2);
+
+        run_tests(&opts, tests, |_| Ok(())).unwrap();
+        assert_eq!(running.lock().unwrap().1, 2);
+        drop(release);
+    }
+
+    #[test]
+    fn parse_isolate_flag() {
+        let args = vec!["progname".to_string(), "--isolate".to_string()];
+        let opts = match parse_opts(&args) {
+            Some(Ok(o)) => o,
+            _ => panic!("Malformed arg in parse_isolate_flag"),
+        };
+        assert!(opts.isolate);
+    }
+
+    #[test]
+    fn isolated_test_result_round_trip() {
+        use {read_isolated_result, write_isolated_result};
+
+        let mut stdout = b"printed by a subprocess\n".to_vec();
+        write_isolated_result(&mut stdout, &TrFailedMsg("no\nway".to_string()), b"captured")
+            .unwrap();
+        match read_isolated_result(&stdout) {
+            Some((TrFailedMsg(msg), captured, before)) => {
+                assert_eq!(msg, "no\nway");
+                assert_eq!(captured, b"captured");
+                assert_eq!(before, b"printed by a subprocess\n");
+            }
+            _ => panic!("the result should be read back"),
+        }
+
+        let mut stdout = Vec::new();
+        write_isolated_result(&mut stdout, &TrOk, b"").unwrap();
+        assert!(read_isolated_result(&stdout).unwrap().0 == TrOk);
+
+        // e.g. the test called `process::exit`
+        assert!(read_isolated_result(b"partial output").is_none());
+    }
+
+    #[test]
+    fn parse_shard_and_shuffle() {
+        let args = vec!["progname".to_string(), "--shard".to_string(), "2/3".to_string(),
+                        "--shuffle-seed".to_string(), "42".to_string()];
+        let opts = match parse_opts(&args) {
+            Some(Ok(o)) => o,
+            _ => panic!("Malformed arg in parse_shard_and_shuffle"),
+        };
+        assert_eq!(opts.shard, Some((2, 3)));
+        assert_eq!(opts.shuffle_seed, Some(42));
+
+        let args = vec!["progname".to_string(), "--shuffle".to_string()];
+        let opts = match parse_opts(&args) {
+            Some(Ok(o)) => o,
+            _ => panic!("Malformed arg in parse_shard_and_shuffle"),
+        };
+        assert!(opts.shuffle_seed.is_some());
+
+        for bad in &["0/3", "4/3", "1", "a/b"] {
+            let args = vec!["progname".to_string(), "--shard".to_string(), bad.to_string()];
+            assert!(match parse_opts(&args) {
+                Some(Err(_)) => true,
+                _ => false,
+            });
+        }
+    }
+
+    fn named_tests(names: &[&str]) -> Vec<TestDescAndFn> {
+        names.iter().map(|name| TestDescAndFn {
+            desc: TestDesc {
+                name: DynTestName(name.to_string()),
+                ignore: false,
+                should_panic: ShouldPanic::No,
+                timeout: None,
+            },
+            testfn: DynTestFn(Box::new(move |()| {})),
+        }).collect()
+    }
+
+    #[test]
+    fn shards_partition_tests() {
+        let names: Vec<String> = (0..50).map(|i| format!("mod{}::test{}", i % 7, i)).collect();
+        let names: Vec<&str> = names.iter().map(|s| &**s).collect();
+
+        let mut seen = Vec::new();
+        for index in 1..4 {
+            let opts = TestOpts {
+                shard: Some((index, 3)),
+                ..TestOpts::new()
+            };
+            let shard = filter_tests(&opts, named_tests(&names));
+            // Sharding is stable with respect to other tests being removed
+            let opts = TestOpts {
+                shard: Some((index, 3)),
+                skip: vec!["mod3::".to_string()],
+                ..TestOpts::new()
+            };
+            let smaller = filter_tests(&opts, named_tests(&names));
+            assert!(smaller.iter().all(|t| shard.iter().any(|s| s.desc.name == t.desc.name)));
+
+            seen.extend(shard.into_iter().map(|t| t.desc.name.to_string()));
+        }
+        seen.sort();
+        let mut expected: Vec<String> = names.iter().map(|s| s.to_string()).collect();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn shuffle_is_deterministic() {
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let order = |seed| {
+            let mut tests = named_tests(&names);
+            shuffle_tests(seed, &mut tests);
+            tests.into_iter().map(|t| t.desc.name.to_string()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(order(7), order(7));
+        assert!(order(7) != order(8));
+        let mut sorted = order(7);
+        sorted.sort();
+        assert_eq!(sorted, names.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn run_tests_reports_events() {
+        let mut tests = named_tests(&["fails", "passes"]);
+        tests[0].testfn = DynTestFn(Box::new(move |()| panic!("boom")));
+
+        let mut filtered = Vec::new();
+        let mut results = Vec::new();
+        run_tests(&TestOpts::new(), tests, |event| {
+            match event {
+                TeFiltered(descs) => filtered = descs,
+                TeResult(desc, result, _, _) => results.push((desc.name.to_string(), result)),
+                _ => {}
+            }
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(filtered.len(), 2);
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(results.len(), 2);
+        assert!(results[0].0 == "fails" && results[0].1 == TrFailed);
+        assert!(results[1].0 == "passes" && results[1].1 == TrOk);
+    }
+
+    #[test]
+    fn flaky_test_passes_on_retry() {
+        use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
+        use StaticTestFn;
+        static RUNS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+        fn fails_twice() {
+            if RUNS.fetch_add(1, Ordering::SeqCst) < 2 {
+                panic!("not yet");
+            }
+        }
+        let test = || TestDescAndFn {
+            desc: TestDesc {
+                name: StaticTestName("fails_twice"),
+                ignore: false,
+                should_panic: ShouldPanic::No,
+                timeout: None,
+            },
+            testfn: StaticTestFn(fails_twice),
+        };
+        let mut opts = TestOpts::new();
+        opts.run_tests = true;
+        opts.retries = 1;
+
+        let run = |opts: &TestOpts| {
+            let mut retries = 0;
+            let mut result = None;
+            run_tests(opts, vec![test()], |event| {
+                match event {
+                    TeRetry(..) => retries += 1,
+                    TeResult(_, res, _, _) => result = Some(res),
+                    _ => {}
+                }
+                Ok(())
+            }).unwrap();
+            (retries, result.unwrap())
+        };
+
+        let (retries, result) = run(&opts);
+        assert_eq!(retries, 1);
+        assert!(result == TrFailed);
+
+        RUNS.store(0, Ordering::SeqCst);
+        opts.retries = 2;
+        let (retries, result) = run(&opts);
+        assert_eq!(retries, 2);
+        assert!(result == TrOk);
+    }
+
+    #[test]
+    fn dynamic_tests_are_retried() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use DynRetryableTestFn;
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let test_runs = runs.clone();
+        let retryable = TestDescAndFn {
+            desc: TestDesc {
+                name: StaticTestName("retryable"),
+                ignore: false,
+                should_panic: ShouldPanic::No,
+                timeout: None,
+            },
+            testfn: DynRetryableTestFn(Arc::new(move || {
+                if test_runs.fetch_add(1, Ordering::SeqCst) < 1 {
+                    panic!("not yet");
+                }
+            })),
+        };
+        let once = TestDescAndFn {
+            desc: TestDesc {
+                name: StaticTestName("once"),
+                ignore: false,
+                should_panic: ShouldPanic::No,
+                timeout: None,
+            },
+            testfn: DynTestFn(Box::new(move |()| panic!("never"))),
+        };
+        let mut opts = TestOpts::new();
+        opts.retries = 1;
+        opts.test_threads = Some(1);
+
+        let mut results = Vec::new();
+        run_tests(&opts, vec![retryable, once], |event| {
+            if let TeResult(desc, result, stdout, _) = event {
+                results.push((desc.name.to_string(), result, stdout));
+            }
+            Ok(())
+        }).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(results.len(), 2);
+        assert!(results[0].0 == "once" && results[0].1 == TrFailed);
+        assert!(String::from_utf8_lossy(&results[0].2).contains("was not retried"));
+        assert!(results[1].0 == "retryable" && results[1].1 == TrOk);
+    }
+
+    fn run_results(opts: &TestOpts, tests: Vec<TestDescAndFn>) -> Vec<(String, TestResult)> {
+        let mut results = Vec::new();
+        run_tests(opts, tests, |event| {
+            if let TeResult(desc, result, _, _) = event {
+                results.push((desc.name.to_string(), result));
+            }
+            Ok(())
+        }).unwrap();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results
+    }
+
+    #[test]
+    fn per_test_fixtures_wrap_each_test() {
+        let mut tests = named_tests(&["fails", "passes", "uses_value"]);
+        tests[0].testfn = DynTestFn(Box::new(move |()| panic!("boom")));
+        tests[2].testfn = DynTestFn(Box::new(move |()| {
+            assert_eq!(fixture::<u32>("answer").map(|n| *n), Some(42));
+        }));
+        let mut opts = TestOpts::new();
+        opts.fixtures.each_test.push(TestFixture::new("answer", || 42u32, |_| {}));
+        opts.fixtures.each_test.push(TestFixture::new("flaky_cleanup",
+                                                      || (),
+                                                      |_| panic!("could not clean up")));
+
+        let results = run_results(&opts, tests);
+        let msg = "teardown of fixture `flaky_cleanup` panicked: could not clean up".to_owned();
+        assert!(results[0].1 == TrFailed);
+        assert!(results[1].1 == TrFixtureFailed(msg.clone()));
+        assert!(results[2].1 == TrFixtureFailed(msg));
+    }
+
+    #[test]
+    fn failed_suite_setup_skips_all_tests() {
+        use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+        static TORN_DOWN: AtomicBool = ATOMIC_BOOL_INIT;
+
+        let mut tests = named_tests(&["a", "b"]);
+        tests[0].testfn = DynTestFn(Box::new(move |()| panic!("should not run")));
+        let mut opts = TestOpts::new();
+        opts.fixtures.suite.push(TestFixture::new("first",
+                                                  || (),
+                                                  |_| TORN_DOWN.store(true, Ordering::SeqCst)));
+        opts.fixtures.suite.push(TestFixture::new("server",
+                                                  || -> () { panic!("no port") },
+                                                  |_| {}));
+
+        let results = run_results(&opts, tests);
+        let msg = "setup of fixture `server` panicked: no port".to_owned();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|&(_, ref result)| *result == TrFixtureFailed(msg.clone())));
+        assert!(TORN_DOWN.load(Ordering::SeqCst));
+    }
+
     #[test]
     pub fn filter_for_ignored_option() {
         // When we run ignored tests the test filter should filter out all the
@@ -1740,6 +3493,7 @@ mod tests {
                                  name: StaticTestName("1"),
                                  ignore: true,
                                  should_panic: ShouldPanic::No,
+                                 timeout: None,
                              },
                              testfn: DynTestFn(Box::new(move |()| {})),
                          },
@@ -1748,6 +3502,7 @@ mod tests {
                                  name: StaticTestName("2"),
                                  ignore: false,
                                  should_panic: ShouldPanic::No,
+                                 timeout: None,
                              },
                              testfn: DynTestFn(Box::new(move |()| {})),
                          }];
@@ -1771,6 +3526,7 @@ mod tests {
                     name: StaticTestName(name),
                     ignore: false,
                     should_panic: ShouldPanic::No,
+                    timeout: None,
                 },
                 testfn: DynTestFn(Box::new(move |()| {}))
             })
@@ -1852,6 +3608,7 @@ mod tests {
                         name: DynTestName((*name).clone()),
                         ignore: false,
                         should_panic: ShouldPanic::No,
+                        timeout: None,
                     },
                     testfn: DynTestFn(Box::new(move |()| testfn())),
                 };
@@ -14,7 +14,7 @@
 #![feature(plugin)]
 #![plugin(lint_plugin_test)]
 #![forbid(test_lint)]
-//~^ NOTE lint level defined here
+//~^ NOTE `#[forbid(test_lint)]` set at
 //~| NOTE `forbid` level set here
 
 fn lintme() { } //~ ERROR item is named 'lintme'
@@ -0,0 +1,60 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: -Z borrowck-mir
+
+// Tests that the MIR borrow checker reports accesses which conflict with
+// borrows whose references are used afterwards.
+
+fn mut_and_mut() {
+    let mut x = 1;
+    let a = &mut x;
+    let b = &mut x; //~ ERROR cannot borrow `x` as mutable more than once at a time
+    *a += 1;
+    *b += 1;
+}
+
+fn shared_and_mut() {
+    let mut v = vec![1];
+    let r = &v;
+    v.push(2); //~ ERROR cannot borrow `v` as mutable because it is also borrowed as immutable
+    println!("{:?}", r);
+}
+
+fn use_while_mutably_borrowed() {
+    let mut x = 1;
+    let r = &mut x;
+    let y = x; //~ ERROR cannot use `x` because it was mutably borrowed
+    *r += y;
+}
+
+fn move_while_borrowed() {
+    let s = String::new();
+    let r = &s;
+    let t = s; //~ ERROR cannot move out of `s` because it is borrowed
+    println!("{}", r);
+    drop(t);
+}
+
+fn assign_while_borrowed() {
+    let mut x = 1;
+    let r = &x;
+    x = 2; //~ ERROR cannot assign to `x` because it is borrowed
+    println!("{}", r);
+}
+
+fn assign_needs_drop_while_borrowed() {
+    let mut s = String::new();
+    let r = &s;
+    s = String::from("x"); //~ ERROR cannot assign to `s` because it is borrowed
+    println!("{}", r);
+}
+
+fn main() {}
@@ -27,6 +27,7 @@ fn main() {
     let d = 42u8 - (42u8 + 1);
     //~^ ERROR attempt to subtract with overflow
     let _e = [5u8][1];
+    //~^ ERROR index out of bounds: the len is 1 but the index is 1
     black_box(a);
     black_box(b);
     black_box(c);
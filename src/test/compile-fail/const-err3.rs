@@ -0,0 +1,37 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: -Zforce-overflow-checks=on
+
+// Operations which always panic because of the values of variables.
+
+#![deny(const_err)]
+
+fn black_box<T>(_: T) {
+    unimplemented!()
+}
+
+fn main() {
+    let a = 255u8;
+    let b = a + 1;
+    //~^ ERROR attempt to add with overflow
+    let c = a - 255;
+    let d = 10 / c;
+    //~^ ERROR attempt to divide by zero
+    let arr = [1, 2, 3];
+    let e = arr[a as usize - 250];
+    //~^ ERROR index out of bounds: the len is 3 but the index is 5
+
+    // A variable which is assigned again is not a constant.
+    let mut f = 255u8;
+    f -= 255;
+    let g = f + 1;
+    black_box((b, d, e, g));
+}
@@ -0,0 +1,21 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: --test
+
+#[test]
+#[timeout = "10"] //~ ERROR the `#[timeout]` attribute on tests is an experimental feature
+fn slow() {}
+
+// Only the test harness knows `#[timeout]`, so elsewhere it is just unknown
+#[timeout = "10"] //~ ERROR The attribute `timeout` is currently unknown to the compiler
+fn not_a_test() {}
+
+fn main() {}
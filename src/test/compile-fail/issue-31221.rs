@@ -12,9 +12,9 @@
 #![allow(unused_variables)]
 #![allow(non_snake_case)]
 #![deny(unreachable_patterns)]
-//~^ NOTE lint level defined here
-//~^^ NOTE lint level defined here
-//~^^^ NOTE lint level defined here
+//~^ NOTE `#[deny(unreachable_patterns)]` set at
+//~^^ NOTE `#[deny(unreachable_patterns)]` set at
+//~^^^ NOTE `#[deny(unreachable_patterns)]` set at
 
 #[derive(Clone, Copy)]
 enum Enum {
@@ -9,7 +9,7 @@
 // except according to those terms.
 
 #![deny(bad_style)]
-//~^ NOTE lint level defined here
+//~^ NOTE `#[deny(bad_style)]` set at
 #![allow(dead_code)]
 
 fn CamelCase() {} //~ ERROR function `CamelCase` should have a snake case name
@@ -19,8 +19,8 @@ mod test {
     fn CamelCase() {}
 
     #[forbid(bad_style)]
-    //~^ NOTE lint level defined here
-    //~^^ NOTE lint level defined here
+    //~^ NOTE `#[forbid(bad_style)]` set at
+    //~^^ NOTE `#[forbid(bad_style)]` set at
     mod bad {
         fn CamelCase() {} //~ ERROR function `CamelCase` should have a snake case name
 
@@ -29,8 +29,8 @@ mod test {
 
     mod warn {
         #![warn(bad_style)]
-        //~^ NOTE lint level defined here
-        //~| NOTE lint level defined here
+        //~^ NOTE `#[warn(bad_style)]` set at
+        //~| NOTE `#[warn(bad_style)]` set at
 
         fn CamelCase() {} //~ WARN function `CamelCase` should have a snake case name
 
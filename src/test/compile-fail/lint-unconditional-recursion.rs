@@ -9,20 +9,20 @@
 // except according to those terms.
 
 #![deny(unconditional_recursion)]
-//~^ NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
-//~| NOTE lint level defined here
+//~^ NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
+//~| NOTE `#[deny(unconditional_recursion)]` set at
 #![allow(dead_code)]
 fn foo() { //~ ERROR function cannot return without recurring
     foo(); //~ NOTE recursive call site
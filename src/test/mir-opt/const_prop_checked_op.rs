@@ -0,0 +1,30 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: -Z force-overflow-checks=on
+
+fn test() -> u8 {
+    let x: u32 = 6 * 7;
+    x as u8
+}
+
+fn main() { }
+
+// END RUST SOURCE
+// START rustc.node4.ConstProp.after.mir
+//  bb0: {
+//      assert(!const false, "attempt to multiply with overflow") -> bb1;
+//  }
+//  bb1: {
+//      _1 = const 42u32;
+//      _0 = const 42u8;
+//      return;
+//  }
+// END rustc.node4.ConstProp.after.mir
@@ -0,0 +1,29 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+fn test() -> u32 {
+    let x = 2u32;
+    if x == 2 { 20 } else { 30 }
+}
+
+fn main() { }
+
+// END RUST SOURCE
+// START rustc.node4.ConstProp.after.mir
+//  bb0: {
+//      if(const true) -> [true: bb1, false: bb2];
+//  }
+// END rustc.node4.ConstProp.after.mir
+// START rustc.node4.SimplifyBranches.after-const-prop-after.mir
+//  bb0: {
+//      goto -> bb1;
+//  }
+//  bb1: {
+// END rustc.node4.SimplifyBranches.after-const-prop-after.mir
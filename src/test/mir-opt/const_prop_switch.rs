@@ -0,0 +1,35 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+fn test() -> u32 {
+    let x = 2u8;
+    match x {
+        1 => 10,
+        2 => 20,
+        _ => 30,
+    }
+}
+
+fn main() { }
+
+// END RUST SOURCE
+// START rustc.node4.ConstProp.before.mir
+//  bb0: {
+//      _1 = const 2u8;
+//      switchInt(_1) -> [1u8: bb1, 2u8: bb2, otherwise: bb3];
+//  }
+// END rustc.node4.ConstProp.before.mir
+// START rustc.node4.ConstProp.after.mir
+//  bb0: {
+//      _1 = const 2u8;
+//      goto -> bb2;
+//  }
+//  bb1: {
+// END rustc.node4.ConstProp.after.mir
@@ -0,0 +1,45 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+fn test(x: u32) -> u64 {
+    let a = x as u16;
+    let mut b = a as u64;
+    b = x as u64;
+    b
+}
+
+fn main() { }
+
+// END RUST SOURCE
+// START rustc.node4.DeadStoreElimination.before.mir
+//  bb0: {
+//      _4 = _1;
+//      _3 = _4 as u16 (Misc);
+//      _5 = _3 as u64 (Misc);
+//      _7 = _1;
+//      _5 = _7 as u64 (Misc);
+//      _8 = _5;
+//      _0 = _8;
+//      return;
+//  }
+// END rustc.node4.DeadStoreElimination.before.mir
+// START rustc.node4.SimplifyLocals.after.mir
+//  let mut _0: u64;
+//  let mut _2: u64;
+//  let mut _3: u32;
+//  let mut _4: u64;
+//  bb0: {
+//      _3 = _1;
+//      _2 = _3 as u64 (Misc);
+//      _4 = _2;
+//      _0 = _4;
+//      return;
+//  }
+// END rustc.node4.SimplifyLocals.after.mir
@@ -0,0 +1,35 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+fn test(x: u32) -> u32 {
+    let a = x ^ 1;
+    let b = a ^ a;
+    b ^ b
+}
+
+fn main() { }
+
+// END RUST SOURCE
+// START rustc.node4.DeadStoreElimination.after.mir
+//  bb0: {
+//      StorageLive(_3);
+//      _3 = BitXor(_1, const 1u32);
+//      _6 = _3;
+//      _7 = _3;
+//      StorageDead(_3);
+//      StorageLive(_5);
+//      _5 = BitXor(_6, _7);
+//      _8 = _5;
+//      _9 = _5;
+//      StorageDead(_5);
+//      _0 = BitXor(_8, _9);
+//      return;
+//  }
+// END rustc.node4.DeadStoreElimination.after.mir
@@ -0,0 +1,41 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+fn test(x: u32) -> u32 {
+    flip(x)
+}
+
+#[inline]
+fn flip(x: u32) -> u32 {
+    x ^ 1
+}
+
+fn main() { }
+
+// END RUST SOURCE
+// START rustc.node4.Inline.after.mir
+//  bb0: {
+//      _2 = _1;
+//      _4 = _2;
+//      goto -> bb2;
+//  }
+//  bb1: {
+//      return;
+//  }
+//  bb2: {
+//      _5 = _4;
+//      _3 = BitXor(_5, const 1u32);
+//      goto -> bb3;
+//  }
+//  bb3: {
+//      _0 = _3;
+//      goto -> bb1;
+//  }
+// END rustc.node4.Inline.after.mir
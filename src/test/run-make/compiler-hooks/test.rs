@@ -0,0 +1,116 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Test that the hooks within the analysis passes are called in order, and
+// that they can stop compilation.
+
+#![feature(rustc_private, box_syntax)]
+
+extern crate rustc;
+extern crate rustc_driver;
+extern crate syntax;
+extern crate getopts;
+
+use rustc_driver::{CompilerCalls, Compilation};
+use rustc_driver::driver::{CompileController, MirPassState};
+use rustc::session::Session;
+use syntax::codemap::FileLoader;
+use std::cell::RefCell;
+use std::env;
+use std::io;
+use std::path::{PathBuf, Path};
+use std::rc::Rc;
+
+struct SourceLoader;
+
+impl FileLoader for SourceLoader {
+    fn file_exists(&self, _: &Path) -> bool { true }
+    fn abs_path(&self, _: &Path) -> Option<PathBuf> { None }
+    fn read_file(&self, _: &Path) -> io::Result<String> {
+        Ok(r#"
+pub fn add(a: i32, b: i32) -> i32 { a + b }
+pub const ANSWER: i32 = 42;
+"#.to_string())
+    }
+}
+
+struct HookCalls {
+    events: Rc<RefCell<Vec<String>>>,
+}
+
+impl<'a> CompilerCalls<'a> for HookCalls {
+    fn build_controller(&mut self,
+                        _: &Session,
+                        _: &getopts::Matches)
+                        -> CompileController<'a> {
+        let mut cc = CompileController::basic();
+
+        let events = self.events.clone();
+        cc.after_typeck.callback = box move |state| {
+            events.borrow_mut().push(format!("typeck {}", state.crate_name));
+            Compilation::Continue
+        };
+        let events = self.events.clone();
+        cc.after_mir_construction.callback = box move |state| {
+            let count = state.tcx.mir_map.borrow().keys().len();
+            events.borrow_mut().push(format!("mir {}", count));
+            Compilation::Continue
+        };
+        let events = self.events.clone();
+        let after_mir_pass: Box<Fn(&MirPassState) -> Compilation> = box move |state| {
+            let event = format!("pass {}", state.pass_name);
+            let mut events = events.borrow_mut();
+            if events.last() != Some(&event) {
+                events.push(event);
+            }
+            if state.pass_name == "QualifyAndPromoteConstants" {
+                Compilation::Stop
+            } else {
+                Compilation::Continue
+            }
+        };
+        cc.after_mir_pass = Some(after_mir_pass);
+        let events = self.events.clone();
+        cc.after_analysis.callback = box move |_| {
+            events.borrow_mut().push("analysis".to_string());
+        };
+        cc
+    }
+}
+
+fn main() {
+    let mut path = match std::env::args().nth(2) {
+        Some(path) => PathBuf::from(&path),
+        None => panic!("missing rustc path")
+    };
+
+    // Remove two segments from rustc path to get sysroot.
+    path.pop();
+    path.pop();
+
+    let mut args: Vec<String> =
+        format!("_ _ --sysroot {} --crate-type lib --crate-name hooked", path.to_str().unwrap())
+        .split(' ').map(|s| s.to_string()).collect();
+    args.push("--out-dir".to_string());
+    args.push(env::var("TMPDIR").unwrap());
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let mut calls = HookCalls { events: events.clone() };
+    let (result, _) = rustc_driver::run_compiler(&args, &mut calls, Some(box SourceLoader), None);
+
+    // Compilation was stopped without any errors
+    assert_eq!(result, Err(0));
+    assert_eq!(*events.borrow(),
+               vec!["typeck hooked",
+                    "mir 2",
+                    "pass SimplifyCfg-initial",
+                    "pass QualifyAndPromoteConstants",
+                    "analysis"]);
+}
@@ -0,0 +1,30 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![feature(libc)]
+
+extern crate libc;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn sleeps_forever() {
+    let pid = unsafe { libc::getpid() };
+    let mut file = File::create(env::var("PID_FILE").unwrap()).unwrap();
+    write!(file, "{}", pid).unwrap();
+    drop(file);
+    loop {
+        thread::sleep(Duration::from_secs(1000));
+    }
+}
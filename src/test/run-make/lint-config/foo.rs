@@ -0,0 +1,27 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+fn unused_at_root() {}
+
+mod net {
+    fn unused_in_net() {}
+
+    pub mod http {
+        fn unused_in_http() {}
+
+        #[allow(dead_code)]
+        fn allowed_in_http() {}
+    }
+}
+
+fn main() {
+    let Unused = 1;
+}
@@ -0,0 +1,38 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: -Z borrowck-mir
+
+// Tests that under the MIR borrow checker a borrow ends once the reference
+// it made is no longer used, rather than at the end of its lexical scope.
+
+fn push_after_last_use() {
+    let mut v = vec![1];
+    let r = &mut v;
+    r.push(2);
+    v.push(3);
+    assert_eq!(v, [1, 2, 3]);
+}
+
+fn reassign_reference() {
+    let mut a = 1;
+    let mut b = 2;
+    let mut r = &mut a;
+    *r += 1;
+    r = &mut b;
+    a += 1;
+    *r += 1;
+    assert_eq!((a, b), (3, 3));
+}
+
+fn main() {
+    push_after_last_use();
+    reassign_reference();
+}
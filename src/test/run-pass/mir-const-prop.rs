@@ -0,0 +1,96 @@
+// Copyright 2017 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// compile-flags: -Z mir-opt-level=2 -Z force-overflow-checks=on
+
+// Test that constant propagation computes the same values as the
+// operations it replaces. The values are kept in variables, which the
+// constant evaluation of the HIR does not look at.
+
+#[inline(never)]
+fn index(a: [u32; 3]) -> u32 {
+    a[2]
+}
+
+#[inline(never)]
+fn checked_mul() -> u8 {
+    let x: u32 = 6 * 7;
+    x as u8
+}
+
+#[inline(never)]
+fn arithmetic() -> (i32, i32, i32, u64, i32) {
+    let x = -7i32;
+    let y = 1u64;
+    let z = -16i32;
+    (x / 2, x % 3, x - 2, y << 40, z >> 2)
+}
+
+#[inline(never)]
+fn casts() -> (u8, u8, u64, u32, i32, i8) {
+    let a = 300u32;
+    let b = -1i8;
+    let c = -1i32;
+    let d = 'a';
+    let e = true;
+    let f = 200u8;
+    (a as u8, b as u8, c as u64, d as u32, e as i32, f as i8)
+}
+
+#[inline(never)]
+fn unary() -> (u8, i64, bool) {
+    let x = 0b1010u8;
+    let y = 5i64;
+    let z = false;
+    (!x, -y, !z)
+}
+
+#[inline(never)]
+fn comparisons() -> (bool, bool, bool, bool) {
+    let x = 3u8;
+    let y = -3i8;
+    let a = true;
+    let b = false;
+    (x < 2, y < 2, a > b, a ^ b)
+}
+
+#[inline(never)]
+fn switch(taken: bool) -> u32 {
+    let x = 2u8;
+    let y = 5u8;
+    let z = if taken { x } else { y };
+    match x {
+        1 => 10,
+        2 => match y {
+            1 => 11,
+            2 => 12,
+            _ => 20 + z as u32,
+        },
+        _ => 30,
+    }
+}
+
+#[inline(never)]
+fn if_else() -> u32 {
+    let x = 2u32;
+    if x == 2 { 20 } else { 30 }
+}
+
+fn main() {
+    assert_eq!(index([1, 2, 3]), 3);
+    assert_eq!(checked_mul(), 42);
+    assert_eq!(arithmetic(), (-3, -1, -9, 1 << 40, -4));
+    assert_eq!(casts(), (44, 255, !0, 97, 1, -56));
+    assert_eq!(unary(), (0b11110101, -5, true));
+    assert_eq!(comparisons(), (false, true, true, true));
+    assert_eq!(switch(true), 22);
+    assert_eq!(switch(false), 25);
+    assert_eq!(if_else(), 20);
+}
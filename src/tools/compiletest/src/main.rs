@@ -39,6 +39,7 @@ use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
 use getopts::{optopt, optflag, reqopt};
 use common::Config;
 use common::{Pretty, DebugInfoGdb, DebugInfoLldb, Mode};
@@ -330,23 +331,18 @@ pub fn run_tests(config: &Config) {
 }
 
 pub fn test_opts(config: &Config) -> test::TestOpts {
-    test::TestOpts {
-        filter: config.filter.clone(),
-        filter_exact: config.filter_exact,
-        run_ignored: config.run_ignored,
-        quiet: config.quiet,
-        logfile: config.logfile.clone(),
-        run_tests: true,
-        bench_benchmarks: true,
-        nocapture: match env::var("RUST_TEST_NOCAPTURE") {
-            Ok(val) => &val != "0",
-            Err(_) => false
-        },
-        color: test::AutoColor,
-        test_threads: None,
-        skip: vec![],
-        list: false,
-    }
+    let mut opts = test::TestOpts::new();
+    opts.filter = config.filter.clone();
+    opts.filter_exact = config.filter_exact;
+    opts.run_ignored = config.run_ignored;
+    opts.quiet = config.quiet;
+    opts.logfile = config.logfile.clone();
+    opts.bench_benchmarks = true;
+    opts.nocapture = match env::var("RUST_TEST_NOCAPTURE") {
+        Ok(val) => &val != "0",
+        Err(_) => false
+    };
+    opts
 }
 
 pub fn make_tests(config: &Config) -> Vec<test::TestDescAndFn> {
@@ -475,6 +471,7 @@ pub fn make_test(config: &Config, testpaths: &TestPaths) -> test::TestDescAndFn
             name: make_test_name(config, testpaths),
             ignore: ignore,
             should_panic: should_panic,
+            timeout: None,
         },
         testfn: make_test_closure(config, testpaths),
     }
@@ -494,8 +491,8 @@ pub fn make_test_name(config: &Config, testpaths: &TestPaths) -> test::TestName
 pub fn make_test_closure(config: &Config, testpaths: &TestPaths) -> test::TestFn {
     let config = config.clone();
     let testpaths = testpaths.clone();
-    test::DynTestFn(Box::new(move |()| {
-        runtest::run(config, &testpaths)
+    test::DynRetryableTestFn(Arc::new(move || {
+        runtest::run(config.clone(), &testpaths)
     }))
 }
 